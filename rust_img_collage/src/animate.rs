@@ -0,0 +1,130 @@
+//! Animated PNG output (`--animate`).
+//!
+//! APNG keeps full colour and alpha where GIF cannot, and any plain PNG
+//! viewer still shows the first frame. Two modes:
+//!
+//! - `pages` cycles the --paginate pages as the frames of one looping
+//!   animation at the output path (the numbered page files still land
+//!   next to it). Short last pages are padded onto the background fill.
+//! - `build-up` replays the grid composite: the first frame is the bare
+//!   background and every later frame reveals one cell, stored as a
+//!   delta frame of just that cell's pixels, so the file stays close to
+//!   the plain PNG in size.
+
+use crate::error::{self, Error};
+use std::fs::File;
+use std::io::BufWriter;
+
+/// How long each frame is held, in milliseconds.
+const FRAME_DELAY_MS: u16 = 800;
+
+/// Starts an animated RGBA encoder for `frames` frames looping forever.
+fn encoder(
+    output_path: &str,
+    (width, height): (u32, u32),
+    frames: u32,
+) -> error::Result<png::Encoder<'static, BufWriter<File>>> {
+    let file = File::create(output_path).map_err(|e| Error::output(output_path, e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .set_animated(frames, 0)
+        .and_then(|()| encoder.set_frame_delay(FRAME_DELAY_MS, 1000))
+        .map_err(|e| Error::output(output_path, e))?;
+    Ok(encoder)
+}
+
+/// Writes the rendered pages as the frames of one APNG at `output_path`.
+pub fn write_page_cycle(page_paths: &[String], output_path: &str) -> error::Result<()> {
+    let mut pages = Vec::with_capacity(page_paths.len());
+    for path in page_paths {
+        pages.push(image::open(path)?.to_rgba8());
+    }
+    // Every frame shares the canvas of the largest page; a short last
+    // page sits top-left on the background fill, where its grid started.
+    let width = pages.iter().map(|p| p.width()).max().unwrap_or(1);
+    let height = pages.iter().map(|p| p.height()).max().unwrap_or(1);
+
+    let encoder = encoder(output_path, (width, height), pages.len() as u32)?;
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| Error::output(output_path, e))?;
+    for page in &pages {
+        let frame = if page.dimensions() == (width, height) {
+            std::borrow::Cow::Borrowed(page.as_raw())
+        } else {
+            let mut padded = vec![0u8; (width as u64 * height as u64 * 4) as usize];
+            crate::background::fill(&mut padded, (width, height));
+            for (y, row) in page.as_raw().chunks_exact(page.width() as usize * 4).enumerate() {
+                let start = y * width as usize * 4;
+                padded[start..start + row.len()].copy_from_slice(row);
+            }
+            std::borrow::Cow::Owned(padded)
+        };
+        writer
+            .write_image_data(&frame)
+            .map_err(|e| Error::output(output_path, e))?;
+    }
+    writer.finish().map_err(|e| Error::output(output_path, e))?;
+    tracing::info!(
+        "Page-cycle APNG saved to '{}' ({} frames)",
+        output_path,
+        page_paths.len()
+    );
+    Ok(())
+}
+
+/// Writes the composited grid as a build-up APNG: background first, then
+/// one delta frame per cell rectangle in paste order.
+pub fn write_build_up(
+    canvas: &[u8],
+    (width, height): (u32, u32),
+    cells: &[(u32, u32, u32, u32)],
+    output_path: &str,
+) -> error::Result<()> {
+    // Clamp to the canvas up front: the declared frame count must match
+    // the frames actually written.
+    let cells: Vec<(u32, u32, u32, u32)> = cells
+        .iter()
+        .map(|&(x, y, w, h)| (x, y, w.min(width.saturating_sub(x)), h.min(height.saturating_sub(y))))
+        .filter(|&(_, _, w, h)| w > 0 && h > 0)
+        .collect();
+
+    let encoder = encoder(output_path, (width, height), cells.len() as u32 + 1)?;
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| Error::output(output_path, e))?;
+    let mut background = vec![0u8; canvas.len()];
+    crate::background::fill(&mut background, (width, height));
+    writer
+        .write_image_data(&background)
+        .map_err(|e| Error::output(output_path, e))?;
+    // Each later frame replaces just its own cell; everything revealed
+    // so far stays put.
+    writer
+        .set_dispose_op(png::DisposeOp::None)
+        .and_then(|()| writer.set_blend_op(png::BlendOp::Source))
+        .map_err(|e| Error::output(output_path, e))?;
+    for (x, y, w, h) in cells.iter().copied() {
+        writer
+            .set_frame_position(x, y)
+            .and_then(|()| writer.set_frame_dimension(w, h))
+            .map_err(|e| Error::output(output_path, e))?;
+        let mut region = Vec::with_capacity((w as u64 * h as u64 * 4) as usize);
+        for row in y..y + h {
+            let start = ((row as u64 * width as u64 + x as u64) * 4) as usize;
+            region.extend_from_slice(&canvas[start..start + (w * 4) as usize]);
+        }
+        writer
+            .write_image_data(&region)
+            .map_err(|e| Error::output(output_path, e))?;
+    }
+    writer.finish().map_err(|e| Error::output(output_path, e))?;
+    tracing::info!(
+        "Build-up APNG saved to '{}' ({} frames)",
+        output_path,
+        cells.len() + 1
+    );
+    Ok(())
+}
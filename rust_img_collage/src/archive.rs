@@ -0,0 +1,125 @@
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Returns true if the path looks like a supported archive (.zip, .tar,
+/// .tar.gz, .tgz).
+pub fn is_archive(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    name.ends_with(".zip")
+        || name.ends_with(".tar")
+        || name.ends_with(".tar.gz")
+        || name.ends_with(".tgz")
+}
+
+/// Returns true if the entry name has a supported image extension (the
+/// same set the directory scanner accepts, so the lists cannot drift).
+fn is_image_name(name: &str) -> bool {
+    let ext = Path::new(name)
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    crate::scanner::accepted_extension(&ext)
+}
+
+/// Reads all image entries out of an archive, sorted by entry name.
+/// The image bytes are kept in memory on each returned entry, so the
+/// archive never needs to be unpacked to disk.
+pub fn load_archive_entries(path: &Path) -> error::Result<Vec<ManifestEntry>> {
+    let name = path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    let mut entries = if name.ends_with(".zip") {
+        load_zip_entries(path)?
+    } else {
+        load_tar_entries(path, name.ends_with(".tar.gz") || name.ends_with(".tgz"))?
+    };
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+fn load_zip_entries(path: &Path) -> error::Result<Vec<ManifestEntry>> {
+    let file = File::open(path)?;
+    let mut zip = zip::ZipArchive::new(file)
+        .map_err(|e| Error::Usage(format!("invalid archive {:?}: {}", path, e)))?;
+
+    let mut entries = Vec::new();
+    for i in 0..zip.len() {
+        let mut entry = match zip.by_index(i) {
+            Ok(e) => e,
+            Err(e) => {
+                tracing::warn!("Error reading zip entry {}: {}", i, e);
+                continue;
+            }
+        };
+        if !entry.is_file() || !is_image_name(entry.name()) {
+            continue;
+        }
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        if let Err(e) = entry.read_to_end(&mut data) {
+            tracing::warn!("Error reading zip entry {:?}: {}", entry.name(), e);
+            continue;
+        }
+        let mut item = ManifestEntry::from_path(PathBuf::from(entry.name()));
+        item.data = Some(data);
+        entries.push(item);
+    }
+    Ok(entries)
+}
+
+fn load_tar_entries(path: &Path, gzipped: bool) -> error::Result<Vec<ManifestEntry>> {
+    let file = File::open(path)?;
+    let reader: Box<dyn Read> = if gzipped {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    let mut tar = tar::Archive::new(reader);
+
+    let mut entries = Vec::new();
+    let archive_entries = tar
+        .entries()
+        .map_err(|e| Error::Usage(format!("invalid archive {:?}: {}", path, e)))?;
+    for entry in archive_entries {
+        let mut entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                tracing::warn!("Error reading tar entry: {}", e);
+                continue;
+            }
+        };
+        if entry.header().entry_type() != tar::EntryType::Regular {
+            continue;
+        }
+        let entry_path = match entry.path() {
+            Ok(p) => p.into_owned(),
+            Err(_) => continue,
+        };
+        let Some(name) = entry_path.to_str() else {
+            continue;
+        };
+        if !is_image_name(name) {
+            continue;
+        }
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        if let Err(e) = entry.read_to_end(&mut data) {
+            tracing::warn!("Error reading tar entry {:?}: {}", entry_path, e);
+            continue;
+        }
+        let mut item = ManifestEntry::from_path(entry_path);
+        item.data = Some(data);
+        entries.push(item);
+    }
+    Ok(entries)
+}
@@ -0,0 +1,77 @@
+//! Tokio-friendly entry point (feature `async`).
+//!
+//! The build itself stays synchronous — decoding and encoding are CPU
+//! work — but it runs on tokio's blocking pool, and progress comes back
+//! as an async `Stream`, so a web service can embed collage generation
+//! without stalling its runtime:
+//!
+//! ```ignore
+//! let (handle, mut events) = async_api::build_collage(entries, args, output);
+//! while let Some(event) = events.next().await { /* update UI */ }
+//! handle.await.unwrap()?;
+//! ```
+
+// The binary itself never calls this surface; it exists for embedders
+// who compile with the feature enabled.
+#![allow(dead_code)]
+
+use crate::error;
+use crate::manifest::ManifestEntry;
+use crate::progress::{self, ProgressObserver};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+/// An owned [`progress::Event`], sendable across the channel.
+#[derive(Debug, Clone)]
+pub enum Event {
+    Scanned(usize),
+    ImageDone {
+        index: usize,
+        total: usize,
+        path: PathBuf,
+        seconds: f64,
+    },
+    Encoding,
+}
+
+/// Forwards build progress into the event channel; send failures just
+/// mean the consumer dropped the stream, which is fine.
+struct ChannelObserver(tokio::sync::mpsc::UnboundedSender<Event>);
+
+impl ProgressObserver for ChannelObserver {
+    fn on_event(&self, event: progress::Event<'_>) {
+        let owned = match event {
+            progress::Event::Scanned(total) => Event::Scanned(total),
+            progress::Event::ImageDone { index, total, path, seconds } => Event::ImageDone {
+                index,
+                total,
+                path: path.to_path_buf(),
+                seconds,
+            },
+            progress::Event::Encoding => Event::Encoding,
+        };
+        let _ = self.0.send(owned);
+    }
+}
+
+/// Starts a grid build on tokio's blocking pool. Returns the join
+/// handle with the build result and a stream of progress events; the
+/// stream ends when the build finishes. Must be called inside a tokio
+/// runtime.
+pub fn build_collage(
+    entries: Vec<ManifestEntry>,
+    args: crate::Args,
+    output_path: String,
+) -> (
+    tokio::task::JoinHandle<error::Result<()>>,
+    UnboundedReceiverStream<Event>,
+) {
+    let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+    let handle = tokio::task::spawn_blocking(move || {
+        progress::set(Arc::new(ChannelObserver(sender)));
+        let mut run = crate::summary::RunSummary::default();
+        crate::create_collage(&entries, &args, &output_path, &mut run, 0)
+    });
+    (handle, UnboundedReceiverStream::new(receiver))
+}
@@ -0,0 +1,185 @@
+//! Texture atlas output (`--atlas`).
+//!
+//! Bin-packs the inputs at native size (optionally trimming transparent
+//! borders), composites them onto one sheet (optionally padded up to a
+//! power-of-two canvas), and writes a TexturePacker/libGDX-compatible
+//! JSON-hash descriptor that game engines can load directly.
+
+use crate::manifest::ManifestEntry;
+use image::{ImageBuffer, Rgba, RgbaImage};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+/// One packed frame with its trim bookkeeping.
+struct Frame {
+    name: String,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+    /// Offset of the trimmed rect inside the original image.
+    trim_x: u32,
+    trim_y: u32,
+    /// Original (untrimmed) size.
+    source_w: u32,
+    source_h: u32,
+}
+
+/// Finds the opaque bounding box of an image (alpha > 0). Returns
+/// (x, y, w, h); fully transparent images keep a 1x1 rect.
+fn opaque_bounds(img: &RgbaImage) -> (u32, u32, u32, u32) {
+    let (w, h) = img.dimensions();
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (w, h, 0u32, 0u32);
+    for (x, y, pixel) in img.enumerate_pixels() {
+        if pixel[3] > 0 {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+    }
+    if min_x > max_x {
+        (0, 0, 1.min(w), 1.min(h))
+    } else {
+        (min_x, min_y, max_x - min_x + 1, max_y - min_y + 1)
+    }
+}
+
+/// Decoded input ready for packing: name, pixels, and trim bounds.
+type TrimmedImage = (String, RgbaImage, (u32, u32, u32, u32));
+
+/// Rounds up to the next power of two.
+fn next_pot(n: u32) -> u32 {
+    n.max(1).next_power_of_two()
+}
+
+/// Builds the atlas sheet and JSON descriptor.
+pub fn create_atlas(
+    entries: &[ManifestEntry],
+    output_path: &str,
+    json_path: Option<&Path>,
+    power_of_two: bool,
+    trim: bool,
+) -> crate::error::Result<()> {
+    // Decode and (optionally) trim everything up front.
+    let mut images: Vec<TrimmedImage> = Vec::new();
+    for entry in entries {
+        let img = match entry.load_image() {
+            Ok(img) => img.to_rgba8(),
+            Err(e) => {
+                tracing::warn!("Error processing {:?}: {}", entry.path, e);
+                continue;
+            }
+        };
+        let name = entry
+            .path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("frame")
+            .to_string();
+        let bounds = if trim {
+            opaque_bounds(&img)
+        } else {
+            (0, 0, img.width(), img.height())
+        };
+        images.push((name, img, bounds));
+    }
+    if images.is_empty() {
+        tracing::warn!("No images found!");
+        return Ok(());
+    }
+
+    // Pack tallest-first on shelves for reasonable density, like most
+    // atlas tools do.
+    let mut order: Vec<usize> = (0..images.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(images[i].2 .3));
+
+    let total_area: u64 = images.iter().map(|(_, _, b)| (b.2 * b.3) as u64).sum();
+    let max_w = images.iter().map(|(_, _, b)| b.2).max().unwrap();
+    let target_width = std::cmp::max((total_area as f64).sqrt().ceil() as u32, max_w);
+    let target_width = if power_of_two { next_pot(target_width) } else { target_width };
+
+    let mut frames: Vec<Frame> = Vec::with_capacity(images.len());
+    let (mut pen_x, mut pen_y, mut row_h, mut sheet_w) = (0u32, 0u32, 0u32, 0u32);
+    for &i in &order {
+        let (name, img, (bx, by, bw, bh)) = &images[i];
+        if pen_x + bw > target_width {
+            pen_y += row_h;
+            pen_x = 0;
+            row_h = 0;
+        }
+        frames.push(Frame {
+            name: name.clone(),
+            x: pen_x,
+            y: pen_y,
+            w: *bw,
+            h: *bh,
+            trim_x: *bx,
+            trim_y: *by,
+            source_w: img.width(),
+            source_h: img.height(),
+        });
+        pen_x += bw;
+        row_h = std::cmp::max(row_h, *bh);
+        sheet_w = std::cmp::max(sheet_w, pen_x);
+    }
+    let sheet_h = pen_y + row_h;
+    let (canvas_w, canvas_h) = if power_of_two {
+        (next_pot(sheet_w), next_pot(sheet_h))
+    } else {
+        (sheet_w, sheet_h)
+    };
+
+    // Composite the sheet by blitting each trimmed rect.
+    let mut sheet = ImageBuffer::<Rgba<u8>, Vec<u8>>::new(canvas_w, canvas_h);
+    for (frame, &i) in frames.iter().zip(order.iter()) {
+        let img = &images[i].1;
+        let view = image::imageops::crop_imm(img, frame.trim_x, frame.trim_y, frame.w, frame.h);
+        image::imageops::overlay(&mut sheet, &view.to_image(), frame.x as i64, frame.y as i64);
+    }
+    sheet.save(output_path)?;
+    tracing::info!("Atlas saved to '{}'", output_path);
+
+    // TexturePacker JSON-hash descriptor.
+    let sheet_file = Path::new(output_path)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or(output_path);
+    let mut frame_map = serde_json::Map::new();
+    for frame in &frames {
+        let trimmed = frame.w != frame.source_w || frame.h != frame.source_h;
+        frame_map.insert(
+            frame.name.clone(),
+            json!({
+                "frame": { "x": frame.x, "y": frame.y, "w": frame.w, "h": frame.h },
+                "rotated": false,
+                "trimmed": trimmed,
+                "spriteSourceSize": { "x": frame.trim_x, "y": frame.trim_y, "w": frame.w, "h": frame.h },
+                "sourceSize": { "w": frame.source_w, "h": frame.source_h },
+            }),
+        );
+    }
+    let descriptor = json!({
+        "frames": frame_map,
+        "meta": {
+            "app": "rust_img_collage",
+            "image": sheet_file,
+            "format": "RGBA8888",
+            "size": { "w": canvas_w, "h": canvas_h },
+            "scale": "1",
+        },
+    });
+
+    let default_json = match output_path.rsplit_once('.') {
+        Some((stem, _)) => format!("{}.json", stem),
+        None => format!("{}.json", output_path),
+    };
+    let json_path = json_path
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| default_json.into());
+    fs::write(&json_path, serde_json::to_string_pretty(&descriptor).unwrap())
+        .map_err(|e| crate::error::Error::output(&json_path.to_string_lossy(), e))?;
+    tracing::info!("Atlas descriptor saved to {:?}", json_path);
+    Ok(())
+}
@@ -0,0 +1,174 @@
+//! Canvas backgrounds (`--background`): a flat colour or a linear/radial
+//! gradient, painted into the memory map before any cell is composited.
+//! Unset, the canvas keeps the traditional "transparent white" (RGB 255,
+//! alpha 0) that encodes cleanly to WebP.
+
+use crate::error::{self, Error};
+
+/// A parsed background specification.
+enum Background {
+    /// The default "transparent white" (RGB 255, alpha 0).
+    Transparent,
+    /// One flat colour.
+    Solid([u8; 4]),
+    /// Gradient along an axis at the given angle in degrees, from the
+    /// first colour to the second.
+    Linear(f64, [u8; 4], [u8; 4]),
+    /// Centre-out gradient from the first colour to the second.
+    Radial([u8; 4], [u8; 4]),
+    /// A texture image, scaled to cover the canvas or tiled at its
+    /// native size (--background-image / --background-mode).
+    Image(image::DynamicImage, crate::BackgroundMode),
+}
+
+static BACKGROUND: std::sync::OnceLock<Background> = std::sync::OnceLock::new();
+
+/// Parses `#rgb` or `#rrggbb` into an opaque RGBA colour.
+pub fn parse_color(text: &str) -> Option<[u8; 4]> {
+    let hex = text.strip_prefix('#')?;
+    match hex.len() {
+        3 => {
+            let mut color = [0u8, 0, 0, 255];
+            for (slot, ch) in color.iter_mut().zip(hex.chars()) {
+                let nibble = ch.to_digit(16)? as u8;
+                *slot = nibble << 4 | nibble;
+            }
+            Some(color)
+        }
+        6 => {
+            let mut color = [0u8, 0, 0, 255];
+            for (slot, pair) in color.iter_mut().zip(hex.as_bytes().chunks(2)) {
+                *slot = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+            }
+            Some(color)
+        }
+        _ => None,
+    }
+}
+
+/// Parses and installs the --background spec; call once at startup.
+pub fn configure(spec: &str) -> error::Result<()> {
+    let bad = || {
+        Error::Usage(format!(
+            "invalid --background {:?}; expected #rrggbb, linear(45deg,#111,#333) or radial(#111,#333)",
+            spec
+        ))
+    };
+    let background = if let Some(inner) = spec.strip_prefix("linear(").and_then(|s| s.strip_suffix(')')) {
+        let parts: Vec<&str> = inner.split(',').map(str::trim).collect();
+        if parts.len() != 3 {
+            return Err(bad());
+        }
+        let degrees: f64 = parts[0]
+            .strip_suffix("deg")
+            .ok_or_else(bad)?
+            .trim()
+            .parse()
+            .map_err(|_| bad())?;
+        Background::Linear(
+            degrees,
+            parse_color(parts[1]).ok_or_else(bad)?,
+            parse_color(parts[2]).ok_or_else(bad)?,
+        )
+    } else if let Some(inner) = spec.strip_prefix("radial(").and_then(|s| s.strip_suffix(')')) {
+        let parts: Vec<&str> = inner.split(',').map(str::trim).collect();
+        if parts.len() != 2 {
+            return Err(bad());
+        }
+        Background::Radial(
+            parse_color(parts[0]).ok_or_else(bad)?,
+            parse_color(parts[1]).ok_or_else(bad)?,
+        )
+    } else {
+        Background::Solid(parse_color(spec).ok_or_else(bad)?)
+    };
+    let _ = BACKGROUND.set(background);
+    Ok(())
+}
+
+/// Loads and installs the --background-image texture; call once at
+/// startup.
+pub fn configure_image(path: &std::path::Path, mode: crate::BackgroundMode) -> error::Result<()> {
+    let img = image::open(path).map_err(|e| {
+        Error::Usage(format!("cannot read --background-image {:?}: {}", path, e))
+    })?;
+    let _ = BACKGROUND.set(Background::Image(img, mode));
+    Ok(())
+}
+
+/// Linear interpolation between two colours.
+fn lerp(from: [u8; 4], to: [u8; 4], t: f64) -> [u8; 4] {
+    let mut out = [0u8; 4];
+    for (slot, (&a, &b)) in out.iter_mut().zip(from.iter().zip(&to)) {
+        *slot = (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+    }
+    out
+}
+
+/// Paints the configured background across the canvas.
+pub fn fill(buf: &mut [u8], (width, height): (u32, u32)) {
+    match BACKGROUND.get().unwrap_or(&Background::Transparent) {
+        Background::Transparent => {
+            for pixel in buf.chunks_exact_mut(4) {
+                pixel.copy_from_slice(&[255, 255, 255, 0]);
+            }
+        }
+        Background::Solid(color) => {
+            for pixel in buf.chunks_exact_mut(4) {
+                pixel.copy_from_slice(color);
+            }
+        }
+        Background::Linear(degrees, from, to) => {
+            // Project each pixel onto the gradient axis and normalise by
+            // the canvas extent along it.
+            let (dy, dx) = degrees.to_radians().sin_cos();
+            let extent = (width as f64 * dx.abs() + height as f64 * dy.abs()).max(1.0);
+            let min_proj = (width as f64 * dx).min(0.0) + (height as f64 * dy).min(0.0);
+            for y in 0..height {
+                for x in 0..width {
+                    let proj = (x as f64 + 0.5) * dx + (y as f64 + 0.5) * dy;
+                    let t = ((proj - min_proj) / extent).clamp(0.0, 1.0);
+                    let index = ((y as u64 * width as u64 + x as u64) * 4) as usize;
+                    buf[index..index + 4].copy_from_slice(&lerp(*from, *to, t));
+                }
+            }
+        }
+        Background::Image(img, crate::BackgroundMode::Cover) => {
+            let covered = img
+                .resize_to_fill(width, height, image::imageops::FilterType::Lanczos3)
+                .to_rgba8();
+            for (y, row) in covered.rows().enumerate() {
+                for (x, pixel) in row.enumerate() {
+                    let index = ((y as u64 * width as u64 + x as u64) * 4) as usize;
+                    buf[index..index + 4].copy_from_slice(&pixel.0);
+                }
+            }
+        }
+        Background::Image(img, crate::BackgroundMode::Tile) => {
+            let tile = img.to_rgba8();
+            let (tw, th) = tile.dimensions();
+            for y in 0..height {
+                for x in 0..width {
+                    let pixel = tile.get_pixel(x % tw, y % th);
+                    let index = ((y as u64 * width as u64 + x as u64) * 4) as usize;
+                    buf[index..index + 4].copy_from_slice(&pixel.0);
+                }
+            }
+        }
+        Background::Radial(from, to) => {
+            let center_x = width as f64 / 2.0;
+            let center_y = height as f64 / 2.0;
+            let max_dist = (center_x.powi(2) + center_y.powi(2)).sqrt().max(1.0);
+            for y in 0..height {
+                for x in 0..width {
+                    let dist = ((x as f64 + 0.5 - center_x).powi(2)
+                        + (y as f64 + 0.5 - center_y).powi(2))
+                    .sqrt();
+                    let t = (dist / max_dist).clamp(0.0, 1.0);
+                    let index = ((y as u64 * width as u64 + x as u64) * 4) as usize;
+                    buf[index..index + 4].copy_from_slice(&lerp(*from, *to, t));
+                }
+            }
+        }
+    }
+}
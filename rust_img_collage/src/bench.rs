@@ -0,0 +1,154 @@
+//! Built-in pipeline benchmark (`bench` subcommand).
+//!
+//! Times the pipeline phase by phase — decode, resize (per filter),
+//! composite, encode — on either a sample directory or synthetic
+//! gradient images, and prints a short report with throughput numbers
+//! so users can tune --cell-size and --threads for their hardware.
+//! The report goes to stdout; logging stays on stderr as usual.
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use crate::summary::RunSummary;
+use image::imageops::FilterType;
+use std::path::PathBuf;
+
+/// Synthetic source image size; large enough that decode and resize
+/// dominate, like typical camera output scaled down.
+const SYNTH_W: u32 = 1024;
+const SYNTH_H: u32 = 768;
+
+/// Builds `count` synthetic PNG entries: a diagonal gradient with a
+/// per-image tint, kept in memory like archive entries.
+fn synthetic_entries(count: usize) -> error::Result<Vec<ManifestEntry>> {
+    let mut entries = Vec::with_capacity(count);
+    for index in 0..count {
+        let tint = (index as u32 * 37) % 255;
+        let img = image::RgbaImage::from_fn(SYNTH_W, SYNTH_H, |x, y| {
+            image::Rgba([
+                ((x * 255) / SYNTH_W) as u8,
+                ((y * 255) / SYNTH_H) as u8,
+                tint as u8,
+                255,
+            ])
+        });
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgba8(img)
+            .write_to(&mut bytes, image::ImageOutputFormat::Png)
+            .map_err(Error::Image)?;
+        let mut entry = ManifestEntry::from_path(PathBuf::from(format!("synthetic-{}.png", index)));
+        entry.data = Some(bytes.into_inner());
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Times one full grid build, returning (composite s, encode s).
+fn timed_build(entries: &[ManifestEntry], args: &crate::Args, threads: usize) -> error::Result<(f64, f64)> {
+    let bench_args = <crate::Args as clap::Parser>::parse_from([
+        "rust_img_collage".to_string(),
+        "--cell-size".to_string(),
+        args.cell_size.to_string(),
+        "--threads".to_string(),
+        threads.to_string(),
+    ]);
+    let output = std::env::temp_dir().join("collage_bench.webp");
+    let output_path = output.to_string_lossy().into_owned();
+    let mut run = RunSummary::default();
+    crate::create_collage(entries, &bench_args, &output_path, &mut run, 0)?;
+    let _ = std::fs::remove_file(&output);
+    Ok((
+        run.phase_seconds.get("composite").copied().unwrap_or(0.0),
+        run.phase_seconds.get("encode").copied().unwrap_or(0.0),
+    ))
+}
+
+/// Runs the benchmark and prints the report.
+pub fn run_bench(args: &crate::Args, images: usize, input: Option<&str>) -> error::Result<()> {
+    if images == 0 {
+        return Err(Error::Usage("bench needs at least one image".to_string()));
+    }
+    let entries = match input {
+        Some(dir) => {
+            let (paths, _) = crate::get_sorted_image_paths(dir, None, false)?;
+            let mut entries: Vec<ManifestEntry> =
+                paths.into_iter().map(ManifestEntry::from_path).collect();
+            entries.truncate(images);
+            if entries.is_empty() {
+                return Err(Error::NoImages);
+            }
+            entries
+        }
+        None => synthetic_entries(images)?,
+    };
+    println!(
+        "bench: {} images, cell size {} px",
+        entries.len(),
+        args.cell_size
+    );
+
+    // Decode: every image once, sequentially.
+    let start = std::time::Instant::now();
+    let mut decoded = Vec::with_capacity(entries.len());
+    let mut megapixels = 0.0;
+    for entry in &entries {
+        match entry.load_image() {
+            Ok(img) => {
+                megapixels += (img.width() as f64 * img.height() as f64) / 1e6;
+                decoded.push(img);
+            }
+            Err(e) => tracing::warn!("bench: cannot decode {:?}: {}", entry.path, e),
+        }
+    }
+    if decoded.is_empty() {
+        return Err(Error::NoImages);
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    println!(
+        "decode     {:7.2} s  ({:.1} images/s, {:.1} MP/s)",
+        elapsed,
+        decoded.len() as f64 / elapsed,
+        megapixels / elapsed
+    );
+
+    // Resize: each filter over a small sample, worst (Lanczos3) last.
+    let sample = &decoded[..decoded.len().min(8)];
+    for (filter, name) in [
+        (FilterType::Nearest, "nearest"),
+        (FilterType::Triangle, "triangle"),
+        (FilterType::CatmullRom, "catmull-rom"),
+        (FilterType::Lanczos3, "lanczos3"),
+    ] {
+        let start = std::time::Instant::now();
+        for img in sample {
+            let _ = img.resize(args.cell_size, args.cell_size, filter);
+        }
+        let elapsed = start.elapsed().as_secs_f64();
+        println!(
+            "resize     {:7.2} ms/image  ({})",
+            elapsed * 1000.0 / sample.len() as f64,
+            name
+        );
+    }
+    drop(decoded);
+
+    // Full builds: serial against the auto thread count.
+    let auto = crate::effective_threads(args);
+    for threads in [1, auto] {
+        let (composite, encode) = timed_build(&entries, args, threads)?;
+        println!(
+            "composite  {:7.2} s  encode {:6.2} s  ({:.1} images/s, {} thread{})",
+            composite,
+            encode,
+            entries.len() as f64 / composite.max(1e-9),
+            threads,
+            if threads == 1 { "" } else { "s" }
+        );
+        if auto == 1 {
+            break;
+        }
+    }
+    if cfg!(feature = "gpu") {
+        println!("gpu        rerun with --gpu to compare the wgpu backend");
+    }
+    Ok(())
+}
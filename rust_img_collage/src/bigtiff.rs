@@ -0,0 +1,190 @@
+//! Tiled, pyramidal BigTIFF output (`--tiles tiff`).
+//!
+//! The `tiff` crates only write striped classic TIFF, which caps out at
+//! 4 GB and doesn't suit GIS/medical viewers, so this is a small
+//! hand-rolled writer: little-endian BigTIFF (version 43) with one IFD per
+//! pyramid level, uncompressed 256x256 RGBA tiles written straight from
+//! the memory-mapped canvas. Reduced levels are box-downsampled through
+//! disk-backed temp maps so the whole pyramid never sits in memory.
+
+use memmap2::MmapMut;
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom, Write};
+use tempfile::tempfile;
+
+/// Tile edge length in pixels.
+const TILE_SIZE: u32 = 256;
+
+// TIFF field types.
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+const TYPE_LONG8: u16 = 16;
+
+/// One 20-byte BigTIFF IFD entry with an inline (<= 8 byte) value.
+struct IfdEntry {
+    tag: u16,
+    field_type: u16,
+    count: u64,
+    value: [u8; 8],
+}
+
+fn entry_long(tag: u16, value: u32) -> IfdEntry {
+    let mut v = [0u8; 8];
+    v[..4].copy_from_slice(&value.to_le_bytes());
+    IfdEntry { tag, field_type: TYPE_LONG, count: 1, value: v }
+}
+
+fn entry_shorts(tag: u16, values: &[u16]) -> IfdEntry {
+    let mut v = [0u8; 8];
+    for (i, s) in values.iter().enumerate() {
+        v[i * 2..i * 2 + 2].copy_from_slice(&s.to_le_bytes());
+    }
+    IfdEntry { tag, field_type: TYPE_SHORT, count: values.len() as u64, value: v }
+}
+
+fn entry_offset(tag: u16, field_type: u16, count: u64, offset: u64) -> IfdEntry {
+    IfdEntry { tag, field_type, count, value: offset.to_le_bytes() }
+}
+
+/// Box-downsamples an RGBA buffer by 2 into a fresh disk-backed map.
+fn downsample(src: &[u8], src_w: u32, src_h: u32) -> io::Result<(MmapMut, u32, u32)> {
+    let dst_w = (src_w / 2).max(1);
+    let dst_h = (src_h / 2).max(1);
+    let file = tempfile()?;
+    file.set_len(dst_w as u64 * dst_h as u64 * 4)?;
+    crate::resources::add_temp(dst_w as u64 * dst_h as u64 * 4);
+    let mut dst = unsafe { MmapMut::map_mut(&file)? };
+    for y in 0..dst_h {
+        for x in 0..dst_w {
+            let mut sums = [0u32; 4];
+            let mut n = 0u32;
+            for dy in 0..2 {
+                for dx in 0..2 {
+                    let sx = (x * 2 + dx).min(src_w - 1);
+                    let sy = (y * 2 + dy).min(src_h - 1);
+                    let off = ((sy as u64 * src_w as u64 + sx as u64) * 4) as usize;
+                    for c in 0..4 {
+                        sums[c] += src[off + c] as u32;
+                    }
+                    n += 1;
+                }
+            }
+            let off = ((y as u64 * dst_w as u64 + x as u64) * 4) as usize;
+            for c in 0..4 {
+                dst[off + c] = (sums[c] / n) as u8;
+            }
+        }
+    }
+    Ok((dst, dst_w, dst_h))
+}
+
+/// Writes the tile data for one level, returning (offsets, byte counts).
+/// Edge tiles are zero-padded to the full tile size as TIFF requires.
+fn write_tiles(out: &mut File, canvas: &[u8], w: u32, h: u32) -> io::Result<(Vec<u64>, Vec<u64>)> {
+    let cols = w.div_ceil(TILE_SIZE);
+    let rows = h.div_ceil(TILE_SIZE);
+    let tile_bytes = (TILE_SIZE * TILE_SIZE * 4) as usize;
+    let mut offsets = Vec::new();
+    let mut counts = Vec::new();
+    let mut tile = vec![0u8; tile_bytes];
+    for tr in 0..rows {
+        for tc in 0..cols {
+            tile.fill(0);
+            let x0 = tc * TILE_SIZE;
+            let y0 = tr * TILE_SIZE;
+            let copy_w = TILE_SIZE.min(w - x0) as usize * 4;
+            for row in 0..TILE_SIZE.min(h - y0) {
+                let src = (((y0 + row) as u64 * w as u64 + x0 as u64) * 4) as usize;
+                let dst = (row * TILE_SIZE * 4) as usize;
+                tile[dst..dst + copy_w].copy_from_slice(&canvas[src..src + copy_w]);
+            }
+            offsets.push(out.stream_position()?);
+            counts.push(tile_bytes as u64);
+            out.write_all(&tile)?;
+        }
+    }
+    Ok((offsets, counts))
+}
+
+/// Writes a LONG8 array, returning its file offset.
+fn write_long8_array(out: &mut File, values: &[u64]) -> io::Result<u64> {
+    let offset = out.stream_position()?;
+    for v in values {
+        out.write_all(&v.to_le_bytes())?;
+    }
+    Ok(offset)
+}
+
+/// Writes one pyramid level's IFD, returning the position of its
+/// next-IFD pointer so the chain can be patched later.
+fn write_ifd(out: &mut File, w: u32, h: u32, reduced: bool, tile_offsets: u64, tile_counts: u64, tiles: u64) -> io::Result<(u64, u64)> {
+    let entries = [
+        entry_long(254, if reduced { 1 } else { 0 }),   // NewSubfileType
+        entry_long(256, w),                             // ImageWidth
+        entry_long(257, h),                             // ImageLength
+        entry_shorts(258, &[8, 8, 8, 8]),               // BitsPerSample
+        entry_shorts(259, &[1]),                        // Compression: none
+        entry_shorts(262, &[2]),                        // Photometric: RGB
+        entry_shorts(277, &[4]),                        // SamplesPerPixel
+        entry_long(322, TILE_SIZE),                     // TileWidth
+        entry_long(323, TILE_SIZE),                     // TileLength
+        entry_offset(324, TYPE_LONG8, tiles, tile_offsets), // TileOffsets
+        entry_offset(325, TYPE_LONG8, tiles, tile_counts),  // TileByteCounts
+        entry_shorts(338, &[2]),                        // ExtraSamples: unassoc alpha
+    ];
+    let ifd_offset = out.stream_position()?;
+    out.write_all(&(entries.len() as u64).to_le_bytes())?;
+    for e in &entries {
+        out.write_all(&e.tag.to_le_bytes())?;
+        out.write_all(&e.field_type.to_le_bytes())?;
+        out.write_all(&e.count.to_le_bytes())?;
+        out.write_all(&e.value)?;
+    }
+    let next_ptr_pos = out.stream_position()?;
+    out.write_all(&0u64.to_le_bytes())?;
+    Ok((ifd_offset, next_ptr_pos))
+}
+
+/// Writes the canvas as a tiled, pyramidal BigTIFF.
+pub fn write_pyramidal_bigtiff(canvas: &[u8], full_w: u32, full_h: u32, output_path: &str) -> io::Result<()> {
+    let mut out = File::create(output_path)?;
+
+    // BigTIFF header: "II", version 43, 8-byte offsets, first IFD offset
+    // patched once the level-0 tiles are written.
+    out.write_all(b"II")?;
+    out.write_all(&43u16.to_le_bytes())?;
+    out.write_all(&8u16.to_le_bytes())?;
+    out.write_all(&0u16.to_le_bytes())?;
+    let mut next_ptr_pos = out.stream_position()?;
+    out.write_all(&0u64.to_le_bytes())?;
+
+    let mut level: Option<(MmapMut, u32, u32)> = None;
+    let mut levels = 0u32;
+    loop {
+        let (data, w, h): (&[u8], u32, u32) = match &level {
+            None => (canvas, full_w, full_h),
+            Some((map, w, h)) => (map, *w, *h),
+        };
+        let (offsets, counts) = write_tiles(&mut out, data, w, h)?;
+        let offsets_pos = write_long8_array(&mut out, &offsets)?;
+        let counts_pos = write_long8_array(&mut out, &counts)?;
+        let (ifd_offset, ifd_next) =
+            write_ifd(&mut out, w, h, levels > 0, offsets_pos, counts_pos, offsets.len() as u64)?;
+
+        // Chain this IFD onto the previous one (or the header).
+        let end = out.stream_position()?;
+        out.seek(SeekFrom::Start(next_ptr_pos))?;
+        out.write_all(&ifd_offset.to_le_bytes())?;
+        out.seek(SeekFrom::Start(end))?;
+        next_ptr_pos = ifd_next;
+        levels += 1;
+
+        if w.max(h) <= TILE_SIZE {
+            break;
+        }
+        level = Some(downsample(data, w, h)?);
+    }
+
+    tracing::info!("Pyramidal BigTIFF saved to '{}' ({} levels)", output_path, levels);
+    Ok(())
+}
@@ -0,0 +1,93 @@
+//! BlurHash encoding (`--blurhash`): the compact base83 string web
+//! frontends decode into an instant blurred placeholder. A purpose-built
+//! encoder — the algorithm is one small DCT plus quantization, no crate
+//! needed.
+
+const BASE83: &[u8; 83] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+/// Appends `value` as `length` base83 digits.
+fn encode83(out: &mut String, value: u32, length: u32) {
+    for i in (0..length).rev() {
+        let digit = (value / 83u32.pow(i)) % 83;
+        out.push(BASE83[digit as usize] as char);
+    }
+}
+
+/// sRGB byte to linear intensity.
+fn to_linear(byte: u8) -> f64 {
+    let v = byte as f64 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Linear intensity back to an sRGB byte.
+fn to_srgb(v: f64) -> u32 {
+    let v = v.clamp(0.0, 1.0);
+    let v = if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (v * 255.0 + 0.5) as u32
+}
+
+/// Signed power used when quantizing AC components.
+fn sign_pow(value: f64, exp: f64) -> f64 {
+    value.abs().powf(exp).copysign(value)
+}
+
+/// Encodes the image as a BlurHash with `cx` by `cy` DCT components
+/// (both 1-9; 4x3 is the usual choice). The image should already be a
+/// small thumbnail — cost scales with pixels times components.
+pub fn encode(img: &image::DynamicImage, cx: u32, cy: u32) -> String {
+    let rgb = img.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let pixels = (width as f64 * height as f64).max(1.0);
+
+    // One normalized cosine-basis factor per component, in linear RGB.
+    let mut factors = Vec::with_capacity((cx * cy) as usize);
+    for j in 0..cy {
+        for i in 0..cx {
+            let norm = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+            let mut f = [0.0f64; 3];
+            for (x, y, pixel) in rgb.enumerate_pixels() {
+                let basis = (std::f64::consts::PI * i as f64 * x as f64 / width as f64).cos()
+                    * (std::f64::consts::PI * j as f64 * y as f64 / height as f64).cos();
+                for (slot, &byte) in f.iter_mut().zip(&pixel.0) {
+                    *slot += basis * to_linear(byte);
+                }
+            }
+            factors.push(f.map(|v| v * norm / pixels));
+        }
+    }
+
+    let mut out = String::new();
+    encode83(&mut out, (cx - 1) + (cy - 1) * 9, 1);
+
+    let ac = &factors[1..];
+    let actual_max = ac
+        .iter()
+        .flatten()
+        .fold(0.0f64, |max, &v| max.max(v.abs()));
+    let quant_max = if ac.is_empty() {
+        0
+    } else {
+        ((actual_max * 166.0 - 0.5).floor() as i64).clamp(0, 82) as u32
+    };
+    let max_val = (quant_max + 1) as f64 / 166.0;
+    encode83(&mut out, quant_max, 1);
+
+    let dc = factors[0];
+    encode83(&mut out, (to_srgb(dc[0]) << 16) + (to_srgb(dc[1]) << 8) + to_srgb(dc[2]), 4);
+    for f in ac {
+        let quant = f.map(|v| {
+            ((sign_pow(v / max_val, 0.5) * 9.0 + 9.5).floor() as i64).clamp(0, 18) as u32
+        });
+        encode83(&mut out, quant[0] * 19 * 19 + quant[1] * 19 + quant[2], 2);
+    }
+    out
+}
@@ -0,0 +1,129 @@
+//! Brick layout (`--layout brick`): like the grid, but every other row is
+//! shifted by half a cell, brickwork style. Shifted rows get one extra
+//! image whose halves are clipped at the canvas edges, so the wall stays
+//! flush. Images are cover-fitted so the joints stay tight.
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use crate::summary::RunSummary;
+use memmap2::MmapMut;
+use std::cmp;
+use tempfile::tempfile;
+
+/// Copies a cover-fitted image into a cell that may hang off the canvas
+/// edge; out-of-bounds pixels are clipped.
+fn paste_clipped(
+    buf: &mut [u8],
+    (canvas_w, canvas_h): (u32, u32),
+    (cell_x, cell_y): (i64, i64),
+    cell_size: u32,
+    img: &image::DynamicImage,
+) {
+    let filled = img
+        .resize_to_fill(cell_size, cell_size, image::imageops::FilterType::Lanczos3)
+        .to_rgba8();
+    for y in 0..cell_size {
+        for x in 0..cell_size {
+            let tx = cell_x + x as i64;
+            let ty = cell_y + y as i64;
+            if tx < 0 || ty < 0 || tx >= canvas_w as i64 || ty >= canvas_h as i64 {
+                continue;
+            }
+            let pixel = filled.get_pixel(x, y);
+            let index = ((ty as u32 * canvas_w + tx as u32) * 4) as usize;
+            buf[index..index + 4].copy_from_slice(&pixel.0);
+        }
+    }
+}
+
+/// Renders the brick collage to `output_path`.
+pub fn create_brick(
+    entries: &[ManifestEntry],
+    args: &crate::Args,
+    output_path: &str,
+    run: &mut RunSummary,
+) -> error::Result<()> {
+    if entries.is_empty() {
+        return Err(Error::NoImages);
+    }
+    let cell_size = args.cell_size;
+    let n = entries.len() as u32;
+    let ncols = cmp::max(1, (n as f64).sqrt().ceil() as u32);
+    let width = ncols * cell_size;
+
+    // Plan the cells row by row: offset rows hold one extra, clipped image.
+    let mut cells: Vec<(i64, i64)> = Vec::with_capacity(entries.len());
+    let mut row = 0u32;
+    while (cells.len() as u32) < n {
+        let offset = !row.is_multiple_of(2);
+        let in_row = if offset { ncols + 1 } else { ncols };
+        let shift = if offset { -(cell_size as i64) / 2 } else { 0 };
+        for col in 0..in_row {
+            if (cells.len() as u32) == n {
+                break;
+            }
+            cells.push((
+                shift + (col * cell_size) as i64,
+                (row * cell_size) as i64,
+            ));
+        }
+        row += 1;
+    }
+    let nrows = row;
+    let height = nrows * cell_size;
+    tracing::debug!(
+        "brick layout: {} images in {} courses, canvas {}x{} px",
+        n, nrows, width, height
+    );
+    run.total_images = entries.len();
+    run.grid_cols = ncols;
+    run.grid_rows = nrows;
+    run.canvas_width = width;
+    run.canvas_height = height;
+
+    let num_pixels = (width as u64 * height as u64) as usize;
+    let file = tempfile()?;
+    file.set_len(num_pixels as u64 * 4)?;
+    crate::resources::add_temp(num_pixels as u64 * 4);
+    let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+    crate::background::fill(&mut mmap, (width, height));
+
+    let composite_start = std::time::Instant::now();
+    for (entry, &(cell_x, cell_y)) in entries.iter().zip(&cells) {
+        match entry.load_image() {
+            Ok(img) => {
+                paste_clipped(&mut mmap, (width, height), (cell_x, cell_y), cell_size, &img);
+            }
+            Err(e) => {
+                if args.strict || args.on_error == crate::OnError::Abort {
+                    return Err(Error::Decode(entry.path.clone(), e));
+                }
+                tracing::error!("Error processing {:?}: {}", entry.path, e);
+                run.skip(&entry.path, &e);
+                if args.on_error == crate::OnError::Placeholder {
+                    let x = cell_x.max(0) as u32;
+                    let y = cell_y.max(0) as u32;
+                    crate::draw_placeholder(
+                        &mut mmap,
+                        (width, height),
+                        (x, y, cell_size, cell_size),
+                        cell_size,
+                        &entry.path,
+                    );
+                }
+            }
+        }
+    }
+    mmap.flush()?;
+    run.phase_seconds
+        .insert("composite".to_string(), composite_start.elapsed().as_secs_f64());
+
+    let encode_start = std::time::Instant::now();
+    crate::save_canvas(&mmap, (width, height), args, output_path)?;
+    run.phase_seconds
+        .insert("encode".to_string(), encode_start.elapsed().as_secs_f64());
+    run.output_file = output_path.to_string();
+    run.output_bytes = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!("Brick collage saved to '{}' ({} courses)", output_path, nrows);
+    Ok(())
+}
@@ -0,0 +1,88 @@
+//! Burst collapsing (`--best-of-burst`).
+//!
+//! Burst shooting leaves runs of near-identical frames that flood a
+//! collage. Consecutive entries are clustered by perceptual similarity
+//! (a 64-bit difference hash over a tiny grayscale thumbnail; a handful
+//! of differing bits still counts as the same scene), and each cluster
+//! keeps only its best frame: the sharpest (the Laplacian variance
+//! already used by --skip-blurry) weighted by how well-exposed it is.
+//! Only consecutive entries cluster, so two visits to the same spot on
+//! different days both survive.
+
+use crate::manifest::ManifestEntry;
+use image::DynamicImage;
+
+/// Hamming distance up to which two consecutive frames count as the
+/// same burst.
+const SAME_SCENE_BITS: u32 = 10;
+
+/// Difference hash: 1 bit per pixel pair of a 9x8 grayscale thumbnail,
+/// set when the left pixel is brighter than its right neighbour.
+fn dhash(img: &DynamicImage) -> u64 {
+    let gray = img.thumbnail_exact(9, 8).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if gray.get_pixel(x, y)[0] > gray.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// 1 for a mid-gray mean, falling to 0 at full black or white.
+fn exposure(img: &DynamicImage) -> f64 {
+    let gray = img.thumbnail(64, 64).to_luma8();
+    let sum: u64 = gray.pixels().map(|p| p[0] as u64).sum();
+    let mean = sum as f64 / (gray.width() as f64 * gray.height() as f64) / 255.0;
+    1.0 - 2.0 * (mean - 0.5).abs()
+}
+
+/// Collapses each burst to its best frame, in place. Unreadable entries
+/// never cluster and are kept for the decode stage's --on-error policy.
+pub fn apply(entries: &mut Vec<ManifestEntry>) {
+    let before = entries.len();
+    let metrics: Vec<Option<(u64, f64)>> = entries
+        .iter()
+        .map(|entry| {
+            entry.load_image().ok().map(|img| {
+                // Sharpness spans orders of magnitude; exposure only
+                // tips the balance between comparably sharp frames.
+                let score = crate::sharpness(&img) * (0.5 + exposure(&img));
+                (dhash(&img), score)
+            })
+        })
+        .collect();
+
+    let mut keep = vec![true; entries.len()];
+    let mut cluster_best: Option<usize> = None;
+    for index in 0..entries.len() {
+        let Some((hash, score)) = metrics[index] else {
+            cluster_best = None;
+            continue;
+        };
+        match cluster_best {
+            Some(best)
+                if (metrics[best].unwrap().0 ^ hash).count_ones() <= SAME_SCENE_BITS =>
+            {
+                if score > metrics[best].unwrap().1 {
+                    keep[best] = false;
+                    cluster_best = Some(index);
+                } else {
+                    keep[index] = false;
+                }
+            }
+            _ => cluster_best = Some(index),
+        }
+    }
+    let mut it = keep.iter();
+    entries.retain(|_| *it.next().unwrap());
+    if entries.len() < before {
+        tracing::info!(
+            "Collapsed {} burst frames, keeping the best of each run",
+            before - entries.len()
+        );
+    }
+}
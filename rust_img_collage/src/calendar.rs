@@ -0,0 +1,148 @@
+//! Calendar layout (`--layout calendar --month YYYY-MM`): a month grid
+//! where each day cell holds a mini-collage of the photos captured that
+//! day. Days without photos just show their date. Weeks run Monday-first.
+
+use crate::date;
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use crate::summary::RunSummary;
+use crate::text;
+use memmap2::MmapMut;
+use std::cmp;
+use std::collections::BTreeMap;
+use tempfile::tempfile;
+
+/// Parses `YYYY-MM` into (year, month).
+fn parse_month(value: &str) -> error::Result<(i32, u32)> {
+    let parsed = value.split_once('-').and_then(|(y, m)| {
+        let year = y.parse::<i32>().ok()?;
+        let month = m.parse::<u32>().ok()?;
+        (1..=12).contains(&month).then_some((year, month))
+    });
+    parsed.ok_or_else(|| Error::Usage(format!("invalid --month {:?}, expected YYYY-MM", value)))
+}
+
+/// Renders the month calendar to `output_path`.
+pub fn create_calendar(
+    entries: &[ManifestEntry],
+    args: &crate::Args,
+    output_path: &str,
+    run: &mut RunSummary,
+) -> error::Result<()> {
+    let month_arg = args
+        .month
+        .as_deref()
+        .ok_or_else(|| Error::Usage("--layout calendar requires --month YYYY-MM".to_string()))?;
+    let (year, month) = parse_month(month_arg)?;
+    let cell_size = args.cell_size;
+    let scale = cmp::max(1, cell_size / 200);
+    let band_height = text::line_height(scale) * 2;
+
+    // Bucket this month's images by day; anything else is ignored.
+    let mut days: BTreeMap<u32, Vec<&ManifestEntry>> = BTreeMap::new();
+    let mut outside = 0usize;
+    for entry in entries {
+        match date::capture_day(entry) {
+            Some((y, m, d)) if y == year && m == month => {
+                days.entry(d).or_default().push(entry)
+            }
+            _ => {
+                outside += 1;
+                continue;
+            }
+        };
+    }
+    if outside > 0 {
+        tracing::info!("{} images fall outside {} and were ignored", outside, month_arg);
+    }
+
+    let total_days = date::days_in_month(year, month);
+    let first_weekday = date::weekday((year, month, 1));
+    let weeks = (first_weekday + total_days).div_ceil(7);
+    let width = 7 * cell_size;
+    let height = band_height + weeks * cell_size;
+    tracing::debug!(
+        "calendar layout: {} with {} populated days, canvas {}x{} px",
+        month_arg, days.len(), width, height
+    );
+    run.total_images = entries.len() - outside;
+    run.grid_cols = 7;
+    run.grid_rows = weeks;
+    run.canvas_width = width;
+    run.canvas_height = height;
+
+    let num_pixels = (width as u64 * height as u64) as usize;
+    let file = tempfile()?;
+    file.set_len(num_pixels as u64 * 4)?;
+    crate::resources::add_temp(num_pixels as u64 * 4);
+    let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+    crate::background::fill(&mut mmap, (width, height));
+
+    // Month heading: the raw YYYY-MM argument, or --date-format applied
+    // to the first of the month (e.g. "%B %Y").
+    let heading = match args.date_format.as_deref() {
+        Some(format) => date::format_day((year, month, 1), format),
+        None => month_arg.to_string(),
+    };
+    text::draw_text(
+        &mut mmap, (width, height),
+        (scale as i64 * 2, (text::line_height(scale) / 2) as i64),
+        scale, [0, 0, 0, 255], &heading,
+    );
+
+    let composite_start = std::time::Instant::now();
+    for day in 1..=total_days {
+        let slot = first_weekday + day - 1;
+        let cell_x = (slot % 7) * cell_size;
+        let cell_y = band_height + (slot / 7) * cell_size;
+
+        // The day's photos as a nested mini-grid filling the cell.
+        if let Some(group) = days.get(&day) {
+            let mini_cols = cmp::max(1, (group.len() as f64).sqrt().ceil() as u32);
+            let mini_rows = (group.len() as u32).div_ceil(mini_cols);
+            let mini_w = cell_size / mini_cols;
+            let mini_h = cell_size / mini_rows;
+            for (i, entry) in group.iter().enumerate() {
+                let x = cell_x + (i as u32 % mini_cols) * mini_w;
+                let y = cell_y + (i as u32 / mini_cols) * mini_h;
+                match entry.load_image() {
+                    Ok(img) => {
+                        crate::paste_image(&mut mmap, (width, height), (x, y, mini_w, mini_h), &img);
+                    }
+                    Err(e) => {
+                        if args.strict || args.on_error == crate::OnError::Abort {
+                            return Err(Error::Decode(entry.path.clone(), e));
+                        }
+                        tracing::error!("Error processing {:?}: {}", entry.path, e);
+                        run.skip(&entry.path, &e);
+                    }
+                }
+            }
+        }
+
+        // Date number in the top-left corner, shadowed for readability.
+        let label = day.to_string();
+        text::draw_text(
+            &mut mmap, (width, height),
+            (cell_x as i64 + scale as i64 * 3, cell_y as i64 + scale as i64 * 3),
+            scale, [0, 0, 0, 255], &label,
+        );
+        text::draw_text(
+            &mut mmap, (width, height),
+            (cell_x as i64 + scale as i64 * 2, cell_y as i64 + scale as i64 * 2),
+            scale, [255, 255, 255, 255], &label,
+        );
+    }
+    mmap.flush()?;
+    run.phase_seconds
+        .insert("composite".to_string(), composite_start.elapsed().as_secs_f64());
+
+    let encode_start = std::time::Instant::now();
+    crate::save_canvas(&mmap, (width, height), args, output_path)?;
+    run.phase_seconds
+        .insert("encode".to_string(), encode_start.elapsed().as_secs_f64());
+    run.output_file = output_path.to_string();
+    run.output_bytes = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!("Calendar saved to '{}' ({} weeks)", output_path, weeks);
+    Ok(())
+}
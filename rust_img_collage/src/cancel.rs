@@ -0,0 +1,43 @@
+//! Cooperative cancellation for long builds.
+//!
+//! The token is a plain shared `AtomicBool`, installed per thread (each
+//! server job composites on its own thread). The compositing loop calls
+//! [`check`] between images; once the holder sets the flag the run is
+//! abandoned with `Error::Cancelled` and the temp canvas is simply
+//! dropped, so cancellation never leaves a half-written output.
+
+use crate::error::{self, Error};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+thread_local! {
+    static TOKEN: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+/// Installs `token` as the current thread's cancellation token; the
+/// holder keeps its own clone and stores `true` to cancel.
+pub fn set(token: Arc<AtomicBool>) {
+    TOKEN.with(|slot| *slot.borrow_mut() = Some(token));
+}
+
+/// The current thread's token, for handing to worker threads that
+/// composite on its behalf (see [`crate::parallel`]).
+pub fn token() -> Option<Arc<AtomicBool>> {
+    TOKEN.with(|slot| slot.borrow().clone())
+}
+
+/// Fails with `Error::Cancelled` once the current thread's token is
+/// set. Threads without a token never cancel.
+pub fn check() -> error::Result<()> {
+    let cancelled = TOKEN.with(|slot| {
+        slot.borrow()
+            .as_ref()
+            .is_some_and(|token| token.load(Ordering::Relaxed))
+    });
+    if cancelled {
+        Err(Error::Cancelled)
+    } else {
+        Ok(())
+    }
+}
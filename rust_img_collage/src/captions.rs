@@ -0,0 +1,161 @@
+//! Sidecar captions (`--captions`): each image without a manifest caption
+//! is labelled from a sidecar file next to it — `IMG_001.txt` (first
+//! non-empty line), the Description of `IMG_001.xmp` (a tiny scan of the
+//! XML, no parser crate needed for one field), or a `caption`/
+//! `description` field in `IMG_001.json` — falling back to the file name,
+//! so curated caption sets flow straight into the collage.
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The sidecar path for `path` with the given extension: `IMG.ext`
+/// first (the common convention), then the full-name `IMG.jpg.ext`.
+fn sidecar(path: &Path, ext: &str) -> Option<PathBuf> {
+    let swapped = path.with_extension(ext);
+    if swapped.is_file() {
+        return Some(swapped);
+    }
+    let mut name = path.file_name()?.to_os_string();
+    name.push(format!(".{}", ext));
+    let appended = path.with_file_name(name);
+    appended.is_file().then_some(appended)
+}
+
+/// First non-empty line of a plain-text sidecar.
+fn from_txt(path: &Path) -> Option<String> {
+    let text = fs::read_to_string(path).ok()?;
+    let line = text.lines().map(str::trim).find(|line| !line.is_empty())?;
+    Some(line.to_string())
+}
+
+/// The `dc:description` of an XMP sidecar, in either its element form
+/// (`<dc:description><rdf:Alt><rdf:li ...>text</rdf:li>`) or its
+/// attribute form (`dc:description="text"`).
+fn from_xmp(path: &Path) -> Option<String> {
+    let xml = fs::read_to_string(path).ok()?;
+    let at = xml.find("dc:description")?;
+    let rest = &xml[at + "dc:description".len()..];
+    let value = if let Some(quoted) = rest.strip_prefix("=\"") {
+        &quoted[..quoted.find('"')?]
+    } else {
+        let li = rest.find("<rdf:li")?;
+        let open = rest[li..].find('>')? + li + 1;
+        &rest[open..open + rest[open..].find('<')?]
+    };
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// The `caption` (or `description`) string of a JSON sidecar.
+fn from_json(path: &Path) -> Option<String> {
+    let text = fs::read_to_string(path).ok()?;
+    let value: serde_json::Value = serde_json::from_str(&text).ok()?;
+    ["caption", "description"]
+        .iter()
+        .find_map(|key| value.get(key)?.as_str())
+        .map(str::to_string)
+}
+
+/// Fills in missing captions from sidecars (txt, then XMP, then JSON),
+/// falling back to the file stem. Manifest captions are left alone.
+pub fn apply(entries: &mut [ManifestEntry]) {
+    let mut from_sidecars = 0usize;
+    for entry in entries.iter_mut() {
+        if entry.caption.is_some() {
+            continue;
+        }
+        let caption = sidecar(&entry.path, "txt")
+            .and_then(|p| from_txt(&p))
+            .or_else(|| sidecar(&entry.path, "xmp").and_then(|p| from_xmp(&p)))
+            .or_else(|| sidecar(&entry.path, "json").and_then(|p| from_json(&p)));
+        if caption.is_some() {
+            from_sidecars += 1;
+        }
+        entry.caption = caption.or_else(|| {
+            entry
+                .path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        });
+    }
+    if from_sidecars > 0 {
+        tracing::info!("Captions read from {} sidecar files", from_sidecars);
+    }
+}
+
+/// One `{field}` or `{field:format}` of a label template.
+fn field(entry: &ManifestEntry, index: usize, name: &str, format: Option<&str>) -> Option<String> {
+    let part = |pick: fn(&Path) -> Option<&std::ffi::OsStr>| {
+        pick(&entry.path)
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    Some(match name {
+        "path" => entry.path.to_string_lossy().into_owned(),
+        "name" => part(Path::file_name),
+        "stem" => part(Path::file_stem),
+        "ext" => part(|p| p.extension()),
+        "folder" => entry
+            .path
+            .parent()
+            .and_then(|p| p.file_name())
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        "index" => (index + 1).to_string(),
+        "width" => entry.dimensions().map(|(w, _)| w.to_string()).unwrap_or_default(),
+        "height" => entry.dimensions().map(|(_, h)| h.to_string()).unwrap_or_default(),
+        "exif_date" => crate::date::capture_day(entry)
+            .map(|day| crate::date::format_day(day, format.unwrap_or("%Y-%m-%d")))
+            .unwrap_or_default(),
+        "rating" => crate::date::rating(entry).map(|r| r.to_string()).unwrap_or_default(),
+        _ => return None,
+    })
+}
+
+/// Captions every entry from a --label-template, e.g.
+/// `"{folder}/{stem} — {exif_date:%Y-%m-%d} ({width}x{height})"`.
+/// `{{` and `}}` escape literal braces; an unknown field is a usage
+/// error. Template captions override manifest and sidecar captions.
+pub fn apply_template(entries: &mut [ManifestEntry], template: &str) -> error::Result<()> {
+    for (index, entry) in entries.iter_mut().enumerate() {
+        let mut out = String::new();
+        let mut rest = template;
+        while let Some(at) = rest.find(['{', '}']) {
+            out.push_str(&rest[..at]);
+            // A doubled brace is a literal; a lone '}' is malformed.
+            if rest[at..].starts_with("{{") || rest[at..].starts_with("}}") {
+                out.push_str(&rest[at..at + 1]);
+                rest = &rest[at + 2..];
+                continue;
+            }
+            if rest[at..].starts_with('}') {
+                return Err(Error::Usage(format!(
+                    "invalid --label-template {:?}: unmatched '}}'",
+                    template
+                )));
+            }
+            let close = rest[at..].find('}').ok_or_else(|| {
+                Error::Usage(format!("invalid --label-template {:?}: unmatched '{{'", template))
+            })? + at;
+            let spec = &rest[at + 1..close];
+            let (name, format) = match spec.split_once(':') {
+                Some((name, format)) => (name, Some(format)),
+                None => (spec, None),
+            };
+            let value = field(entry, index, name, format).ok_or_else(|| {
+                Error::Usage(format!(
+                    "unknown field {:?} in --label-template; expected path, name, stem, ext, \
+                     folder, index, width, height, exif_date or rating",
+                    name
+                ))
+            })?;
+            out.push_str(&value);
+            rest = &rest[close + 1..];
+        }
+        out.push_str(rest);
+        entry.caption = Some(out);
+    }
+    Ok(())
+}
@@ -0,0 +1,173 @@
+//! Contact sheet mode (`--contact-sheet`): a grid of thumbnails, each
+//! over a light band listing the camera, lens, exposure (focal length,
+//! aperture, shutter, ISO) and capture time from the image's EXIF — the
+//! Lightroom-style review sheet, minus Lightroom.
+
+use crate::date::{self, ExifSummary};
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use crate::summary::RunSummary;
+use crate::text;
+use memmap2::MmapMut;
+use std::cmp;
+use tempfile::tempfile;
+
+/// Lines per metadata band: camera, lens, exposure, capture time.
+const BAND_LINES: u32 = 4;
+
+/// The exposure line, e.g. "50mm f/1.8 1/250s ISO 200"; missing fields
+/// are simply left out.
+fn exposure_line(exif: &ExifSummary) -> String {
+    let mut parts = Vec::new();
+    if let Some(focal) = exif.focal_length {
+        parts.push(format!("{:.0}mm", focal));
+    }
+    if let Some(aperture) = exif.aperture {
+        parts.push(format!("f/{:.1}", aperture));
+    }
+    if let Some((num, den)) = exif.exposure {
+        if num == 1 {
+            parts.push(format!("1/{}s", den));
+        } else {
+            parts.push(format!("{:.1}s", num as f64 / den as f64));
+        }
+    }
+    if let Some(iso) = exif.iso {
+        parts.push(format!("ISO {}", iso));
+    }
+    parts.join(" ")
+}
+
+/// The four band lines for an entry; empty lines still occupy their slot
+/// so the bands line up across cells.
+fn band_lines(entry: &ManifestEntry) -> [String; BAND_LINES as usize] {
+    let exif = date::exif_summary(entry);
+    [
+        exif.camera.clone().unwrap_or_default(),
+        exif.lens.clone().unwrap_or_default(),
+        exposure_line(&exif),
+        exif.datetime.clone().unwrap_or_default(),
+    ]
+}
+
+/// Trims `line` (with a trailing ellipsis) until it fits `max_width`.
+fn fit_line(line: &str, scale: u32, max_width: u32) -> String {
+    if text::text_width(line, scale) <= max_width {
+        return line.to_string();
+    }
+    let mut fitted: String = line.to_string();
+    while fitted.pop().is_some() {
+        let candidate = format!("{}…", fitted.trim_end());
+        if text::text_width(&candidate, scale) <= max_width {
+            return candidate;
+        }
+    }
+    String::new()
+}
+
+/// Renders the contact sheet to `output_path`.
+pub fn create_contact_sheet(
+    entries: &[ManifestEntry],
+    args: &crate::Args,
+    output_path: &str,
+    run: &mut RunSummary,
+) -> error::Result<()> {
+    if entries.is_empty() {
+        return Err(Error::NoImages);
+    }
+    let cell_size = args.cell_size;
+    let scale = cmp::max(1, cell_size / 200);
+    let pad = scale * 2;
+    let band_height = BAND_LINES * text::line_height(scale) + 2 * pad;
+    let slot_height = cell_size + band_height;
+    let n = entries.len() as u32;
+    let ncols = cmp::max(1, (n as f64).sqrt().ceil() as u32);
+    let nrows = n.div_ceil(ncols);
+    let width = ncols * cell_size;
+    let height = nrows * slot_height;
+    tracing::debug!(
+        "contact sheet: {}x{} grid, {} px bands, canvas {}x{} px",
+        ncols, nrows, band_height, width, height
+    );
+    run.total_images = entries.len();
+    run.grid_cols = ncols;
+    run.grid_rows = nrows;
+    run.canvas_width = width;
+    run.canvas_height = height;
+
+    let num_pixels = (width as u64 * height as u64) as usize;
+    let file = tempfile()?;
+    file.set_len(num_pixels as u64 * 4)?;
+    crate::resources::add_temp(num_pixels as u64 * 4);
+    let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+    crate::background::fill(&mut mmap, (width, height));
+
+    let composite_start = std::time::Instant::now();
+    for (index, entry) in entries.iter().enumerate() {
+        let cell_x = (index as u32 % ncols) * cell_size;
+        let cell_y = (index as u32 / ncols) * slot_height;
+        match entry.load_image() {
+            Ok(img) => {
+                crate::paste_image(
+                    &mut mmap,
+                    (width, height),
+                    (cell_x, cell_y, cell_size, cell_size),
+                    &img,
+                );
+            }
+            Err(e) => {
+                if args.strict || args.on_error == crate::OnError::Abort {
+                    return Err(Error::Decode(entry.path.clone(), e));
+                }
+                tracing::error!("Error processing {:?}: {}", entry.path, e);
+                run.skip(&entry.path, &e);
+                if args.on_error == crate::OnError::Placeholder {
+                    crate::draw_placeholder(
+                        &mut mmap,
+                        (width, height),
+                        (cell_x, cell_y, cell_size, cell_size),
+                        cell_size,
+                        &entry.path,
+                    );
+                }
+            }
+        }
+
+        // The metadata band: a light field with one line per EXIF group.
+        let band_y = cell_y + cell_size;
+        for y in band_y..band_y + band_height {
+            for x in cell_x..cell_x + cell_size {
+                let at = ((y as u64 * width as u64 + x as u64) * 4) as usize;
+                mmap[at..at + 4].copy_from_slice(&[245, 245, 245, 255]);
+            }
+        }
+        for (line_no, line) in band_lines(entry).iter().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            text::draw_text(
+                &mut mmap,
+                (width, height),
+                (
+                    (cell_x + pad) as i64,
+                    (band_y + pad + line_no as u32 * text::line_height(scale)) as i64,
+                ),
+                scale,
+                [32, 32, 32, 255],
+                &fit_line(line, scale, cell_size - 2 * pad),
+            );
+        }
+    }
+    mmap.flush()?;
+    run.phase_seconds
+        .insert("composite".to_string(), composite_start.elapsed().as_secs_f64());
+
+    let encode_start = std::time::Instant::now();
+    crate::save_canvas(&mmap, (width, height), args, output_path)?;
+    run.phase_seconds
+        .insert("encode".to_string(), encode_start.elapsed().as_secs_f64());
+    run.output_file = output_path.to_string();
+    run.output_bytes = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!("Contact sheet saved to '{}'", output_path);
+    Ok(())
+}
@@ -0,0 +1,491 @@
+//! Capture-date extraction for the date-aware layouts.
+//!
+//! JPEGs are checked for an EXIF `DateTimeOriginal` (a tiny purpose-built
+//! scan of the APP1 segment — no EXIF crate needed for a handful of
+//! tags); everything else falls back to the file's modification time.
+//! The same scan feeds the rating, the contact sheet's camera and
+//! exposure fields, and the label templates.
+
+use crate::manifest::ManifestEntry;
+use std::time::UNIX_EPOCH;
+
+/// A calendar day, as (year, month, day).
+pub type Day = (i32, u32, u32);
+
+static DETERMINISTIC: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Registers --deterministic: capture dates then come from EXIF alone,
+/// since mtimes differ between otherwise identical checkouts. Call once
+/// at startup.
+pub fn configure_deterministic() {
+    DETERMINISTIC.store(true, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// Whether --deterministic is active.
+pub fn deterministic() -> bool {
+    DETERMINISTIC.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Parses a `YYYY-MM-DD` date (as --since/--until take it) as a Day.
+pub fn parse_day(spec: &str) -> Option<Day> {
+    let mut parts = spec.split('-');
+    let y: i32 = parts.next()?.parse().ok()?;
+    let m: u32 = parts.next()?.parse().ok()?;
+    let d: u32 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() || !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+        return None;
+    }
+    Some((y, m, d))
+}
+
+/// Reads the two-byte integer at `at` with the TIFF block's byte order.
+fn read_u16(data: &[u8], at: usize, le: bool) -> Option<u16> {
+    let bytes: [u8; 2] = data.get(at..at + 2)?.try_into().ok()?;
+    Some(if le {
+        u16::from_le_bytes(bytes)
+    } else {
+        u16::from_be_bytes(bytes)
+    })
+}
+
+/// Reads the four-byte integer at `at` with the TIFF block's byte order.
+fn read_u32(data: &[u8], at: usize, le: bool) -> Option<u32> {
+    let bytes: [u8; 4] = data.get(at..at + 4)?.try_into().ok()?;
+    Some(if le {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    })
+}
+
+/// Scans one IFD for `tag`, returning the offset of its ASCII value, or
+/// the value of a LONG tag (for the ExifIFD pointer).
+fn find_tag(tiff: &[u8], ifd: usize, tag: u16, le: bool) -> Option<u32> {
+    find_entry(tiff, ifd, tag, le).and_then(|(_, _, at)| read_u32(tiff, at, le))
+}
+
+/// Scans one IFD for `tag`, returning its (field type, value count,
+/// offset of the four-byte value field).
+fn find_entry(tiff: &[u8], ifd: usize, tag: u16, le: bool) -> Option<(u16, u32, usize)> {
+    let count = read_u16(tiff, ifd, le)? as usize;
+    for i in 0..count {
+        let entry = ifd + 2 + i * 12;
+        if read_u16(tiff, entry, le)? == tag {
+            return Some((
+                read_u16(tiff, entry + 2, le)?,
+                read_u32(tiff, entry + 4, le)?,
+                entry + 8,
+            ));
+        }
+    }
+    None
+}
+
+/// Reads an ASCII tag; the value sits inline when it fits in four bytes,
+/// behind an offset otherwise. Trailing NULs and whitespace are dropped.
+fn ascii_tag(tiff: &[u8], ifd: usize, tag: u16, le: bool) -> Option<String> {
+    let (field_type, count, at) = find_entry(tiff, ifd, tag, le)?;
+    if field_type != 2 {
+        return None;
+    }
+    let start = if count <= 4 { at } else { read_u32(tiff, at, le)? as usize };
+    let bytes = tiff.get(start..start + count as usize)?;
+    let text = std::str::from_utf8(bytes).ok()?.trim_end_matches('\0').trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Reads a RATIONAL tag as (numerator, denominator).
+fn rational_tag(tiff: &[u8], ifd: usize, tag: u16, le: bool) -> Option<(u32, u32)> {
+    let (field_type, _, at) = find_entry(tiff, ifd, tag, le)?;
+    if field_type != 5 {
+        return None;
+    }
+    let value = read_u32(tiff, at, le)? as usize;
+    Some((read_u32(tiff, value, le)?, read_u32(tiff, value + 4, le)?))
+}
+
+/// Reads a SHORT (inline, first value) or LONG tag as an integer.
+fn integer_tag(tiff: &[u8], ifd: usize, tag: u16, le: bool) -> Option<u32> {
+    let (field_type, _, at) = find_entry(tiff, ifd, tag, le)?;
+    match field_type {
+        3 => read_u16(tiff, at, le).map(u32::from),
+        4 => read_u32(tiff, at, le),
+        _ => None,
+    }
+}
+
+/// Camera and exposure EXIF fields, as shown on the contact sheet.
+#[derive(Default)]
+pub struct ExifSummary {
+    /// Make and model, the make's duplicate prefix dropped.
+    pub camera: Option<String>,
+    /// LensModel from the Exif sub-IFD.
+    pub lens: Option<String>,
+    /// Focal length in millimetres.
+    pub focal_length: Option<f64>,
+    /// FNumber, e.g. 2.8.
+    pub aperture: Option<f64>,
+    /// ExposureTime as (numerator, denominator), kept rational so
+    /// "1/250" survives formatting.
+    pub exposure: Option<(u32, u32)>,
+    pub iso: Option<u32>,
+    /// DateTimeOriginal (or IFD0's DateTime), as written: "YYYY:MM:DD HH:MM:SS".
+    pub datetime: Option<String>,
+}
+
+/// Pulls the contact-sheet EXIF fields from an entry's bytes; every
+/// field is optional and non-JPEG files yield an empty summary.
+pub fn exif_summary(entry: &ManifestEntry) -> ExifSummary {
+    let bytes = match &entry.data {
+        Some(bytes) => std::borrow::Cow::Borrowed(bytes.as_slice()),
+        None => match std::fs::read(&entry.path) {
+            Ok(bytes) => std::borrow::Cow::Owned(bytes),
+            Err(_) => return ExifSummary::default(),
+        },
+    };
+    let Some((tiff, le)) = tiff_block(&bytes) else {
+        return ExifSummary::default();
+    };
+    let Some(ifd0) = read_u32(tiff, 4, le).map(|at| at as usize) else {
+        return ExifSummary::default();
+    };
+    let exif_ifd = find_tag(tiff, ifd0, 0x8769, le).map(|at| at as usize);
+
+    let make = ascii_tag(tiff, ifd0, 0x010F, le);
+    let model = ascii_tag(tiff, ifd0, 0x0110, le);
+    let camera = match (make, model) {
+        // Many models repeat the make ("Canon Canon EOS R5"); drop it.
+        (Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => model.or(make),
+    };
+    let mut summary = ExifSummary {
+        camera,
+        ..ExifSummary::default()
+    };
+    if let Some(exif) = exif_ifd {
+        summary.lens = ascii_tag(tiff, exif, 0xA434, le);
+        summary.focal_length = rational_tag(tiff, exif, 0x920A, le)
+            .filter(|(_, den)| *den != 0)
+            .map(|(num, den)| num as f64 / den as f64);
+        summary.aperture = rational_tag(tiff, exif, 0x829D, le)
+            .filter(|(_, den)| *den != 0)
+            .map(|(num, den)| num as f64 / den as f64);
+        summary.exposure = rational_tag(tiff, exif, 0x829A, le).filter(|(_, den)| *den != 0);
+        summary.iso = integer_tag(tiff, exif, 0x8827, le);
+        summary.datetime = ascii_tag(tiff, exif, 0x9003, le);
+    }
+    if summary.datetime.is_none() {
+        summary.datetime = ascii_tag(tiff, ifd0, 0x0132, le);
+    }
+    summary
+}
+
+/// Parses `YYYY:MM:DD ...` as written by EXIF date tags.
+fn parse_exif_date(value: &[u8]) -> Option<Day> {
+    let text = std::str::from_utf8(value.get(..10)?).ok()?;
+    let mut parts = text.split(':');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let day = parts.next()?.parse().ok()?;
+    if (1..=12).contains(&month) && (1..=31).contains(&day) {
+        Some((year, month, day))
+    } else {
+        None
+    }
+}
+
+/// Finds the TIFF block inside a JPEG's APP1/Exif segment, returning it
+/// with its byte order flag.
+fn tiff_block(bytes: &[u8]) -> Option<(&[u8], bool)> {
+    if bytes.get(..2)? != [0xFF, 0xD8] {
+        return None;
+    }
+    // Walk the JPEG segments looking for APP1/Exif.
+    let mut at = 2;
+    let tiff = loop {
+        if bytes.get(at)? != &0xFF {
+            return None;
+        }
+        let marker = *bytes.get(at + 1)?;
+        if marker == 0xDA {
+            return None; // Start of scan: no EXIF before the image data.
+        }
+        let len = u16::from_be_bytes(bytes.get(at + 2..at + 4)?.try_into().ok()?) as usize;
+        if marker == 0xE1 && bytes.get(at + 4..at + 10)? == b"Exif\0\0" {
+            break bytes.get(at + 10..at + 2 + len)?;
+        }
+        at += 2 + len;
+    };
+
+    let le = match tiff.get(..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    Some((tiff, le))
+}
+
+/// Extracts `DateTimeOriginal` (or plain `DateTime`) from a JPEG's EXIF
+/// block, if it has one.
+fn exif_date(bytes: &[u8]) -> Option<Day> {
+    let (tiff, le) = tiff_block(bytes)?;
+    let ifd0 = read_u32(tiff, 4, le)? as usize;
+    // Prefer DateTimeOriginal in the Exif sub-IFD; fall back to IFD0's
+    // DateTime (last modified in camera, but better than nothing).
+    let date_at = find_tag(tiff, ifd0, 0x8769, le)
+        .and_then(|exif_ifd| find_tag(tiff, exif_ifd as usize, 0x9003, le))
+        .or_else(|| find_tag(tiff, ifd0, 0x0132, le))?;
+    parse_exif_date(tiff.get(date_at as usize..)?)
+}
+
+/// The embedded EXIF thumbnail — IFD1's JPEGInterchangeFormat blob — if
+/// the JPEG carries one (--use-exif-thumbs reads it in place of the
+/// full image).
+pub fn exif_thumbnail(bytes: &[u8]) -> Option<Vec<u8>> {
+    let (tiff, le) = tiff_block(bytes)?;
+    let ifd0 = read_u32(tiff, 4, le)? as usize;
+    // IFD1 (the thumbnail IFD) is linked from the end of IFD0's entry
+    // list.
+    let count = read_u16(tiff, ifd0, le)? as usize;
+    let ifd1 = read_u32(tiff, ifd0 + 2 + count * 12, le)? as usize;
+    if ifd1 == 0 {
+        return None;
+    }
+    let offset = find_tag(tiff, ifd1, 0x0201, le)? as usize;
+    let length = find_tag(tiff, ifd1, 0x0202, le)? as usize;
+    let blob = tiff.get(offset..offset + length)?;
+    (blob.get(..2)? == [0xFF, 0xD8]).then(|| blob.to_vec())
+}
+
+/// Converts days since the Unix epoch to a civil date
+/// (Howard Hinnant's `civil_from_days` algorithm).
+fn civil_from_days(days: i64) -> Day {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let year = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = if month <= 2 { year + 1 } else { year };
+    (year as i32, month as u32, day as u32)
+}
+
+/// Reads a GPS angle: three RATIONALs (degrees, minutes, seconds) as
+/// decimal degrees.
+fn gps_angle(tiff: &[u8], ifd: usize, tag: u16, le: bool) -> Option<f64> {
+    let (field_type, count, at) = find_entry(tiff, ifd, tag, le)?;
+    if field_type != 5 || count < 3 {
+        return None;
+    }
+    let value = read_u32(tiff, at, le)? as usize;
+    let mut parts = [0.0f64; 3];
+    for (i, part) in parts.iter_mut().enumerate() {
+        let num = read_u32(tiff, value + i * 8, le)?;
+        let den = read_u32(tiff, value + i * 8 + 4, le)?;
+        if den == 0 {
+            return None;
+        }
+        *part = num as f64 / den as f64;
+    }
+    Some(parts[0] + parts[1] / 60.0 + parts[2] / 3600.0)
+}
+
+/// The capture position from the GPS sub-IFD as (latitude, longitude)
+/// in signed decimal degrees, if the image carries one.
+pub fn gps_coords(entry: &ManifestEntry) -> Option<(f64, f64)> {
+    let bytes = match &entry.data {
+        Some(bytes) => std::borrow::Cow::Borrowed(bytes.as_slice()),
+        None => std::borrow::Cow::Owned(std::fs::read(&entry.path).ok()?),
+    };
+    let (tiff, le) = tiff_block(&bytes)?;
+    let ifd0 = read_u32(tiff, 4, le)? as usize;
+    let gps = find_tag(tiff, ifd0, 0x8825, le)? as usize;
+    let mut lat = gps_angle(tiff, gps, 0x0002, le)?;
+    let mut lon = gps_angle(tiff, gps, 0x0004, le)?;
+    if ascii_tag(tiff, gps, 0x0001, le).as_deref() == Some("S") {
+        lat = -lat;
+    }
+    if ascii_tag(tiff, gps, 0x0003, le).as_deref() == Some("W") {
+        lon = -lon;
+    }
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return None;
+    }
+    Some((lat, lon))
+}
+
+/// The EXIF star rating (Windows tag 0x4746 in IFD0), 0-5, if present.
+pub fn rating(entry: &ManifestEntry) -> Option<u32> {
+    let bytes = match &entry.data {
+        Some(bytes) => std::borrow::Cow::Borrowed(bytes.as_slice()),
+        None => std::borrow::Cow::Owned(std::fs::read(&entry.path).ok()?),
+    };
+    let (tiff, le) = tiff_block(&bytes)?;
+    let ifd0 = read_u32(tiff, 4, le)? as usize;
+    // SHORT values sit inline in the entry's value field.
+    let raw = find_tag(tiff, ifd0, 0x4746, le)?;
+    let value = if le { raw & 0xFFFF } else { raw >> 16 };
+    (value <= 5).then_some(value)
+}
+
+/// Days in a month, leap years included.
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        _ => {
+            if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) {
+                29
+            } else {
+                28
+            }
+        }
+    }
+}
+
+/// Converts a civil date to days since the Unix epoch (the inverse of
+/// `civil_from_days`).
+pub fn days_from_civil((year, month, day): Day) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year } as i64;
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Weekday of a civil date with Monday as 0 (ISO order).
+pub fn weekday((year, month, day): Day) -> u32 {
+    (days_from_civil((year, month, day)) + 3).rem_euclid(7) as u32
+}
+
+/// Month and weekday names for the `%B`/`%b`/`%A`/`%a` format fields,
+/// Monday first to match [`weekday`].
+struct Locale {
+    months: [&'static str; 12],
+    weekdays: [&'static str; 7],
+}
+
+/// The built-in --locale tables; English is the default.
+const LOCALES: &[(&str, Locale)] = &[
+    ("en", Locale {
+        months: ["January", "February", "March", "April", "May", "June", "July",
+            "August", "September", "October", "November", "December"],
+        weekdays: ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"],
+    }),
+    ("de", Locale {
+        months: ["Januar", "Februar", "März", "April", "Mai", "Juni", "Juli",
+            "August", "September", "Oktober", "November", "Dezember"],
+        weekdays: ["Montag", "Dienstag", "Mittwoch", "Donnerstag", "Freitag", "Samstag", "Sonntag"],
+    }),
+    ("es", Locale {
+        months: ["enero", "febrero", "marzo", "abril", "mayo", "junio", "julio",
+            "agosto", "septiembre", "octubre", "noviembre", "diciembre"],
+        weekdays: ["lunes", "martes", "miércoles", "jueves", "viernes", "sábado", "domingo"],
+    }),
+    ("fr", Locale {
+        months: ["janvier", "février", "mars", "avril", "mai", "juin", "juillet",
+            "août", "septembre", "octobre", "novembre", "décembre"],
+        weekdays: ["lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche"],
+    }),
+    ("it", Locale {
+        months: ["gennaio", "febbraio", "marzo", "aprile", "maggio", "giugno", "luglio",
+            "agosto", "settembre", "ottobre", "novembre", "dicembre"],
+        weekdays: ["lunedì", "martedì", "mercoledì", "giovedì", "venerdì", "sabato", "domenica"],
+    }),
+    ("pt", Locale {
+        months: ["janeiro", "fevereiro", "março", "abril", "maio", "junho", "julho",
+            "agosto", "setembro", "outubro", "novembro", "dezembro"],
+        weekdays: ["segunda-feira", "terça-feira", "quarta-feira", "quinta-feira",
+            "sexta-feira", "sábado", "domingo"],
+    }),
+];
+
+static LOCALE: std::sync::OnceLock<&'static Locale> = std::sync::OnceLock::new();
+
+/// Registers --locale for the month and weekday names format_day hands
+/// out. Call once at startup.
+pub fn configure_locale(code: &str) -> crate::error::Result<()> {
+    let locale = LOCALES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(code.trim()))
+        .map(|(_, locale)| locale)
+        .ok_or_else(|| {
+            crate::error::Error::Usage(format!(
+                "unknown --locale {:?}; available: {}",
+                code,
+                LOCALES.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+            ))
+        })?;
+    let _ = LOCALE.set(locale);
+    Ok(())
+}
+
+fn locale() -> &'static Locale {
+    LOCALE.get().copied().unwrap_or(&LOCALES[0].1)
+}
+
+/// Renders a strftime-style format against a day: `%Y`/`%y` year,
+/// `%m`/`%d` zero-padded month and day, `%e` unpadded day, `%B`/`%b`
+/// month name, `%A`/`%a` weekday name (full and three-letter, in the
+/// --locale language), `%j` day of year and `%%` a literal percent.
+/// Unknown fields pass through unchanged.
+pub fn format_day((year, month, day): Day, format: &str) -> String {
+    let locale = locale();
+    let month_name = locale.months.get(month.wrapping_sub(1) as usize).copied().unwrap_or("?");
+    let weekday_name = locale.weekdays[weekday((year, month, day)) as usize];
+    let short = |name: &str| name.chars().take(3).collect::<String>();
+    let mut out = String::with_capacity(format.len());
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => out.push_str(&format!("{:04}", year)),
+            Some('y') => out.push_str(&format!("{:02}", year.rem_euclid(100))),
+            Some('m') => out.push_str(&format!("{:02}", month)),
+            Some('d') => out.push_str(&format!("{:02}", day)),
+            Some('e') => out.push_str(&day.to_string()),
+            Some('B') => out.push_str(month_name),
+            Some('b') => out.push_str(&short(month_name)),
+            Some('A') => out.push_str(weekday_name),
+            Some('a') => out.push_str(&short(weekday_name)),
+            Some('j') => out.push_str(
+                &(days_from_civil((year, month, day)) - days_from_civil((year, 1, 1)) + 1)
+                    .to_string(),
+            ),
+            Some('%') => out.push('%'),
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+    out
+}
+
+/// The day an entry was captured: EXIF if available, mtime otherwise.
+/// In-memory entries (archives, S3) have no mtime, so only EXIF applies.
+pub fn capture_day(entry: &ManifestEntry) -> Option<Day> {
+    let from_exif = match &entry.data {
+        Some(bytes) => exif_date(bytes),
+        None => std::fs::read(&entry.path).ok().and_then(|b| exif_date(&b)),
+    };
+    if from_exif.is_some() {
+        return from_exif;
+    }
+    if deterministic() {
+        return None;
+    }
+    let mtime = std::fs::metadata(&entry.path).ok()?.modified().ok()?;
+    let secs = mtime.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+    Some(civil_from_days(secs.div_euclid(86_400)))
+}
@@ -0,0 +1,130 @@
+//! Diagonal layout (`--layout diagonal`): images drawn as 45°-rotated
+//! squares (isometric diamonds) on an interleaved lattice that tessellates
+//! the plane. Edge diamonds are clipped so the canvas stays a clean
+//! rectangle. The rotation is done by inverse-mapping each target pixel
+//! back into the cover-fitted source square with bilinear sampling.
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use crate::rotate::sample_bilinear;
+use crate::summary::RunSummary;
+use memmap2::MmapMut;
+use std::cmp;
+use tempfile::tempfile;
+
+/// Draws one diamond (a 45°-rotated square of diagonal `d`) centred at
+/// (`cx`, `cy`), clipping at the canvas edges.
+fn paste_diamond(
+    buf: &mut [u8],
+    (canvas_w, canvas_h): (u32, u32),
+    (cx, cy): (f64, f64),
+    d: f64,
+    img: &image::DynamicImage,
+) {
+    // The source square has side d/sqrt(2); cover-fit the image to it.
+    let side = (d / std::f64::consts::SQRT_2).round().max(1.0) as u32;
+    let source = img
+        .resize_to_fill(side, side, image::imageops::FilterType::Lanczos3)
+        .to_rgba8();
+    let half = d / 2.0;
+    let x_min = ((cx - half).floor().max(0.0)) as u32;
+    let x_max = ((cx + half).ceil().min(canvas_w as f64 - 1.0)) as u32;
+    let y_min = ((cy - half).floor().max(0.0)) as u32;
+    let y_max = ((cy + half).ceil().min(canvas_h as f64 - 1.0)) as u32;
+    let inv_sqrt2 = std::f64::consts::FRAC_1_SQRT_2;
+    for y in y_min..=y_max {
+        for x in x_min..=x_max {
+            let dx = x as f64 - cx;
+            let dy = y as f64 - cy;
+            if dx.abs() + dy.abs() > half {
+                continue;
+            }
+            // Rotate back by -45° into source coordinates.
+            let u = (dx + dy) * inv_sqrt2 + side as f64 / 2.0;
+            let v = (dy - dx) * inv_sqrt2 + side as f64 / 2.0;
+            if let Some(pixel) = sample_bilinear(&source, u, v) {
+                let index = ((y * canvas_w + x) * 4) as usize;
+                buf[index..index + 4].copy_from_slice(&pixel);
+            }
+        }
+    }
+}
+
+/// Renders the diagonal collage to `output_path`.
+pub fn create_diagonal(
+    entries: &[ManifestEntry],
+    args: &crate::Args,
+    output_path: &str,
+    run: &mut RunSummary,
+) -> error::Result<()> {
+    if entries.is_empty() {
+        return Err(Error::NoImages);
+    }
+    let d = args.cell_size as f64;
+    let n = entries.len() as u32;
+    let ncols = cmp::max(1, (n as f64).sqrt().ceil() as u32);
+    let width = ncols * args.cell_size;
+
+    // Interleaved diamond lattice: even half-rows sit on the edges (and
+    // clip), odd half-rows are centred between them.
+    let mut centers: Vec<(f64, f64)> = Vec::with_capacity(entries.len());
+    let mut half_row = 0u32;
+    while (centers.len() as u32) < n {
+        let y = half_row as f64 * d / 2.0 + d / 2.0;
+        let (start, count) = if half_row.is_multiple_of(2) {
+            (d / 2.0, ncols)
+        } else {
+            (0.0, ncols + 1)
+        };
+        for i in 0..count {
+            if (centers.len() as u32) == n {
+                break;
+            }
+            centers.push((start + i as f64 * d, y));
+        }
+        half_row += 1;
+    }
+    let height = ((half_row as f64 + 1.0) * d / 2.0).ceil() as u32;
+    tracing::debug!(
+        "diagonal layout: {} diamonds, canvas {}x{} px",
+        n, width, height
+    );
+    run.total_images = entries.len();
+    run.grid_cols = ncols;
+    run.grid_rows = half_row.div_ceil(2);
+    run.canvas_width = width;
+    run.canvas_height = height;
+
+    let num_pixels = (width as u64 * height as u64) as usize;
+    let file = tempfile()?;
+    file.set_len(num_pixels as u64 * 4)?;
+    crate::resources::add_temp(num_pixels as u64 * 4);
+    let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+    crate::background::fill(&mut mmap, (width, height));
+
+    let composite_start = std::time::Instant::now();
+    for (entry, &(cx, cy)) in entries.iter().zip(&centers) {
+        match entry.load_image() {
+            Ok(img) => paste_diamond(&mut mmap, (width, height), (cx, cy), d, &img),
+            Err(e) => {
+                if args.strict || args.on_error == crate::OnError::Abort {
+                    return Err(Error::Decode(entry.path.clone(), e));
+                }
+                tracing::error!("Error processing {:?}: {}", entry.path, e);
+                run.skip(&entry.path, &e);
+            }
+        }
+    }
+    mmap.flush()?;
+    run.phase_seconds
+        .insert("composite".to_string(), composite_start.elapsed().as_secs_f64());
+
+    let encode_start = std::time::Instant::now();
+    crate::save_canvas(&mmap, (width, height), args, output_path)?;
+    run.phase_seconds
+        .insert("encode".to_string(), encode_start.elapsed().as_secs_f64());
+    run.output_file = output_path.to_string();
+    run.output_bytes = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!("Diagonal collage saved to '{}'", output_path);
+    Ok(())
+}
@@ -0,0 +1,139 @@
+//! `diff` subcommand: what changed between two collage outputs.
+//!
+//! Automated pipelines regenerate collages on a schedule and want to
+//! audit the result: both images are walked on the cell grid and a cell
+//! counts as changed when its pixels differ beyond a small tolerance
+//! (re-encoding wiggle is not a change). The report comes out as JSON
+//! on stdout — changed cells by row/column, plus sources added and
+//! removed when the two manifests are given — and optionally as a
+//! visual diff image, the newer collage with changed cells outlined.
+
+use std::collections::HashSet;
+
+use crate::error::{self, Error};
+
+/// Mean per-channel difference (0-255) above which a cell has changed;
+/// below it, the gap is put down to encoder noise.
+const TOLERANCE: f64 = 2.0;
+/// Outline width in the visual diff, in pixels.
+const OUTLINE: u32 = 4;
+/// Outline colour: an alarm red that survives most collages.
+const HIGHLIGHT: [u8; 4] = [224, 32, 32, 255];
+
+/// Compares `before` and `after` cell by cell and prints the report.
+pub fn run_diff(
+    before: &str,
+    after: &str,
+    manifests: (Option<&str>, Option<&str>),
+    cell_size: u32,
+    image_path: Option<&str>,
+) -> error::Result<()> {
+    let open = |path: &str| {
+        image::open(path)
+            .map(|img| img.to_rgba8())
+            .map_err(|e| Error::Usage(format!("cannot open {:?}: {}", path, e)))
+    };
+    let old = open(before)?;
+    let mut new = open(after)?;
+    // Walk the larger grid so growth and shrinkage both show up; cells
+    // outside either image compare against nothing and count changed.
+    let cols = new.width().max(old.width()).div_ceil(cell_size);
+    let rows = new.height().max(old.height()).div_ceil(cell_size);
+    let mut changed = Vec::new();
+    for row in 0..rows {
+        for col in 0..cols {
+            if cell_difference(&old, &new, (col, row), cell_size) > TOLERANCE {
+                changed.push((row, col));
+            }
+        }
+    }
+    let (added, removed) = match manifests {
+        (Some(before_manifest), Some(after_manifest)) => {
+            let old_paths: HashSet<String> = manifest_paths(before_manifest)?;
+            let new_paths: HashSet<String> = manifest_paths(after_manifest)?;
+            let mut added: Vec<&String> = new_paths.difference(&old_paths).collect();
+            let mut removed: Vec<&String> = old_paths.difference(&new_paths).collect();
+            added.sort();
+            removed.sort();
+            (
+                added.into_iter().cloned().collect(),
+                removed.into_iter().cloned().collect(),
+            )
+        }
+        _ => (Vec::new(), Vec::new()),
+    };
+    if let Some(image_path) = image_path {
+        for &(row, col) in &changed {
+            outline_cell(&mut new, (col, row), cell_size);
+        }
+        new.save(image_path).map_err(|e| Error::output(image_path, e))?;
+        tracing::info!("Diff image saved to '{}'", image_path);
+    }
+    let report = serde_json::json!({
+        "cells": { "rows": rows, "cols": cols, "changed": changed.len() },
+        "changed": changed
+            .iter()
+            .map(|&(row, col)| serde_json::json!({ "row": row, "col": col }))
+            .collect::<Vec<_>>(),
+        "added": added,
+        "removed": removed,
+    });
+    println!("{}", serde_json::to_string_pretty(&report).expect("report serializes"));
+    Ok(())
+}
+
+/// The source paths listed in a manifest, for added/removed reporting.
+fn manifest_paths(path: &str) -> error::Result<HashSet<String>> {
+    Ok(crate::manifest::load_manifest(path)?
+        .into_iter()
+        .map(|entry| entry.path.to_string_lossy().into_owned())
+        .collect())
+}
+
+/// Mean absolute per-channel difference across one cell. Pixels outside
+/// either image compare as maximally different, so size changes are
+/// flagged rather than silently cropped out of the walk.
+fn cell_difference(
+    old: &image::RgbaImage,
+    new: &image::RgbaImage,
+    (col, row): (u32, u32),
+    cell_size: u32,
+) -> f64 {
+    let (x0, y0) = (col * cell_size, row * cell_size);
+    let mut total = 0u64;
+    let mut count = 0u64;
+    for y in y0..(y0 + cell_size) {
+        for x in x0..(x0 + cell_size) {
+            let a = (x < old.width() && y < old.height()).then(|| old.get_pixel(x, y));
+            let b = (x < new.width() && y < new.height()).then(|| new.get_pixel(x, y));
+            total += match (a, b) {
+                (Some(a), Some(b)) => {
+                    (0..4).map(|c| a[c].abs_diff(b[c]) as u64).sum()
+                }
+                (None, None) => continue,
+                _ => 255 * 4,
+            };
+            count += 4;
+        }
+    }
+    if count == 0 {
+        return 0.0;
+    }
+    total as f64 / count as f64
+}
+
+/// Draws the highlight outline just inside one cell's edge.
+fn outline_cell(img: &mut image::RgbaImage, (col, row): (u32, u32), cell_size: u32) {
+    let (x0, y0) = (col * cell_size, row * cell_size);
+    for y in y0..(y0 + cell_size).min(img.height()) {
+        for x in x0..(x0 + cell_size).min(img.width()) {
+            let edge = (x - x0)
+                .min(y - y0)
+                .min(x0 + cell_size - 1 - x)
+                .min(y0 + cell_size - 1 - y);
+            if edge < OUTLINE {
+                img.put_pixel(x, y, image::Rgba(HIGHLIGHT));
+            }
+        }
+    }
+}
@@ -0,0 +1,286 @@
+//! Embedding layout (`--layout embedding`): a 2D map of the library.
+//!
+//! Each image is summarised by a small colour-statistics vector, the
+//! vectors are projected to 2D with PCA (top two principal components by
+//! power iteration — no external linear algebra), and thumbnails land at
+//! their projected coordinates on a sparse canvas. Similar images end up
+//! near each other; a few rounds of pairwise nudging pull overlapping
+//! thumbnails apart so clusters stay readable instead of stacking.
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use crate::summary::RunSummary;
+use memmap2::MmapMut;
+use image::DynamicImage;
+use tempfile::tempfile;
+
+/// Feature grid edge: mean RGB over an NxN partition of the thumbnail.
+const FEATURE_GRID: u32 = 4;
+/// Dimensionality of the feature vector (3 channels per grid cell).
+const DIMS: usize = (FEATURE_GRID * FEATURE_GRID * 3) as usize;
+/// Nudging passes; each pass pushes every overlapping pair apart a bit.
+const NUDGE_PASSES: usize = 60;
+
+/// Mean RGB per cell of a FEATURE_GRID x FEATURE_GRID partition, scaled
+/// to 0..1. Cheap, but enough for sky/forest/indoor-style clustering.
+fn features(img: &DynamicImage) -> [f64; DIMS] {
+    let thumb = img
+        .thumbnail_exact(FEATURE_GRID * 8, FEATURE_GRID * 8)
+        .to_rgb8();
+    let cell = 8u32;
+    let mut out = [0.0; DIMS];
+    for gy in 0..FEATURE_GRID {
+        for gx in 0..FEATURE_GRID {
+            let mut sums = [0u64; 3];
+            for y in 0..cell {
+                for x in 0..cell {
+                    let p = thumb.get_pixel(gx * cell + x, gy * cell + y);
+                    for c in 0..3 {
+                        sums[c] += p[c] as u64;
+                    }
+                }
+            }
+            let base = ((gy * FEATURE_GRID + gx) * 3) as usize;
+            for c in 0..3 {
+                out[base + c] = sums[c] as f64 / (cell * cell) as f64 / 255.0;
+            }
+        }
+    }
+    out
+}
+
+/// Top two principal components of the (centred) feature vectors, by
+/// power iteration on the covariance matrix with deflation for the
+/// second component. Returns one (x, y) per vector.
+fn project(vectors: &[[f64; DIMS]]) -> Vec<(f64, f64)> {
+    let n = vectors.len();
+    let mut mean = [0.0; DIMS];
+    for v in vectors {
+        for d in 0..DIMS {
+            mean[d] += v[d];
+        }
+    }
+    for m in &mut mean {
+        *m /= n as f64;
+    }
+    let centred: Vec<[f64; DIMS]> = vectors
+        .iter()
+        .map(|v| {
+            let mut c = [0.0; DIMS];
+            for d in 0..DIMS {
+                c[d] = v[d] - mean[d];
+            }
+            c
+        })
+        .collect();
+
+    let mut cov = vec![[0.0; DIMS]; DIMS];
+    for v in &centred {
+        for (i, row) in cov.iter_mut().enumerate() {
+            for (j, e) in row.iter_mut().enumerate() {
+                *e += v[i] * v[j];
+            }
+        }
+    }
+
+    let power_iterate = |cov: &[[f64; DIMS]]| {
+        // Deterministic start; any vector not orthogonal to the
+        // component converges.
+        let mut v = [0.0; DIMS];
+        for (d, e) in v.iter_mut().enumerate() {
+            *e = 1.0 + d as f64 * 0.01;
+        }
+        for _ in 0..100 {
+            let mut next = [0.0; DIMS];
+            for (i, n) in next.iter_mut().enumerate() {
+                for j in 0..DIMS {
+                    *n += cov[i][j] * v[j];
+                }
+            }
+            let norm = next.iter().map(|x| x * x).sum::<f64>().sqrt();
+            if norm < 1e-12 {
+                break;
+            }
+            for n in &mut next {
+                *n /= norm;
+            }
+            v = next;
+        }
+        v
+    };
+
+    let pc1 = power_iterate(&cov);
+    // Deflate: remove the first component's variance, then iterate again.
+    let lambda1: f64 = {
+        let mut cv = [0.0; DIMS];
+        for (i, e) in cv.iter_mut().enumerate() {
+            for j in 0..DIMS {
+                *e += cov[i][j] * pc1[j];
+            }
+        }
+        (0..DIMS).map(|i| pc1[i] * cv[i]).sum()
+    };
+    for (i, row) in cov.iter_mut().enumerate() {
+        for (j, e) in row.iter_mut().enumerate() {
+            *e -= lambda1 * pc1[i] * pc1[j];
+        }
+    }
+    let pc2 = power_iterate(&cov);
+
+    centred
+        .iter()
+        .map(|v| {
+            let x = (0..DIMS).map(|d| v[d] * pc1[d]).sum();
+            let y = (0..DIMS).map(|d| v[d] * pc2[d]).sum();
+            (x, y)
+        })
+        .collect()
+}
+
+/// Pushes overlapping thumbnails apart, a little per pass, keeping every
+/// centre inside the usable area.
+fn nudge(positions: &mut [(f64, f64)], cell: f64, max_x: f64, max_y: f64) {
+    for _ in 0..NUDGE_PASSES {
+        let mut moved = false;
+        for i in 0..positions.len() {
+            for j in i + 1..positions.len() {
+                let dx = positions[j].0 - positions[i].0;
+                let dy = positions[j].1 - positions[i].1;
+                if dx.abs() >= cell || dy.abs() >= cell {
+                    continue;
+                }
+                // Separate along the axis already farthest apart; exact
+                // ties get an arbitrary but deterministic horizontal push.
+                let (push_x, push_y) = if dx.abs() >= dy.abs() {
+                    ((cell - dx.abs()) / 2.0 * dx.signum().max(0.0).mul_add(2.0, -1.0), 0.0)
+                } else {
+                    (0.0, (cell - dy.abs()) / 2.0 * dy.signum().max(0.0).mul_add(2.0, -1.0))
+                };
+                positions[i].0 = (positions[i].0 - push_x * 0.5).clamp(0.0, max_x);
+                positions[i].1 = (positions[i].1 - push_y * 0.5).clamp(0.0, max_y);
+                positions[j].0 = (positions[j].0 + push_x * 0.5).clamp(0.0, max_x);
+                positions[j].1 = (positions[j].1 + push_y * 0.5).clamp(0.0, max_y);
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+}
+
+/// Renders the embedding map to `output_path`.
+pub fn create_embedding(
+    entries: &[ManifestEntry],
+    args: &crate::Args,
+    output_path: &str,
+    run: &mut RunSummary,
+) -> error::Result<()> {
+    if entries.is_empty() {
+        return Err(Error::NoImages);
+    }
+    let cell_size = args.cell_size;
+
+    // First pass: feature vectors. Unreadable images have no place on
+    // the map; they follow the usual --on-error policy and are skipped.
+    let mut placeable: Vec<&ManifestEntry> = Vec::new();
+    let mut vectors: Vec<[f64; DIMS]> = Vec::new();
+    for entry in entries {
+        crate::cancel::check()?;
+        match entry.load_image() {
+            Ok(img) => {
+                vectors.push(features(&img));
+                placeable.push(entry);
+            }
+            Err(e) => {
+                if args.strict || args.on_error == crate::OnError::Abort {
+                    return Err(Error::Decode(entry.path.clone(), e));
+                }
+                tracing::error!("Error processing {:?}: {}", entry.path, e);
+                run.skip(&entry.path, &e);
+            }
+        }
+    }
+    if placeable.is_empty() {
+        return Err(Error::NoImages);
+    }
+
+    // A sparse canvas: roughly 3x the area per image a grid would use,
+    // so clusters read as clusters instead of a packed sheet.
+    let n = placeable.len() as u32;
+    let side_cells = ((n as f64).sqrt().ceil() as u32).max(1) * 2;
+    let width = side_cells * cell_size + 2 * cell_size;
+    let height = side_cells * cell_size + 2 * cell_size;
+
+    let projected = project(&vectors);
+    let (mut min_x, mut max_x) = (f64::MAX, f64::MIN);
+    let (mut min_y, mut max_y) = (f64::MAX, f64::MIN);
+    for &(x, y) in &projected {
+        min_x = min_x.min(x);
+        max_x = max_x.max(x);
+        min_y = min_y.min(y);
+        max_y = max_y.max(y);
+    }
+    let span_x = (max_x - min_x).max(1e-9);
+    let span_y = (max_y - min_y).max(1e-9);
+    let usable_x = (width - 2 * cell_size) as f64;
+    let usable_y = (height - 2 * cell_size) as f64;
+    let mut positions: Vec<(f64, f64)> = projected
+        .iter()
+        .map(|&(x, y)| {
+            (
+                (x - min_x) / span_x * usable_x,
+                (y - min_y) / span_y * usable_y,
+            )
+        })
+        .collect();
+    nudge(&mut positions, cell_size as f64, usable_x, usable_y);
+
+    tracing::debug!(
+        "embedding layout: {} images projected onto a {}x{} px map",
+        n, width, height
+    );
+    run.total_images = placeable.len();
+    run.canvas_width = width;
+    run.canvas_height = height;
+
+    let num_pixels = (width as u64 * height as u64) as usize;
+    let file = tempfile()?;
+    file.set_len(num_pixels as u64 * 4)?;
+    crate::resources::add_temp(num_pixels as u64 * 4);
+    let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+    crate::background::fill(&mut mmap, (width, height));
+
+    let composite_start = std::time::Instant::now();
+    for (entry, &(x, y)) in placeable.iter().zip(&positions) {
+        crate::cancel::check()?;
+        // The feature pass already decoded this image, so a failure here
+        // would be a race with the filesystem; treat it like any other.
+        match entry.load_image() {
+            Ok(img) => {
+                let px = (x as u32 + cell_size / 2).min(width - cell_size);
+                let py = (y as u32 + cell_size / 2).min(height - cell_size);
+                crate::paste_image(&mut mmap, (width, height), (px, py, cell_size, cell_size), &img);
+            }
+            Err(e) => {
+                if args.strict || args.on_error == crate::OnError::Abort {
+                    return Err(Error::Decode(entry.path.clone(), e));
+                }
+                tracing::error!("Error processing {:?}: {}", entry.path, e);
+                run.skip(&entry.path, &e);
+            }
+        }
+    }
+    mmap.flush()?;
+    run.phase_seconds
+        .insert("composite".to_string(), composite_start.elapsed().as_secs_f64());
+
+    let encode_start = std::time::Instant::now();
+    crate::save_canvas(&mmap, (width, height), args, output_path)?;
+    run.phase_seconds
+        .insert("encode".to_string(), encode_start.elapsed().as_secs_f64());
+    run.output_file = output_path.to_string();
+    run.output_bytes = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!("Embedding map saved to '{}'", output_path);
+    Ok(())
+}
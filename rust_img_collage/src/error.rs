@@ -0,0 +1,89 @@
+//! Crate-level error type and the process exit codes derived from it.
+//!
+//! Exit codes (stable, safe to script against):
+//!
+//! - `0` — success
+//! - `1` — any other error (I/O, bad manifest, server failures, ...)
+//! - `2` — usage errors (also used by clap for argument parse failures)
+//! - `3` — no usable images were found in the input
+//! - `4` — some images failed to decode (or the first failure under `--strict`)
+//! - `5` — the output file or a sibling artifact could not be written
+
+use std::fmt;
+use std::path::PathBuf;
+
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_NO_IMAGES: i32 = 3;
+pub const EXIT_PARTIAL: i32 = 4;
+pub const EXIT_OUTPUT_WRITE: i32 = 5;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Bad or missing command-line arguments.
+    Usage(String),
+    /// No usable images were found in the input.
+    NoImages,
+    /// Some images failed to decode and were replaced by blank cells.
+    PartialFailures(usize),
+    /// A single image failed to decode under `--strict`.
+    Decode(PathBuf, image::ImageError),
+    /// The output file (or a rendition, descriptor, ...) could not be written.
+    Output(String, String),
+    /// The build was cancelled through its cancellation token.
+    Cancelled,
+    /// Any other failure in the image pipeline.
+    Image(image::ImageError),
+    /// Any other I/O failure.
+    Io(std::io::Error),
+}
+
+impl Error {
+    /// Tags an error as an output-write failure for `path`.
+    pub fn output(path: &str, cause: impl fmt::Display) -> Self {
+        Error::Output(path.to_string(), cause.to_string())
+    }
+
+    /// The process exit code this error maps to.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Usage(_) => EXIT_USAGE,
+            Error::NoImages => EXIT_NO_IMAGES,
+            Error::PartialFailures(_) | Error::Decode(..) => EXIT_PARTIAL,
+            Error::Output(..) => EXIT_OUTPUT_WRITE,
+            Error::Cancelled | Error::Image(_) | Error::Io(_) => 1,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(msg) => write!(f, "{}", msg),
+            Error::NoImages => write!(f, "no usable images found"),
+            Error::PartialFailures(n) => {
+                write!(f, "{} image(s) failed to decode and were left blank", n)
+            }
+            Error::Decode(path, e) => write!(f, "failed to decode {:?}: {}", path, e),
+            Error::Output(path, cause) => write!(f, "failed to write '{}': {}", path, cause),
+            Error::Cancelled => write!(f, "build cancelled"),
+            Error::Image(e) => write!(f, "{}", e),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        Error::Image(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
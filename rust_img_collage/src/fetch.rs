@@ -0,0 +1,118 @@
+use crate::manifest::ManifestEntry;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Number of download attempts per URL before giving up.
+const RETRIES: u32 = 3;
+
+/// Returns true if the entry path is an http(s) URL.
+pub fn is_url(path: &Path) -> bool {
+    let s = path.to_str().unwrap_or("");
+    s.starts_with("http://") || s.starts_with("https://")
+}
+
+/// Stable cache filename for a URL: FNV-1a hash plus the original extension.
+fn cache_file_name(url: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in url.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    let ext = url
+        .rsplit('/')
+        .next()
+        .and_then(|name| name.split('?').next())
+        .and_then(|name| name.rsplit_once('.').map(|(_, e)| e))
+        .filter(|e| e.len() <= 5 && e.chars().all(|c| c.is_ascii_alphanumeric()));
+    match ext {
+        Some(ext) => format!("{:016x}.{}", hash, ext.to_lowercase()),
+        None => format!("{:016x}", hash),
+    }
+}
+
+/// Downloads one URL with retries, returning the response bytes.
+fn download(url: &str) -> Result<Vec<u8>, String> {
+    let mut last_err = String::new();
+    for attempt in 0..RETRIES {
+        if attempt > 0 {
+            std::thread::sleep(Duration::from_millis(500 << attempt));
+        }
+        match ureq::get(url).call() {
+            Ok(response) => {
+                let mut data = Vec::new();
+                match response.into_reader().read_to_end(&mut data) {
+                    Ok(_) => return Ok(data),
+                    Err(e) => last_err = e.to_string(),
+                }
+            }
+            Err(e) => last_err = e.to_string(),
+        }
+    }
+    Err(last_err)
+}
+
+/// Downloads every URL entry (concurrently, up to `concurrency` at a time)
+/// into the on-disk cache and rewrites the entry path to the cached file.
+/// Already-cached URLs are not re-downloaded.
+pub fn resolve_urls(
+    entries: &mut [ManifestEntry],
+    cache_dir: &Path,
+    concurrency: usize,
+) -> crate::error::Result<()> {
+    let url_indices: Vec<usize> = entries
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| is_url(&e.path))
+        .map(|(i, _)| i)
+        .collect();
+    if url_indices.is_empty() {
+        return Ok(());
+    }
+    fs::create_dir_all(cache_dir)?;
+    tracing::info!("Downloading {} images...", url_indices.len());
+
+    // Worker threads pull the next URL index from a shared counter and
+    // report (entry index, cached path) results back through a mutex.
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<(usize, PathBuf)>> = Mutex::new(Vec::new());
+    let jobs: Vec<(usize, String)> = url_indices
+        .iter()
+        .map(|&i| (i, entries[i].path.to_str().unwrap().to_string()))
+        .collect();
+
+    std::thread::scope(|scope| {
+        for _ in 0..concurrency.max(1).min(jobs.len()) {
+            scope.spawn(|| loop {
+                let job = next.fetch_add(1, Ordering::Relaxed);
+                let Some((entry_idx, url)) = jobs.get(job) else {
+                    break;
+                };
+                let cached = cache_dir.join(cache_file_name(url));
+                if !cached.is_file() {
+                    match download(url) {
+                        Ok(data) => {
+                            if let Err(e) = fs::write(&cached, data) {
+                                tracing::warn!("Error caching {}: {}", url, e);
+                                continue;
+                            }
+                        }
+                        Err(e) => {
+                            tracing::warn!("Error downloading {}: {}", url, e);
+                            continue;
+                        }
+                    }
+                }
+                results.lock().unwrap().push((*entry_idx, cached));
+            });
+        }
+    });
+
+    for (idx, cached) in results.into_inner().unwrap() {
+        entries[idx].path = cached;
+    }
+    Ok(())
+}
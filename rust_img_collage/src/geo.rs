@@ -0,0 +1,174 @@
+//! Geo layout (`--layout geo`): a travel map in one command.
+//!
+//! GPS EXIF positions are projected onto the canvas — equirectangular
+//! by default, Mercator with `--projection mercator` — and thumbnails
+//! land at their coordinates over an optional `--basemap` underlay.
+//! Photos that project onto the same spot cluster: one representative
+//! thumbnail with a "+N" count, so a city of photos doesn't become an
+//! unreadable stack. Images without a GPS tag are skipped with a note.
+
+use crate::date;
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use crate::summary::RunSummary;
+use memmap2::MmapMut;
+use std::collections::BTreeMap;
+use tempfile::tempfile;
+
+/// Projects (lat, lon) into abstract map units (x right, y down).
+fn project(lat: f64, lon: f64, projection: crate::Projection) -> (f64, f64) {
+    match projection {
+        crate::Projection::Equirect => (lon, -lat),
+        crate::Projection::Mercator => {
+            // Clamp toward the poles where the projection diverges.
+            let lat = lat.clamp(-85.0, 85.0).to_radians();
+            (lon, -((std::f64::consts::FRAC_PI_4 + lat / 2.0).tan().ln()))
+        }
+    }
+}
+
+/// Renders the geo map to `output_path`.
+pub fn create_geo(
+    entries: &[ManifestEntry],
+    args: &crate::Args,
+    output_path: &str,
+    run: &mut RunSummary,
+) -> error::Result<()> {
+    if entries.is_empty() {
+        return Err(Error::NoImages);
+    }
+    let cell_size = args.cell_size;
+
+    let mut located: Vec<(&ManifestEntry, (f64, f64))> = Vec::new();
+    for entry in entries {
+        match date::gps_coords(entry) {
+            Some((lat, lon)) => located.push((entry, project(lat, lon, args.projection))),
+            None => tracing::debug!("No GPS position in {:?}; left off the map", entry.path),
+        }
+    }
+    if located.is_empty() {
+        return Err(Error::Usage(
+            "no image carries a GPS EXIF position; --layout geo has nothing to place".to_string(),
+        ));
+    }
+    if located.len() < entries.len() {
+        tracing::info!("{} of {} images have GPS positions", located.len(), entries.len());
+    }
+
+    // With a basemap the canvas is the full world in its projection at
+    // the basemap's own resolution; without one, the canvas hugs the
+    // data with a padded margin.
+    let basemap = match &args.basemap {
+        Some(path) => Some(
+            ManifestEntry::from_path(path.clone())
+                .load_image()
+                .map_err(|e| Error::Decode(path.clone(), e))?,
+        ),
+        None => None,
+    };
+    let ((min_x, min_y), (max_x, max_y), (width, height)) = match &basemap {
+        Some(map) => {
+            let (top_left, bottom_right) = (
+                project(90.0, -180.0, args.projection),
+                project(-90.0, 180.0, args.projection),
+            );
+            ((top_left.0, top_left.1), (bottom_right.0, bottom_right.1), (map.width(), map.height()))
+        }
+        None => {
+            let (mut min_x, mut max_x) = (f64::MAX, f64::MIN);
+            let (mut min_y, mut max_y) = (f64::MAX, f64::MIN);
+            for &(_, (x, y)) in &located {
+                min_x = min_x.min(x);
+                max_x = max_x.max(x);
+                min_y = min_y.min(y);
+                max_y = max_y.max(y);
+            }
+            // Pad by 5% per side so edge photos don't kiss the border.
+            let pad_x = ((max_x - min_x) * 0.05).max(1e-6);
+            let pad_y = ((max_y - min_y) * 0.05).max(1e-6);
+            let side = (std::cmp::max(2, (located.len() as f64).sqrt().ceil() as u32) + 2)
+                * 2
+                * cell_size;
+            (
+                (min_x - pad_x, min_y - pad_y),
+                (max_x + pad_x, max_y + pad_y),
+                (side, side),
+            )
+        }
+    };
+    let span_x = (max_x - min_x).max(1e-9);
+    let span_y = (max_y - min_y).max(1e-9);
+
+    // Cluster photos landing in the same cell-sized patch of map.
+    let mut clusters: BTreeMap<(u32, u32), Vec<&ManifestEntry>> = BTreeMap::new();
+    for &(entry, (x, y)) in &located {
+        let px = (((x - min_x) / span_x * width as f64) as u32)
+            .saturating_sub(cell_size / 2)
+            .min(width.saturating_sub(cell_size));
+        let py = (((y - min_y) / span_y * height as f64) as u32)
+            .saturating_sub(cell_size / 2)
+            .min(height.saturating_sub(cell_size));
+        clusters
+            .entry((px / cell_size, py / cell_size))
+            .or_default()
+            .push(entry);
+    }
+    tracing::debug!(
+        "geo layout: {} photos in {} clusters, canvas {}x{} px",
+        located.len(), clusters.len(), width, height
+    );
+    run.total_images = located.len();
+    run.canvas_width = width;
+    run.canvas_height = height;
+
+    let num_pixels = (width as u64 * height as u64) as usize;
+    let file = tempfile()?;
+    file.set_len(num_pixels as u64 * 4)?;
+    crate::resources::add_temp(num_pixels as u64 * 4);
+    let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+    crate::background::fill(&mut mmap, (width, height));
+    if let Some(map) = &basemap {
+        let rgba = map.to_rgba8();
+        mmap.copy_from_slice(rgba.as_raw());
+    }
+
+    let composite_start = std::time::Instant::now();
+    for ((cx, cy), members) in &clusters {
+        crate::cancel::check()?;
+        let cell = (
+            (cx * cell_size).min(width - cell_size),
+            (cy * cell_size).min(height - cell_size),
+            cell_size,
+            cell_size,
+        );
+        let entry = members[0];
+        match entry.load_image() {
+            Ok(img) => {
+                crate::paste_image(&mut mmap, (width, height), cell, &img);
+                if members.len() > 1 {
+                    let label = format!("+{}", members.len() - 1);
+                    crate::draw_caption(&mut mmap, (width, height), cell, cell_size, args, &label);
+                }
+            }
+            Err(e) => {
+                if args.strict || args.on_error == crate::OnError::Abort {
+                    return Err(Error::Decode(entry.path.clone(), e));
+                }
+                tracing::error!("Error processing {:?}: {}", entry.path, e);
+                run.skip(&entry.path, &e);
+            }
+        }
+    }
+    mmap.flush()?;
+    run.phase_seconds
+        .insert("composite".to_string(), composite_start.elapsed().as_secs_f64());
+
+    let encode_start = std::time::Instant::now();
+    crate::save_canvas(&mmap, (width, height), args, output_path)?;
+    run.phase_seconds
+        .insert("encode".to_string(), encode_start.elapsed().as_secs_f64());
+    run.output_file = output_path.to_string();
+    run.output_bytes = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!("Geo map saved to '{}' ({} clusters)", output_path, clusters.len());
+    Ok(())
+}
@@ -0,0 +1,53 @@
+//! HEIC/HEIF input (`--features heic`).
+//!
+//! iPhone libraries are predominantly HEIC, which the `image` crate
+//! can't open. With the `heic` feature those files are decoded through
+//! the system libheif (via the libheif-rs bindings) straight into the
+//! pipeline, so an iPhone export collages without bulk pre-conversion.
+//! Rotation and mirroring stored in the file are applied during decode,
+//! so portrait shots come out upright.
+
+use std::path::Path;
+
+use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+/// Whether this extension belongs to a HEIF container.
+pub fn is_heic_ext(ext: &str) -> bool {
+    matches!(ext, "heic" | "heif" | "hif")
+}
+
+/// Whether the path looks like a HEIC/HEIF file.
+pub fn is_heic(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| is_heic_ext(&ext.to_lowercase()))
+}
+
+/// Decodes the primary image of a HEIF file at full resolution.
+pub fn decode(path: &Path) -> Result<image::DynamicImage, String> {
+    let name = path
+        .to_str()
+        .ok_or_else(|| format!("non-UTF-8 path {:?}", path))?;
+    let context = HeifContext::read_from_file(name)
+        .map_err(|e| format!("HEIC open of {:?} failed: {}", path, e))?;
+    let handle = context
+        .primary_image_handle()
+        .map_err(|e| format!("HEIC open of {:?} failed: {}", path, e))?;
+    let decoded = LibHeif::new()
+        .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgba), None)
+        .map_err(|e| format!("HEIC decode of {:?} failed: {}", path, e))?;
+    let plane = decoded
+        .planes()
+        .interleaved
+        .ok_or_else(|| format!("HEIC decode of {:?} returned no pixel plane", path))?;
+    // The decoded rows may carry padding, so copy them out stride-aware.
+    let (width, height) = (plane.width, plane.height);
+    let row_bytes = width as usize * 4;
+    let mut pixels = Vec::with_capacity(row_bytes * height as usize);
+    for row in plane.data.chunks(plane.stride).take(height as usize) {
+        pixels.extend_from_slice(&row[..row_bytes]);
+    }
+    image::RgbaImage::from_raw(width, height, pixels)
+        .map(image::DynamicImage::ImageRgba8)
+        .ok_or_else(|| format!("HEIC decode of {:?} returned a malformed buffer", path))
+}
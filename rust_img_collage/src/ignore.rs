@@ -0,0 +1,93 @@
+//! `.collageignore` support: permanent excludes next to the images.
+//!
+//! A gitignore-style file at the input root (and another per subfolder)
+//! keeps folders like `exports/` or `rejects/` out of every run without
+//! repeating flags. Lines are glob patterns (`*`/`?`, as in --order
+//! files) matched against names and paths; a trailing `/` restricts a
+//! pattern to directories, a leading `!` re-includes, blank lines and
+//! `#` comments are skipped, and the last matching line wins.
+
+use std::path::Path;
+
+/// The file name looked for at the root and in each subfolder.
+const IGNORE_FILE: &str = ".collageignore";
+
+/// One parsed line of a `.collageignore`.
+struct Rule {
+    pattern: String,
+    /// `!pattern`: matching paths are re-included.
+    negated: bool,
+    /// `pattern/`: only directories match.
+    dir_only: bool,
+}
+
+/// The combined ignore rules that apply inside one folder.
+pub struct Ignore {
+    rules: Vec<Rule>,
+}
+
+impl Ignore {
+    /// Loads the rules for scanning `folder`: the input root's file
+    /// first (the folder's parent), then the folder's own, so the more
+    /// local file gets the last word.
+    pub fn for_folder(folder: &Path) -> Ignore {
+        let mut rules = Vec::new();
+        if let Some(parent) = folder.parent() {
+            load_into(&parent.join(IGNORE_FILE), &mut rules);
+        }
+        load_into(&folder.join(IGNORE_FILE), &mut rules);
+        Ignore { rules }
+    }
+
+    /// Whether the rules exclude this path.
+    pub fn excludes(&self, path: &Path, is_dir: bool) -> bool {
+        if self.rules.is_empty() {
+            return false;
+        }
+        let full = path.to_string_lossy();
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut excluded = false;
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let pat = &rule.pattern;
+            if crate::glob_match(pat, &name)
+                || crate::glob_match(pat, &full)
+                || crate::glob_match(&format!("*/{}", pat), &full)
+            {
+                excluded = !rule.negated;
+            }
+        }
+        excluded
+    }
+}
+
+/// Appends the parsed rules of one ignore file, if it exists.
+fn load_into(path: &Path, rules: &mut Vec<Rule>) {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return;
+    };
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (line, negated) = match line.strip_prefix('!') {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        let (line, dir_only) = match line.strip_suffix('/') {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        rules.push(Rule {
+            pattern: line.to_string(),
+            negated,
+            dir_only,
+        });
+    }
+}
@@ -0,0 +1,220 @@
+//! Pluggable layouts for embedders.
+//!
+//! A [`Layout`] turns a list of items into one rectangle per item; the
+//! driver here does the rest (background, decoding, cover-fitting,
+//! clipping, encoding). Embedders register an implementation once per
+//! process — the same configure-once arrangement as the effects chain —
+//! and it takes precedence over `--layout`, so experimental layouts
+//! don't require forking the crate:
+//!
+//! ```ignore
+//! struct Diagonal;
+//! impl layout::Layout for Diagonal {
+//!     fn place(&self, items: &[ItemInfo], canvas: Extent) -> Vec<CellRect> { /* ... */ }
+//! }
+//! layout::set(Box::new(Diagonal));
+//! ```
+//!
+//! The built-in layouts keep their specialised compositors (effects,
+//! captions, per-layout flags); [`UniformGrid`] exposes the plain grid's
+//! placement through the trait as a starting point.
+
+// The registration half of this surface is for embedders only; the
+// binary never constructs a layout itself.
+#![allow(dead_code)]
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use crate::summary::RunSummary;
+use memmap2::MmapMut;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tempfile::tempfile;
+
+/// What a layout knows about one image before it is decoded.
+pub struct ItemInfo {
+    /// Path to the image file.
+    pub path: PathBuf,
+    /// Pixel dimensions from the header, or (0, 0) if unreadable.
+    pub width: u32,
+    pub height: u32,
+    /// Relative weight from the manifest (missing weights count as 1).
+    pub weight: f32,
+}
+
+/// Canvas size in pixels.
+#[derive(Clone, Copy)]
+pub struct Extent {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Where one item lands. Rects may hang off the canvas; out-of-bounds
+/// pixels are clipped, brick-style.
+#[derive(Clone, Copy)]
+pub struct CellRect {
+    pub x: i64,
+    pub y: i64,
+    pub w: u32,
+    pub h: u32,
+}
+
+/// Places items on a canvas. Implementations are called once per page
+/// with the final entry list.
+pub trait Layout: Send + Sync {
+    /// Canvas size for `items`. The default is the near-square grid the
+    /// plain layout would use at `cell_size`.
+    fn extent(&self, items: &[ItemInfo], cell_size: u32) -> Extent {
+        let n = items.len() as u32;
+        let ncols = std::cmp::max(1, (n as f64).sqrt().ceil() as u32);
+        let nrows = n.div_ceil(ncols);
+        Extent {
+            width: ncols * cell_size,
+            height: nrows * cell_size,
+        }
+    }
+
+    /// One rectangle per item, in item order. Items past the end of the
+    /// returned vector are left out of the collage.
+    fn place(&self, items: &[ItemInfo], canvas: Extent) -> Vec<CellRect>;
+}
+
+/// The plain grid's placement: row-major uniform cells.
+pub struct UniformGrid {
+    pub cell_size: u32,
+}
+
+impl Layout for UniformGrid {
+    fn place(&self, items: &[ItemInfo], canvas: Extent) -> Vec<CellRect> {
+        let ncols = std::cmp::max(1, canvas.width / self.cell_size);
+        (0..items.len() as u32)
+            .map(|i| CellRect {
+                x: ((i % ncols) * self.cell_size) as i64,
+                y: ((i / ncols) * self.cell_size) as i64,
+                w: self.cell_size,
+                h: self.cell_size,
+            })
+            .collect()
+    }
+}
+
+static CUSTOM: OnceLock<Box<dyn Layout>> = OnceLock::new();
+
+/// Registers a custom layout for this process; it takes precedence over
+/// `--layout`. Call before rendering starts; later calls are ignored.
+pub fn set(layout: Box<dyn Layout>) {
+    let _ = CUSTOM.set(layout);
+}
+
+/// The registered custom layout, if any.
+pub fn custom() -> Option<&'static dyn Layout> {
+    CUSTOM.get().map(|boxed| boxed.as_ref())
+}
+
+/// Builds the [`ItemInfo`] list for `entries`, reading dimensions from
+/// the image headers.
+fn item_infos(entries: &[ManifestEntry]) -> Vec<ItemInfo> {
+    entries
+        .iter()
+        .map(|entry| {
+            let (width, height) = image::image_dimensions(&entry.path).unwrap_or((0, 0));
+            ItemInfo {
+                path: entry.path.clone(),
+                width,
+                height,
+                weight: entry.weight.unwrap_or(1.0),
+            }
+        })
+        .collect()
+}
+
+/// Copies a cover-fitted image into its rect, clipping at the canvas
+/// edges.
+fn paste_clipped(
+    buf: &mut [u8],
+    (canvas_w, canvas_h): (u32, u32),
+    rect: CellRect,
+    img: &image::DynamicImage,
+) {
+    let filled = img
+        .resize_to_fill(rect.w, rect.h, image::imageops::FilterType::Lanczos3)
+        .to_rgba8();
+    for y in 0..rect.h {
+        for x in 0..rect.w {
+            let tx = rect.x + x as i64;
+            let ty = rect.y + y as i64;
+            if tx < 0 || ty < 0 || tx >= canvas_w as i64 || ty >= canvas_h as i64 {
+                continue;
+            }
+            let pixel = filled.get_pixel(x, y);
+            let index = ((ty as u32 * canvas_w + tx as u32) * 4) as usize;
+            buf[index..index + 4].copy_from_slice(&pixel.0);
+        }
+    }
+}
+
+/// Renders `entries` through `layout` to `output_path`.
+pub fn create_custom(
+    entries: &[ManifestEntry],
+    args: &crate::Args,
+    layout: &dyn Layout,
+    output_path: &str,
+    run: &mut RunSummary,
+) -> error::Result<()> {
+    if entries.is_empty() {
+        return Err(Error::NoImages);
+    }
+    let items = item_infos(entries);
+    let canvas = layout.extent(&items, args.cell_size);
+    if canvas.width == 0 || canvas.height == 0 {
+        return Err(Error::Usage("custom layout produced an empty canvas".to_string()));
+    }
+    let rects = layout.place(&items, canvas);
+    let (width, height) = (canvas.width, canvas.height);
+    tracing::debug!(
+        "custom layout: {} rects for {} images, canvas {}x{} px",
+        rects.len(), entries.len(), width, height
+    );
+    run.canvas_width = width;
+    run.canvas_height = height;
+
+    let num_pixels = (width as u64 * height as u64) as usize;
+    let file = tempfile()?;
+    file.set_len(num_pixels as u64 * 4)?;
+    crate::resources::add_temp(num_pixels as u64 * 4);
+    let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+    crate::background::fill(&mut mmap, (width, height));
+
+    let composite_start = std::time::Instant::now();
+    for (entry, rect) in entries.iter().zip(rects) {
+        crate::cancel::check()?;
+        if rect.w == 0 || rect.h == 0 {
+            continue;
+        }
+        match entry.load_image() {
+            Ok(img) => {
+                paste_clipped(&mut mmap, (width, height), rect, &img);
+                run.total_images += 1;
+            }
+            Err(e) => {
+                if args.strict || args.on_error == crate::OnError::Abort {
+                    return Err(Error::Decode(entry.path.clone(), e));
+                }
+                tracing::error!("Error processing {:?}: {}", entry.path, e);
+                run.skip(&entry.path, &e);
+            }
+        }
+    }
+    mmap.flush()?;
+    run.phase_seconds
+        .insert("composite".to_string(), composite_start.elapsed().as_secs_f64());
+
+    let encode_start = std::time::Instant::now();
+    crate::save_canvas(&mmap, (width, height), args, output_path)?;
+    run.phase_seconds
+        .insert("encode".to_string(), encode_start.elapsed().as_secs_f64());
+    run.output_file = output_path.to_string();
+    run.output_bytes = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!("Custom-layout collage saved to '{}'", output_path);
+    Ok(())
+}
@@ -1,203 +1,4594 @@
 use clap::Parser;
 use image::imageops::FilterType;
-use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba};
+use image::{GenericImageView, ImageBuffer, Rgba};
+#[cfg(not(target_arch = "wasm32"))]
 use memmap2::MmapMut;
 use std::cmp;
-use std::fs::{self, File};
-use std::io::{Seek, SeekFrom, Write};
-use std::path::{Path, PathBuf};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+#[cfg(not(target_arch = "wasm32"))]
 use tempfile::tempfile;
 
+// Modules needing mmap, tempfile, threads, or the network are compiled
+// out on wasm32; that target gets the in-memory pipeline in `wasm`.
+#[cfg(not(target_arch = "wasm32"))]
+mod animate;
+mod archive;
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+mod async_api;
+mod atlas;
+mod background;
+#[cfg(not(target_arch = "wasm32"))]
+mod bench;
+#[cfg(not(target_arch = "wasm32"))]
+mod bigtiff;
+mod blurhash;
+#[cfg(not(target_arch = "wasm32"))]
+mod brick;
+#[cfg(not(target_arch = "wasm32"))]
+mod burst;
+#[cfg(not(target_arch = "wasm32"))]
+mod diagonal;
+#[cfg(not(target_arch = "wasm32"))]
+mod diff;
+#[cfg(not(target_arch = "wasm32"))]
+mod embedding;
+#[cfg(not(target_arch = "wasm32"))]
+mod calendar;
+mod cancel;
+mod captions;
+#[cfg(not(target_arch = "wasm32"))]
+mod contact;
+mod date;
+mod error;
+#[cfg(not(target_arch = "wasm32"))]
+mod fetch;
+#[cfg(not(target_arch = "wasm32"))]
+mod geo;
+#[cfg(all(feature = "gpu", not(target_arch = "wasm32")))]
+mod gpu;
+#[cfg(all(feature = "heic", not(target_arch = "wasm32")))]
+mod heic;
+mod ignore;
+#[cfg(not(target_arch = "wasm32"))]
+mod layout;
+mod manifest;
+#[cfg(not(target_arch = "wasm32"))]
+mod montage;
+#[cfg(not(target_arch = "wasm32"))]
+mod pairs;
+#[cfg(not(target_arch = "wasm32"))]
+mod nested;
+#[cfg(not(target_arch = "wasm32"))]
+mod palette;
+#[cfg(not(target_arch = "wasm32"))]
+mod pin;
+#[cfg(not(target_arch = "wasm32"))]
+mod qr;
+mod quantize;
+#[cfg(not(target_arch = "wasm32"))]
+mod parallel;
+#[cfg(all(feature = "pdf", not(target_arch = "wasm32")))]
+mod pdf;
+mod preset;
+#[cfg(all(feature = "preview", not(target_arch = "wasm32")))]
+mod preview;
+mod progress;
+#[cfg(not(target_arch = "wasm32"))]
+mod radial;
+#[cfg(all(feature = "raw", not(target_arch = "wasm32")))]
+mod raw;
+mod report;
+#[cfg(not(target_arch = "wasm32"))]
+mod resources;
+mod rotate;
+#[cfg(not(target_arch = "wasm32"))]
+mod rows;
+mod scanner;
+#[cfg(not(target_arch = "wasm32"))]
+mod scatter;
+#[cfg(all(feature = "s3", not(target_arch = "wasm32")))]
+mod s3_input;
+#[cfg(not(target_arch = "wasm32"))]
+mod separator;
+#[cfg(not(target_arch = "wasm32"))]
+mod server;
+mod skiplist;
+mod source;
+mod sprite;
+mod summary;
+#[cfg(all(feature = "svg", not(target_arch = "wasm32")))]
+mod svg;
+#[cfg(not(target_arch = "wasm32"))]
+mod template;
+mod text;
+#[cfg(not(target_arch = "wasm32"))]
+mod text_tile;
+mod tiles;
+#[cfg(not(target_arch = "wasm32"))]
+mod timeline;
+#[cfg(all(feature = "tui", not(target_arch = "wasm32")))]
+mod tui;
+#[cfg(all(feature = "ffmpeg", not(target_arch = "wasm32")))]
+mod video;
+#[cfg(not(target_arch = "wasm32"))]
+mod voronoi;
+#[cfg(not(target_arch = "wasm32"))]
+mod wallpaper;
+mod wasm;
+
+use error::Error;
+use manifest::ManifestEntry;
+use summary::{LogFormat, RunSummary};
+
 /// Create a collage from images in sorted subfolders.
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(author, version, about)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to the root directory containing subfolders with images.
-    input_dir: String,
+    /// Not needed when --from-manifest is used.
+    input_dir: Option<String>,
 
     /// Output collage file (e.g. collage.webp).
-    output_file: String,
+    output_file: Option<String>,
 
     /// Size in pixels for each cell (default: 200).
     #[arg(long, default_value_t = 200)]
     cell_size: u32,
+
+    /// Manifest file (CSV or JSON) listing the images to use instead of
+    /// scanning a directory. Rows may carry a caption, sort key, weight,
+    /// and cell span (e.g. "2x2").
+    /// Pass `-` to read a CSV manifest from stdin.
+    #[arg(long, value_name = "FILE")]
+    from_manifest: Option<String>,
+
+    /// Directory for caching downloaded images (default: system temp dir).
+    #[arg(long, value_name = "DIR")]
+    download_cache: Option<PathBuf>,
+
+    /// Maximum number of concurrent downloads.
+    #[arg(long, default_value_t = 4)]
+    download_concurrency: usize,
+
+    /// Emit one collage per subfolder instead of a single combined one.
+    /// The output file must then contain `{folder}`, e.g. `{folder}.webp`.
+    #[arg(long)]
+    per_folder: bool,
+
+    /// Render each subfolder into its own mini-collage first, then use
+    /// those as the cells of the top-level collage (folder names become
+    /// captions) — a two-level overview of a deep library.
+    #[arg(long, conflicts_with_all = ["per_folder", "from_manifest"])]
+    nested: bool,
+
+    /// Also emit scaled renditions at these widths (e.g. 400,800,1600),
+    /// named like `collage_800.webp`, from the same compositing pass.
+    #[arg(long, value_delimiter = ',', value_name = "WIDTHS")]
+    sizes: Vec<u32>,
+
+    /// Write an HTML <img srcset> snippet referencing the --sizes renditions.
+    #[arg(long, value_name = "FILE", requires = "sizes")]
+    srcset_file: Option<PathBuf>,
+
+    /// Pack images tightly into a CSS sprite sheet (native sizes, no grid)
+    /// and write a companion stylesheet.
+    #[arg(long)]
+    sprite: bool,
+
+    /// Stylesheet path for --sprite (default: output with .css extension;
+    /// a .scss extension emits an SCSS $sprites map).
+    #[arg(long, value_name = "FILE", requires = "sprite")]
+    sprite_css: Option<PathBuf>,
+
+    /// Bin-pack images into a texture atlas with a TexturePacker-compatible
+    /// JSON descriptor.
+    #[arg(long, conflicts_with = "sprite")]
+    atlas: bool,
+
+    /// Descriptor path for --atlas (default: output with .json extension).
+    #[arg(long, value_name = "FILE", requires = "atlas")]
+    atlas_json: Option<PathBuf>,
+
+    /// Pad the atlas canvas up to power-of-two dimensions.
+    #[arg(long, requires = "atlas")]
+    power_of_two: bool,
+
+    /// Trim transparent borders from atlas frames.
+    #[arg(long, requires = "atlas")]
+    trim: bool,
+
+    /// Abort on the first image that fails to decode instead of leaving
+    /// its cell blank. Without it, partial failures still exit non-zero.
+    #[arg(long)]
+    strict: bool,
+
+    /// What to do with images that fail to decode: render a hatched
+    /// placeholder cell, leave the cell empty, or abort the run.
+    #[arg(long, value_enum, default_value_t = OnError::Placeholder, conflicts_with = "strict")]
+    on_error: OnError,
+
+    /// What to do with degenerate images — zero-dimension or one-pixel
+    /// files and panoramas over 100:1 — whose scale factors would
+    /// otherwise round to zero-sized resizes and corrupt the grid.
+    #[arg(long, value_enum, default_value_t = Degenerate::Pad)]
+    degenerate: Degenerate,
+
+    /// What to do when the canvas would exceed the output container's
+    /// pixel limits (WebP caps each side at 16383 px, JPEG at 65535):
+    /// fail before compositing, shrink the cells to fit, split into
+    /// pages that fit, or keep the pixels and switch the output to PNG.
+    #[arg(long, value_enum, default_value_t = TooBig::Fail)]
+    too_big: TooBig,
+
+    /// Skip images narrower than this, judged from the header alone.
+    #[arg(long, value_name = "PX")]
+    min_width: Option<u32>,
+
+    /// Skip images shorter than this, judged from the header alone.
+    #[arg(long, value_name = "PX")]
+    min_height: Option<u32>,
+
+    /// Skip images whose long-to-short side ratio exceeds this (e.g. 3.0
+    /// drops panoramas and banners).
+    #[arg(long, value_name = "RATIO")]
+    max_aspect_ratio: Option<f32>,
+
+    /// Skip images whose sharpness (variance of the Laplacian, measured on
+    /// a downscaled copy) falls below this threshold; 50-200 is a useful
+    /// range for phone photos.
+    #[arg(long, value_name = "THRESHOLD")]
+    skip_blurry: Option<f64>,
+
+    /// Keep only images captured on or after this date (YYYY-MM-DD), by
+    /// EXIF capture date with an mtime fallback; undatable images drop.
+    #[arg(long, value_name = "DATE")]
+    since: Option<String>,
+
+    /// Keep only images captured on or before this date (YYYY-MM-DD).
+    #[arg(long, value_name = "DATE")]
+    until: Option<String>,
+
+    /// Use at most N images from each subfolder, so one huge folder
+    /// doesn't dominate the collage.
+    #[arg(long, value_name = "N")]
+    limit_per_folder: Option<usize>,
+
+    /// Drop duplicate images before layout; `exact` hashes file contents
+    /// during scanning and keeps only the first of byte-identical copies.
+    #[arg(long, value_enum, value_name = "MODE")]
+    dedup: Option<Dedup>,
+
+    /// Cluster consecutive near-duplicate frames (bursts) and keep only
+    /// the sharpest, best-exposed frame of each cluster.
+    #[arg(long)]
+    best_of_burst: bool,
+
+    /// Sort newest capture date (EXIF, else mtime) to the front, so the
+    /// most recent photos land in the layout's prominent spots — top
+    /// rows of a grid, the center of a radial — and survive a
+    /// --max-images cap on a scheduled "latest photos" wall.
+    #[arg(long)]
+    newest_first: bool,
+
+    /// Randomly sample at most N images from the input (after filters).
+    #[arg(long, value_name = "N")]
+    sample: Option<usize>,
+
+    /// Shuffle the image order (seeded by --seed): within-folders keeps
+    /// the album structure and randomizes only inside each folder —
+    /// variety between scheduled regenerations — while all shuffles
+    /// globally.
+    #[arg(long, value_enum, value_name = "SCOPE")]
+    shuffle: Option<Shuffle>,
+
+    /// Finish within this wall-clock budget, e.g. 90s, 8m or 1h: a
+    /// short decode probe measures throughput, then the set is sampled
+    /// down to what fits (conservatively, single-threaded), so a
+    /// scheduled regeneration never overruns its slot.
+    #[arg(long, value_name = "DURATION")]
+    time_budget: Option<String>,
+
+    /// Seed behind every stochastic feature — --sample, --shuffle, --balance,
+    /// --gap-ratio placement, --rotate-jitter, and the scatter layout's
+    /// positions and z-order — so a pleasing random arrangement can be
+    /// regenerated exactly, e.g. at a higher --cell-size.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Bit-reproducible runs: capture dates come from EXIF alone (no
+    /// mtime fallback, which differs between otherwise identical
+    /// checkouts) and the --summary timings are dropped. Sampling,
+    /// jitter and the encoders are already pinned by --seed and fixed
+    /// parameters.
+    #[arg(long)]
+    deterministic: bool,
+
+    /// Sample this many images in total, spread across the subfolders in
+    /// proportion to their size (every non-empty folder gets at least one),
+    /// so whichever folders sort first don't dominate.
+    #[arg(long, value_name = "N", conflicts_with = "sample")]
+    balance: Option<usize>,
+
+    /// Scale each subfolder's share of the --balance sample, as
+    /// name=factor pairs (globs allowed), e.g.
+    /// 'vacation=3,screenshots=0.2'. Unlisted folders weigh 1; a factor
+    /// of 0 excludes the folder outright.
+    #[arg(long, value_name = "NAME=FACTOR,...", requires = "balance")]
+    folder_weight: Option<String>,
+
+    /// Sort folders and files strictly lexicographically instead of the
+    /// default natural order (where img2 sorts before img10).
+    #[arg(long)]
+    lexicographic: bool,
+
+    /// Follow symlinked folders and files when scanning (skipped by
+    /// default); targets already seen are scanned once, so link cycles
+    /// and doubled albums can't blow the run up.
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// Include dot-files and dot-folders when scanning; the hidden
+    /// caches photo managers leave behind are skipped by default.
+    #[arg(long)]
+    include_hidden: bool,
+
+    /// File of paths or globs (`*`/`?`), one per line; matching images are
+    /// placed first, in the file's order, and the rest follow the normal
+    /// sort. Blank lines and `#` comments are ignored.
+    #[arg(long, value_name = "FILE")]
+    order: Option<PathBuf>,
+
+    /// Give images matching PATTERN a larger cell span, e.g. 'hero*=2x2'
+    /// or '*/best/*=3x2'. May be repeated; patterns match like --order.
+    #[arg(long, value_name = "PATTERN=SPAN")]
+    feature: Vec<String>,
+
+    /// Feature every Nth image (the 1st, N+1th, ...) as a 2x2 block.
+    #[arg(long, value_name = "N")]
+    feature_every: Option<usize>,
+
+    /// Give heavier images proportionally larger cells, weighting by file
+    /// size, EXIF star rating, or the manifest's weight column. Spans
+    /// quantize to 1x1 through 3x3 blocks around the median weight.
+    #[arg(long, value_enum)]
+    weight_by: Option<WeightBy>,
+
+    /// Use at most N images in total (after filters, sampling and
+    /// ordering); the rest are dropped.
+    #[arg(long, value_name = "N")]
+    max_images: Option<usize>,
+
+    /// With --max-images, render a final "+N more" cell counting the
+    /// dropped images, like social-media photo previews (grid only).
+    #[arg(long, requires = "max_images")]
+    overflow_tile: bool,
+
+    /// Render this URL as a scannable QR-code cell so printed collages
+    /// link back to the online album; repeat for multiple codes.
+    #[arg(long, value_name = "URL")]
+    qr_tile: Vec<String>,
+
+    /// Where the QR cells go in the grid order.
+    #[arg(long, value_enum, default_value_t = QrPosition::Last, requires = "qr_tile")]
+    qr_position: QrPosition,
+
+    /// Pin artwork to exact grid coordinates as FILE@rRcC[,WxH] (1-based
+    /// row and column, optional span), e.g. `logo.png@r1c1,2x2`; the
+    /// automatic flow skips the reserved cells. Repeat for multiple pins.
+    #[arg(long, value_name = "FILE@rRcC[,WxH]")]
+    pin: Vec<String>,
+
+    /// Render this text as a styled card cell — quotes, dates, album
+    /// titles; repeat for multiple. A manifest `text` column places
+    /// cards anywhere in the grid order.
+    #[arg(long, value_name = "TEXT")]
+    text_tile: Vec<String>,
+
+    /// Leave every Nth grid cell intentionally empty
+    /// (background-coloured), giving dense collages breathing room.
+    #[arg(long, value_name = "N", conflicts_with = "gap_ratio")]
+    gap_every: Option<usize>,
+
+    /// Leave roughly this fraction of grid cells empty (0 to 1), chosen
+    /// with --seed so the holes are reproducible.
+    #[arg(long, value_name = "RATIO")]
+    gap_ratio: Option<f64>,
+
+    /// Target canvas proportions for the grid as W:H (e.g. 16:9); the
+    /// column count is chosen to approximate them instead of a
+    /// near-square. Useful for wallpapers and video thumbnails.
+    #[arg(long, value_name = "W:H")]
+    aspect: Option<String>,
+
+    /// Exact number of grid columns, overriding the near-square (or
+    /// --aspect) choice. The row count follows from the image count.
+    #[arg(long, value_name = "N", conflicts_with = "aspect")]
+    columns: Option<u32>,
+
+    /// How sorted images map onto grid cells: row-major, column-major, or
+    /// snake (every other row reversed, keeping neighbours adjacent).
+    #[arg(long, value_enum, default_value_t = FillOrder::Row)]
+    fill_order: FillOrder,
+
+    /// Overall page layout: the plain grid, a timeline that groups images
+    /// under one date band per capture day (EXIF, else mtime), or a month
+    /// calendar with a mini-collage per day.
+    #[arg(long, value_enum, default_value_t = Layout::Grid)]
+    layout: Layout,
+
+    /// Which frame to decode from animated GIF/WebP sources: `first`,
+    /// `middle`, or a frame number (clamped to the last frame).
+    #[arg(long, value_name = "first|middle|N")]
+    animated_frame: Option<String>,
+
+    /// Which moment of a video becomes its cell image: `first`,
+    /// `middle`, or seconds in (requires building with `--features
+    /// ffmpeg`; extraction shells out to the ffmpeg binary).
+    #[arg(long, value_name = "first|middle|SECONDS")]
+    video_timestamp: Option<String>,
+
+    /// Render every page of each PDF as its own cell instead of just
+    /// the first (requires building with `--features pdf`; rendering
+    /// shells out to poppler's pdftoppm).
+    #[arg(long)]
+    pdf_all_pages: bool,
+
+    /// Fill in a curated combination of flags for a finished artefact
+    /// out of the box; explicitly set flags always win.
+    #[arg(long, value_enum)]
+    preset: Option<Preset>,
+
+    /// Append an auto-generated stats footer (total photos, busiest
+    /// day, cameras used); --preset year-review turns this on.
+    #[arg(long, conflicts_with = "page_footer")]
+    stats_footer: bool,
+
+    /// Ignore the folder structure and bucket images by EXIF/mtime date
+    /// instead, rendering one labeled section per day, month or year —
+    /// for libraries that are one flat dump of files.
+    #[arg(long, value_enum)]
+    group_by: Option<GroupBy>,
+
+    /// Merge same-named albums under different roots (phone/2024-06 and
+    /// camera/2024-06 become one section), interleaving their images by
+    /// filename or capture date.
+    #[arg(long, value_enum, value_name = "ORDER")]
+    merge_folders: Option<MergeFolders>,
+
+    /// Draw a thin horizontal band between the cells of different
+    /// subfolders in the grid, as PX or PX:#rrggbb (default grey) —
+    /// lighter-weight album boundaries than --group-by's sections.
+    #[arg(long, value_name = "PX[:COLOR]", conflicts_with = "group_by")]
+    folder_separator: Option<String>,
+
+    /// Write each folder's name on its separator band.
+    #[arg(long, requires = "folder_separator")]
+    folder_separator_label: bool,
+
+    /// Month to render with --layout calendar, as YYYY-MM.
+    #[arg(long, value_name = "YYYY-MM")]
+    month: Option<String>,
+
+    /// strftime-style format for the timeline date bands and the
+    /// calendar heading, e.g. "%A, %e %B %Y"; ISO dates otherwise.
+    #[arg(long, value_name = "FMT")]
+    date_format: Option<String>,
+
+    /// Language for %B/%b month and %A/%a weekday names in date labels
+    /// (en, de, es, fr, it, pt).
+    #[arg(long, value_name = "CODE")]
+    locale: Option<String>,
+
+    /// Projection for --layout geo.
+    #[arg(long, value_enum, default_value_t = Projection::Equirect)]
+    projection: Projection,
+
+    /// World-map image drawn under --layout geo, in the same projection;
+    /// the canvas takes the basemap's resolution.
+    #[arg(long, value_name = "FILE")]
+    basemap: Option<PathBuf>,
+
+    /// TOML file declaring named areas (position, span, glob pattern) on a
+    /// cell grid, CSS-grid style, instead of an automatic layout.
+    #[arg(long, value_name = "FILE", conflicts_with = "layout")]
+    layout_file: Option<PathBuf>,
+
+    /// Write the computed cell rectangles as JSON next to the render,
+    /// ready for hand-editing and a --import-layout re-render.
+    #[arg(long, value_name = "FILE")]
+    export_layout: Option<PathBuf>,
+
+    /// Re-render using the cell rectangles from a --export-layout JSON
+    /// file (possibly hand-edited) instead of recomputing the layout.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["layout", "layout_file", "export_layout"])]
+    import_layout: Option<PathBuf>,
+
+    /// Image to feature in the middle of --layout radial (glob, matched
+    /// like --order); defaults to the first image.
+    #[arg(long, value_name = "PATTERN")]
+    center: Option<String>,
+
+    /// Canvas background: a flat colour (`#rrggbb`) or a gradient,
+    /// `linear(45deg,#111,#333)` or `radial(#111,#333)`. The default is
+    /// the transparent white the encoder treats as empty.
+    #[arg(long, value_name = "SPEC")]
+    background: Option<String>,
+
+    /// Outer margin in pixels around the whole composition, filled with
+    /// the background.
+    #[arg(long, value_name = "PX", default_value_t = 0)]
+    margin: u32,
+
+    /// Decorative frame drawn around the whole composition (outside the
+    /// margin), as PX:#rrggbb, e.g. 12:#222222.
+    #[arg(long, value_name = "PX:COLOR")]
+    frame: Option<String>,
+
+    /// Encode the output losslessly, whatever the container: .webp
+    /// switches to the lossless WebP encoder and .png already is.
+    /// JPEG outputs are refused, since JPEG has no lossless mode here.
+    #[arg(long)]
+    lossless: bool,
+
+    /// Mirror the finished collage into a grid of alternately flipped
+    /// copies on a larger canvas (2x2 or 4x4), kaleidoscope style.
+    #[arg(long, value_name = "GRID")]
+    mirror: Option<String>,
+
+    /// Wallpaper mode: after rendering, scale the collage to cover the
+    /// combined monitor area (detected with xrandr, or --monitors) and
+    /// split it into per-monitor files aligned at the seams, named like
+    /// --paginate pages.
+    #[arg(long)]
+    wallpaper: bool,
+
+    /// Monitor layout for --wallpaper: `2x2560x1440` (two side-by-side)
+    /// or an explicit `2560x1440+0+0,1920x1080+2560+0` list.
+    #[arg(long, value_name = "SPEC", requires = "wallpaper")]
+    monitors: Option<String>,
+
+    /// Also set the split files as the desktop background (shells out
+    /// to feh).
+    #[arg(long, requires = "wallpaper")]
+    set_wallpaper: bool,
+
+    /// Open the finished collage in a lightweight native window after
+    /// rendering: Space re-rolls seeded layouts (the seed bumps by one
+    /// and the run repeats), Enter or Escape accepts. Requires building
+    /// with `--features preview`.
+    #[arg(long)]
+    preview: bool,
+
+    /// Write one output per listed theme (light, dark), named
+    /// collage-light.webp, collage-dark.webp, ... The thumbnail layer is
+    /// composited once over transparency and only re-flattened onto each
+    /// theme's background, so sources are decoded and resized once.
+    #[arg(long, value_delimiter = ',', value_enum, conflicts_with = "background")]
+    variants: Vec<Variant>,
+
+    /// Keep the encoded output under this many bytes (e.g. `8MB`,
+    /// `500KB`) by binary-searching the JPEG quality — platforms with
+    /// strict upload limits. Needs a .jpg output; this build's WebP
+    /// encoder is lossless and has no quality to trade.
+    #[arg(long, value_name = "SIZE", conflicts_with = "lossless")]
+    target_size: Option<String>,
+
+    /// Composite the finished canvas over this colour before encoding
+    /// (as #rrggbb): what alpha-less outputs like JPEG show
+    /// under transparency, instead of the implicit black it renders
+    /// when the alpha channel is simply dropped.
+    #[arg(long, value_name = "COLOR")]
+    flatten: Option<String>,
+
+    /// Snap alpha below this value to fully transparent and the rest
+    /// to fully opaque (0-255) before flattening, so soft mask edges
+    /// don't leave halo fringing in JPEG output.
+    #[arg(long, value_name = "0-255")]
+    alpha_threshold: Option<u8>,
+
+    /// Reduce the output to at most this many colors (2-256). PNG
+    /// outputs become true indexed PNG8; other containers keep their
+    /// encoding but compress far smaller.
+    #[arg(long, value_name = "COLORS")]
+    quantize: Option<u16>,
+
+    /// Dithering used when --quantize remaps pixels to the palette.
+    #[arg(long, value_enum, default_value_t = Dither::FloydSteinberg, requires = "quantize")]
+    dither: Dither,
+
+    /// Border drawn inside each cell's edge (grid layout), as
+    /// PX:#rrggbb or PX:dominant — `dominant` picks each image's own
+    /// dominant colour, stained-glass style. The width defaults to 4 px
+    /// when only a colour is given, e.g. `--cell-border dominant`.
+    #[arg(long, value_name = "PX:COLOR")]
+    cell_border: Option<String>,
+
+    /// Categorical palette for automatic per-folder colours (the
+    /// `folder` cell-border colour, separator bands): `okabe-ito` and
+    /// `viridis` are colour-blind safe, `custom:#rrggbb,...` brings
+    /// your own. Folders keep their colour across runs.
+    #[arg(long, value_name = "NAME")]
+    palette: Option<String>,
+
+    /// Image painted under the cells before compositing, visible through
+    /// gutters, letterboxing and transparent regions.
+    #[arg(long, value_name = "FILE", conflicts_with = "background")]
+    background_image: Option<PathBuf>,
+
+    /// How --background-image fills the canvas: scaled to cover it, or
+    /// tiled at its native size.
+    #[arg(long, value_enum, default_value_t = BackgroundMode::Cover, requires = "background_image")]
+    background_mode: BackgroundMode,
+
+    /// Shape of each pasted cell: the full square, or a centred circle
+    /// with anti-aliased edges (avatar-grid style).
+    #[arg(long, value_enum, default_value_t = CellShape::Square)]
+    cell_shape: CellShape,
+
+    /// With --cell-shape circle, draw a ring border this many pixels
+    /// wide around each circle.
+    #[arg(long, value_name = "PX", default_value_t = 0)]
+    ring: u32,
+
+    /// Grayscale image applied as an alpha mask to every pasted cell
+    /// (white keeps, black drops), scaled to the cell size — stars,
+    /// circles, torn-paper edges and the like.
+    #[arg(long, value_name = "FILE")]
+    cell_mask: Option<PathBuf>,
+
+    /// Fill each cell's letterbox bars from the image itself instead of
+    /// the global background, so mixed-orientation photos don't leave
+    /// harsh colour seams.
+    #[arg(long, value_enum, value_name = "MODE")]
+    cell_fill: Option<CellFill>,
+
+    /// Darken each tile's edges by this strength (0 to 1) before
+    /// pasting, separating neighbours without borders or gutters.
+    #[arg(long, value_name = "STRENGTH", default_value_t = 0.0)]
+    vignette: f64,
+
+    /// Rotate each tile by a seeded random angle up to ±DEG degrees,
+    /// anti-aliased, polaroid style (grid and scatter layouts). A
+    /// manifest `rotation` column overrides it per image.
+    #[arg(long, value_name = "DEG")]
+    rotate_jitter: Option<f64>,
+
+    /// Rotate every tile by a right angle (90, 180 or 270) at decode
+    /// time — scanned negatives that are uniformly sideways or upside
+    /// down. A manifest `orient` column overrides it per image.
+    #[arg(long, value_name = "DEG")]
+    tile_rotate: Option<u32>,
+
+    /// Mirror every tile horizontally (h), vertically (v), or both (hv)
+    /// at decode time; a manifest `orient` column overrides it.
+    #[arg(long, value_name = "H|V|HV")]
+    tile_flip: Option<String>,
+
+    /// Decode a JPEG's embedded EXIF thumbnail instead of the full
+    /// image whenever the thumbnail already covers the cell size — an
+    /// order of magnitude faster over RAW+JPEG archives at contact-
+    /// sheet sizes.
+    #[arg(long)]
+    use_exif_thumbs: bool,
+
+    /// Auto-crop uniform-colour margins from every source at decode
+    /// time, before the resize — the white or black mats around scans —
+    /// keeping pixels within TOLERANCE (0-255) per channel of the
+    /// border colour counted as border.
+    #[arg(long, value_name = "TOLERANCE")]
+    trim_borders: Option<u8>,
+
+    /// Threads for decoding and compositing (grid layout). The default,
+    /// 0, picks the machine's core count with a cap that keeps the
+    /// per-thread decode scratch modest on small machines; 1 forces the
+    /// serial path. Rotation (and --resume) stay serial regardless.
+    #[arg(long, value_name = "N", default_value_t = 0, conflicts_with = "resume")]
+    threads: usize,
+
+    /// Resize and composite on the GPU (build with --features gpu).
+    /// Covers the plain grid paste only — rotation, captions, and cell
+    /// effects stay on the CPU path; falls back to the CPU with a
+    /// warning if no adapter is found.
+    #[arg(long, conflicts_with = "resume")]
+    gpu: bool,
+
+    /// How much scatter tiles overlap, as a percentage of the cell size.
+    #[arg(long, value_name = "PERCENT", default_value_t = 20.0)]
+    overlap: f64,
+
+    /// Which scatter tiles paint on top: input order (later on top), a
+    /// seeded shuffle, or file size (largest on top).
+    #[arg(long, value_enum, default_value_t = ZOrder::Input)]
+    z_order: ZOrder,
+
+    /// Before/after comparison mode: two roots whose files are matched by
+    /// name (ignoring the extension) and laid side by side, one pair per
+    /// row. The positional argument is the output file, as with
+    /// --from-manifest.
+    #[arg(long, num_args = 2, value_names = ["BEFORE_DIR", "AFTER_DIR"], conflicts_with = "from_manifest")]
+    pairs: Option<Vec<String>>,
+
+    /// Label each cell: caption text is read from a sidecar next to the
+    /// image (IMG_001.txt, the Description of IMG_001.xmp, or a caption/
+    /// description field in IMG_001.json), falling back to the file name.
+    /// Manifest captions are left alone.
+    #[arg(long)]
+    captions: bool,
+
+    /// Resume an interrupted grid run: the canvas is backed by a named
+    /// .partial file next to the output and completed cells are recorded
+    /// in a .journal, so a crashed run picks up where it stopped instead
+    /// of recompositing everything. Both files are removed on success.
+    #[arg(long)]
+    resume: bool,
+
+    /// Write the resolved entries (after filtering, sampling, ordering)
+    /// back out as a JSON manifest that --from-manifest can read.
+    #[arg(long, value_name = "FILE")]
+    write_manifest: Option<PathBuf>,
+
+    /// Include a BlurHash placeholder string per image in the
+    /// --write-manifest output.
+    #[arg(long, requires = "write_manifest")]
+    blurhash: bool,
+
+    /// Include each source file's byte size and CRC-32 in the
+    /// --write-manifest output, so the collage can later be verified
+    /// against the exact files it was built from.
+    #[arg(long, requires = "write_manifest")]
+    checksums: bool,
+
+    /// Write an HTML <map> fragment (or a JSON array, with a .json
+    /// extension) alongside the collage, one clickable area per grid
+    /// cell linking to the manifest url or the source path.
+    #[arg(long, value_name = "FILE")]
+    image_map: Option<PathBuf>,
+
+    /// Write a standalone HTML proof sheet: the collage image wrapped
+    /// in a client-side map so every cell is a clickable link to its
+    /// manifest `url` (or the source path when no url is set).
+    #[arg(long, value_name = "FILE")]
+    gallery: Option<PathBuf>,
+
+    /// Also write every resized cell thumbnail into this directory as
+    /// an individual file named by its 1-based index (1.webp, 2.webp,
+    /// ...), from the same decode and resize pass — ready-made tiles
+    /// for a web gallery.
+    #[arg(long, value_name = "DIR")]
+    dump_thumbs: Option<PathBuf>,
+
+    /// Write a `index,folder,filename,row,col,x,y,w,h` CSV mapping each
+    /// image to its cell, for spreadsheets and scripts that don't want
+    /// to parse JSON. `-` prints the table to stdout instead.
+    #[arg(long, value_name = "FILE")]
+    index: Option<String>,
+
+    /// Split the output into pages of at most N images, numbered
+    /// out-1.webp, out-2.webp, ...
+    #[arg(long, value_name = "N")]
+    paginate: Option<usize>,
+
+    /// Header template drawn in a band across the top of every page.
+    /// Fields: {title}, {page}, {pages}.
+    #[arg(long, value_name = "TEMPLATE")]
+    page_header: Option<String>,
+
+    /// Footer template drawn in a band across the bottom of every page;
+    /// same fields as --page-header.
+    #[arg(long, value_name = "TEMPLATE")]
+    page_footer: Option<String>,
+
+    /// The {title} value for --page-header/--page-footer templates
+    /// (default: the output file's stem).
+    #[arg(long, value_name = "TITLE")]
+    title: Option<String>,
+
+    /// Contact sheet mode: a grid of thumbnails, each with an EXIF band
+    /// underneath (camera, lens, focal length, aperture, shutter, ISO,
+    /// capture time) for reviewing shoots.
+    #[arg(long)]
+    contact_sheet: bool,
+
+    /// Choose black or white caption text per cell from the luminance of
+    /// the pixels under the label, instead of white-on-shadow.
+    #[arg(long)]
+    label_contrast: bool,
+
+    /// Opacity (0-1) of a semi-transparent band behind each caption,
+    /// toned to make the contrast-picked text pop.
+    #[arg(long, value_name = "OPACITY", requires = "label_contrast")]
+    label_scrim: Option<f64>,
+
+    /// Stamp each cell's 1-based index in its top-left corner, matching
+    /// the manifest row and the printed file list, so a proof sheet can
+    /// be answered with "prints of 14, 27 and 103".
+    #[arg(long)]
+    number_cells: bool,
+
+    /// Caption font: a TTF/OTF file path or an installed family name.
+    /// Repeat to form a fallback chain, tried left to right for each
+    /// character. Without it, captions use the built-in 5x7 pixel font.
+    #[arg(long, value_name = "FONT")]
+    font: Vec<String>,
+
+    /// Caption font size in pixels (at label scale 1) for --font.
+    #[arg(long, value_name = "PX", default_value_t = 8.0, requires = "font")]
+    font_size: f32,
+
+    /// Caption every cell from a template, e.g.
+    /// '{folder}/{stem} ({width}x{height})'. Fields: path, name, stem,
+    /// ext, folder, index, width, height, exif_date (with an optional
+    /// strftime-style format after a colon, month/day names following
+    /// --locale) and rating. Overrides manifest and sidecar captions.
+    #[arg(long, value_name = "TEMPLATE")]
+    label_template: Option<String>,
+
+    /// Width in pixels of the bar drawn between the halves of each pair.
+    #[arg(long, value_name = "PX", default_value_t = 0, requires = "pairs")]
+    divider: u32,
+
+    /// Comma-separated column headings for --pairs, e.g. 'before,after'.
+    #[arg(long, value_name = "A,B", requires = "pairs")]
+    labels: Option<String>,
+
+    /// Write a tiled pyramid (Deep Zoom or static IIIF level 0) instead of
+    /// a single image, streamed out of the memory map.
+    #[arg(long, value_enum, value_name = "LAYOUT")]
+    tiles: Option<TileLayout>,
+
+    /// Write a looping APNG animation (full color and alpha, unlike GIF);
+    /// the output extension must be .png or .apng.
+    #[arg(long, value_enum, value_name = "MODE")]
+    animate: Option<Animate>,
+
+    /// Print a roff man page to stdout and exit.
+    #[arg(long)]
+    generate_man: bool,
+
+    /// Format for progress messages.
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Write an audit report next to the output listing every input
+    /// that was dropped, with the reason (unsupported extension, size
+    /// or sharpness filter, duplicate, decode failure). JSON for a
+    /// .json path, tab-separated text otherwise.
+    #[arg(long, value_name = "FILE")]
+    report: Option<PathBuf>,
+
+    /// Plain text list of files that failed to decode, read at startup
+    /// to drop known-bad files up front and appended to at the end, so
+    /// watch and scheduled runs stop retrying the same corrupt inputs.
+    #[arg(long, value_name = "FILE")]
+    skip_list: Option<PathBuf>,
+
+    /// Decode the --skip-list files anyway this run and rewrite the
+    /// list from scratch, so files fixed in the meantime come back.
+    #[arg(long, requires = "skip_list")]
+    retry_skipped: bool,
+
+    /// Write a machine-readable JSON run summary (counts, skipped files,
+    /// canvas size, per-phase timings, output size) to this file.
+    #[arg(long, value_name = "FILE")]
+    summary: Option<PathBuf>,
+
+    /// Log peak RSS, temp-canvas bytes, and decoded bytes at the end of
+    /// the run — the numbers container and CI memory limits get sized
+    /// from.
+    #[arg(long)]
+    report_resources: bool,
+
+    /// Increase verbosity (-v: debug, -vv: trace).
+    #[arg(short = 'v', long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Only log errors.
+    #[arg(short = 'q', long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+enum Command {
+    /// Benchmark the pipeline (decode, resize filters, composite,
+    /// encode) on a sample directory or synthetic images and print
+    /// per-phase timings and throughput.
+    Bench {
+        /// How many images to run (synthetic, or a cap on the sample).
+        #[arg(long, default_value_t = 48)]
+        images: usize,
+        /// Sample directory of real images instead of synthetic ones.
+        #[arg(long, value_name = "DIR")]
+        input: Option<String>,
+    },
+    /// Compare two collage outputs cell by cell, report which cells
+    /// changed as JSON on stdout, and optionally write a visual diff
+    /// image with changed cells highlighted.
+    Diff {
+        /// The older collage image.
+        before: String,
+        /// The newer collage image.
+        after: String,
+        /// The manifest the older collage was rendered from; with both
+        /// manifests given, added and removed sources are reported too.
+        #[arg(long, value_name = "FILE", requires = "after_manifest")]
+        before_manifest: Option<String>,
+        /// The manifest the newer collage was rendered from.
+        #[arg(long, value_name = "FILE", requires = "before_manifest")]
+        after_manifest: Option<String>,
+        /// Write the newer collage with changed cells outlined here.
+        #[arg(long, value_name = "FILE")]
+        image: Option<String>,
+    },
+    /// Render with ImageMagick `montage` semantics: `-tile 4x`,
+    /// `-geometry +5+5`, `-label '%f'` and friends are translated onto
+    /// the normal pipeline, so scripts built around montage can switch
+    /// with minimal changes.
+    Montage {
+        /// Montage-style options followed by the inputs (image files or
+        /// one directory of subfolders) and the output file.
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, value_name = "ARGS")]
+        args: Vec<String>,
+    },
+    /// Extract the library's dominant colour palette (k-means over
+    /// thumbnails) as JSON on stdout and, optionally, a swatch image.
+    Palette {
+        /// Directory of images to sample.
+        input: String,
+        /// How many colours to extract.
+        #[arg(long, default_value_t = 6)]
+        colors: usize,
+        /// Also write the palette as a swatch image to this path.
+        #[arg(long, value_name = "FILE")]
+        swatch: Option<String>,
+    },
+    /// Preview the scanned library interactively: drop images, pin
+    /// favourites, tweak the cell size, then render with those choices
+    /// (requires building with `--features tui`).
+    Tui {
+        /// Directory of image subfolders to preview.
+        input: String,
+        /// Output collage file, rendered on `r`.
+        output: String,
+    },
+    /// Run an HTTP server: POST jobs, poll progress, download results.
+    Serve {
+        /// Port to listen on.
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+    },
+    /// Print shell completions for the given shell to stdout.
+    Completions {
+        /// Shell to generate completions for.
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+}
+
+/// Policy for canvases over the container's pixel limits (--too-big).
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum TooBig {
+    /// Fail up front, before any compositing work.
+    Fail,
+    /// Shrink the cell size until the canvas fits.
+    Downscale,
+    /// Split the run into pages that each fit.
+    Paginate,
+    /// Switch the output to PNG (no practical limit), with a warning.
+    SwitchFormat,
+}
+
+/// What --shuffle randomizes.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum Shuffle {
+    /// The whole set, across folders.
+    All,
+    /// Each folder's images, keeping the folder order intact.
+    WithinFolders,
+}
+
+/// How --merge-folders orders the images inside a merged album.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum MergeFolders {
+    /// Interleave by filename (natural order).
+    ByName,
+    /// Interleave chronologically by capture day.
+    ByDate,
+}
+
+/// Policy for degenerate inputs (--degenerate): images whose shape
+/// breaks the cell-fitting math rather than the decode.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum Degenerate {
+    /// Pad onto a transparent canvas of workable shape.
+    Pad,
+    /// Treat as a decode failure; --on-error decides the cell.
+    Placeholder,
+    /// Drop the image before layout.
+    Skip,
+}
+
+/// Policy for images that fail to decode (--on-error).
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum OnError {
+    /// Hatched cell with the truncated filename.
+    Placeholder,
+    /// Leave the cell empty.
+    Skip,
+    /// Abort the run (same as --strict).
+    Abort,
+}
+
+/// Page layouts supported by --layout.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum Layout {
+    /// A near-square grid of uniform cells.
+    Grid,
+    /// Images grouped by capture day under date label bands.
+    Timeline,
+    /// A month grid (--month) with a mini-collage in each day cell.
+    Calendar,
+    /// Organic mosaic of Voronoi cells, one cover-fitted image per cell.
+    Voronoi,
+    /// A featured centre image with the rest in concentric rings.
+    Radial,
+    /// Grid with alternate rows offset by half a cell, brickwork style.
+    Brick,
+    /// Isometric diamonds on an interleaved lattice, clipped at the edges.
+    Diagonal,
+    /// Fixed-height rows with native aspect ratios (variable cell widths).
+    Rows,
+    /// Overlapping tiles jittered off a lattice, photos-on-a-table style.
+    Scatter,
+    /// A 2D similarity map: thumbnails placed by projected image features.
+    Embedding,
+    /// A travel map: thumbnails plotted by their GPS EXIF position.
+    Geo,
+}
+
+/// Map projections supported by --projection (geo layout).
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum Projection {
+    /// Equirectangular: degrees map straight to pixels.
+    Equirect,
+    /// Web-style Mercator, latitudes clamped at ±85°.
+    Mercator,
+}
+
+/// Bundled option combinations supported by --preset.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum Preset {
+    /// A shareable annual summary: chronological month sections, a
+    /// title banner, and an auto-generated stats footer.
+    YearReview,
+}
+
+/// Grouping periods supported by --group-by.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum GroupBy {
+    /// One section per capture day.
+    Day,
+    /// One section per calendar month.
+    Month,
+    /// One section per year.
+    Year,
+}
+
+/// Positions supported by --qr-position.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum QrPosition {
+    /// QR cells before the photos.
+    First,
+    /// QR cells after the photos.
+    Last,
+}
+
+/// Fill modes supported by --background-mode.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum BackgroundMode {
+    /// Scale the texture (cropping as needed) to cover the canvas.
+    Cover,
+    /// Repeat the texture at its native size.
+    Tile,
+}
+
+/// Cell shapes supported by --cell-shape.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum CellShape {
+    /// The full square cell.
+    Square,
+    /// A centred circle, anti-aliased; see also --ring.
+    Circle,
+}
+
+/// Letterbox fills supported by --cell-fill.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum CellFill {
+    /// Extend the image's own edge pixels across the bars.
+    EdgeExtend,
+    /// Flood the bars with the image's average colour.
+    Average,
+}
+
+/// Paint orders supported by --z-order (scatter layout).
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum ZOrder {
+    /// Input order: later images paint on top.
+    Input,
+    /// Seeded shuffle of the paint order.
+    Random,
+    /// Largest files paint on top.
+    Size,
+}
+
+/// Duplicate-elimination modes supported by --dedup.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum Dedup {
+    /// Drop later byte-identical copies, keeping the first.
+    Exact,
+}
+
+/// Background/ink themes supported by --variants.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum Variant {
+    /// White background, dark band text.
+    Light,
+    /// Near-black background, light band text.
+    Dark,
+}
+
+impl Variant {
+    fn name(self) -> &'static str {
+        match self {
+            Variant::Light => "light",
+            Variant::Dark => "dark",
+        }
+    }
+
+    fn background(self) -> [u8; 4] {
+        match self {
+            Variant::Light => [255, 255, 255, 255],
+            Variant::Dark => [22, 22, 22, 255],
+        }
+    }
+
+    fn ink(self) -> [u8; 4] {
+        match self {
+            Variant::Light => [32, 32, 32, 255],
+            Variant::Dark => [224, 224, 224, 255],
+        }
+    }
+}
+
+/// Dithering styles supported by --dither.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum Dither {
+    /// Diffuse each pixel's rounding error onto its neighbours.
+    FloydSteinberg,
+    /// Threshold against a fixed 4x4 Bayer pattern.
+    Ordered,
+    /// Snap every pixel to its nearest palette entry.
+    None,
+}
+
+/// Weight sources supported by --weight-by.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum WeightBy {
+    /// File size in bytes.
+    Size,
+    /// EXIF star rating (unrated counts as one star).
+    Rating,
+    /// The manifest's weight column (missing weights count as 1).
+    Manifest,
+}
+
+/// Cell fill orders supported by --fill-order.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum FillOrder {
+    /// Left to right, top to bottom.
+    Row,
+    /// Top to bottom, left to right; suits tall outputs.
+    Column,
+    /// Row-major with every other row reversed (boustrophedon).
+    Snake,
+}
+
+/// Tile pyramid layouts supported by --tiles.
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+enum TileLayout {
+    /// Deep Zoom (.dzi + _files directory), servable by OpenSeadragon.
+    Dzi,
+    /// Static IIIF Image API level-0 tree with info.json.
+    Iiif,
+    /// Tiled, pyramidal BigTIFF in a single file (no 4 GB / 65k limits).
+    Tiff,
+}
+
+/// Animation modes supported by --animate.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+enum Animate {
+    /// Cycle the --paginate pages as the frames of one APNG.
+    Pages,
+    /// Reveal the grid one cell at a time, each cell a delta frame.
+    BuildUp,
+}
+
+/// Compares like a human: runs of digits compare by numeric value, so
+/// `img2` sorts before `img10`. Ties on value (e.g. `img02` vs `img2`)
+/// fall back to the textual form to keep the order total.
+fn natural_cmp(a: &str, b: &str) -> cmp::Ordering {
+    let mut ai = a.chars().peekable();
+    let mut bi = b.chars().peekable();
+    loop {
+        match (ai.peek().copied(), bi.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return cmp::Ordering::Less,
+            (Some(_), None) => return cmp::Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let mut run_a = String::new();
+                while let Some(&c) = ai.peek().filter(|c| c.is_ascii_digit()) {
+                    run_a.push(c);
+                    ai.next();
+                }
+                let mut run_b = String::new();
+                while let Some(&c) = bi.peek().filter(|c| c.is_ascii_digit()) {
+                    run_b.push(c);
+                    bi.next();
+                }
+                // Compare numerically without parsing: strip leading zeros,
+                // then longer run wins, then lexicographic.
+                let va = run_a.trim_start_matches('0');
+                let vb = run_b.trim_start_matches('0');
+                let ord = va.len().cmp(&vb.len()).then_with(|| va.cmp(vb));
+                if ord != cmp::Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                ai.next();
+                bi.next();
+            }
+        }
+    }
+}
+
+/// Orders two paths naturally or, with --lexicographic, as plain bytes.
+fn path_cmp(a: &PathBuf, b: &PathBuf, lexicographic: bool) -> cmp::Ordering {
+    if lexicographic {
+        a.cmp(b)
+    } else {
+        natural_cmp(&a.to_string_lossy(), &b.to_string_lossy())
+    }
 }
 
-/// Recursively gathers image paths from subfolders (sorted by folder and filename).
-fn get_sorted_image_paths(root_dir: &str) -> (Vec<PathBuf>, Vec<PathBuf>) {
-    // List subdirectories (folders) in the root directory.
-    let mut subfolders = fs::read_dir(root_dir)
-        .expect("Unable to read input directory")
+/// Scanner behaviour, registered once from --follow-symlinks and
+/// --include-hidden before any directory is walked.
+static FOLLOW_SYMLINKS: AtomicBool = AtomicBool::new(false);
+static INCLUDE_HIDDEN: AtomicBool = AtomicBool::new(false);
+
+/// Whether the scanner should skip this directory entry outright:
+/// hidden names without --include-hidden, symlinks without
+/// --follow-symlinks.
+fn scanner_skips(entry: &fs::DirEntry) -> bool {
+    if !INCLUDE_HIDDEN.load(Ordering::Relaxed)
+        && entry.file_name().to_string_lossy().starts_with('.')
+    {
+        return true;
+    }
+    !FOLLOW_SYMLINKS.load(Ordering::Relaxed)
+        && entry.file_type().map(|t| t.is_symlink()).unwrap_or(false)
+}
+
+/// Lists the sorted subfolders of the root directory, minus any a
+/// `.collageignore` at the root excludes. Followed symlinks are
+/// deduplicated by canonical path, so cycles and doubled albums scan
+/// once.
+fn get_sorted_subfolders(root_dir: &str, lexicographic: bool) -> error::Result<Vec<PathBuf>> {
+    let ignored = ignore::Ignore::for_folder(std::path::Path::new(root_dir));
+    let mut seen = std::collections::HashSet::new();
+    let mut subfolders = fs::read_dir(root_dir)?
         .filter_map(|entry| {
             let entry = entry.ok()?;
-            if entry.path().is_dir() {
+            if scanner_skips(&entry) {
+                return None;
+            }
+            if entry.path().is_dir() && !ignored.excludes(&entry.path(), true) {
+                if let Ok(canonical) = entry.path().canonicalize() {
+                    if !seen.insert(canonical) {
+                        return None;
+                    }
+                }
                 Some(entry.path())
             } else {
                 None
             }
         })
         .collect::<Vec<_>>();
-    subfolders.sort();
+    subfolders.sort_by(|a, b| path_cmp(a, b, lexicographic));
+    Ok(subfolders)
+}
 
-    // For each folder, collect image paths with .webp, .jpg, or .jpeg extension.
-    let mut image_paths = Vec::new();
-    for folder in &subfolders {
-        let mut imgs_in_folder = fs::read_dir(folder)
-            .unwrap()
-            .filter_map(|entry| {
-                let entry = entry.ok()?;
-                if entry.path().is_file() {
-                    let ext = entry
-                        .path()
-                        .extension()
-                        .and_then(|s| s.to_str())
-                        .unwrap_or("")
-                        .to_lowercase();
-                    if ext == "webp" || ext == "jpg" || ext == "jpeg" {
-                        Some(entry.path())
-                    } else {
-                        None
-                    }
+/// Collects image paths (the build's accepted extensions, see
+/// [`scanner::accepted_extension`]) in one folder, sorted by filename;
+/// `.collageignore` rules (the root's and the folder's own) are
+/// honoured.
+fn images_in_folder(folder: &std::path::Path, lexicographic: bool) -> Vec<PathBuf> {
+    let ignored = ignore::Ignore::for_folder(folder);
+    let mut imgs_in_folder = fs::read_dir(folder)
+        .unwrap()
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            if scanner_skips(&entry) {
+                return None;
+            }
+            if entry.path().is_file() && !ignored.excludes(&entry.path(), false) {
+                let ext = entry
+                    .path()
+                    .extension()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("")
+                    .to_lowercase();
+                if scanner::accepted_extension(&ext) {
+                    Some(entry.path())
                 } else {
+                    report::note(&entry.path(), format!("unsupported extension {:?}", ext));
                     None
                 }
-            })
-            .collect::<Vec<_>>();
-        imgs_in_folder.sort();
-        image_paths.extend(imgs_in_folder);
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    imgs_in_folder.sort_by(|a, b| path_cmp(a, b, lexicographic));
+    imgs_in_folder
+}
+
+/// Recursively gathers image paths from subfolders (sorted by folder and
+/// filename), keeping at most `limit` images per folder if set.
+fn get_sorted_image_paths(
+    root_dir: &str,
+    limit: Option<usize>,
+    lexicographic: bool,
+) -> error::Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let subfolders = get_sorted_subfolders(root_dir, lexicographic)?;
+    let mut image_paths = Vec::new();
+    for folder in &subfolders {
+        let mut imgs = images_in_folder(folder, lexicographic);
+        if let Some(limit) = limit {
+            imgs.truncate(limit);
+        }
+        image_paths.extend(imgs);
     }
-    (image_paths, subfolders)
+    Ok((image_paths, subfolders))
 }
 
-/// Creates the collage using a disk‑backed memory map to reduce in‑memory usage.
-fn create_collage(image_paths: &[PathBuf], cell_size: u32, output_path: &str) -> image::ImageResult<()> {
-    let total_images = image_paths.len() as u32;
-    if total_images == 0 {
-        eprintln!("No images found!");
-        return Ok(());
+/// Parses an aspect ratio given as `W:H` (e.g. `16:9`), returning W/H.
+fn parse_aspect(spec: &str) -> error::Result<f64> {
+    let bad = || Error::Usage(format!("invalid --aspect {:?}; expected W:H, e.g. 16:9", spec));
+    let (w, h) = spec.split_once(':').ok_or_else(bad)?;
+    let w: f64 = w.trim().parse().map_err(|_| bad())?;
+    let h: f64 = h.trim().parse().map_err(|_| bad())?;
+    if !w.is_finite() || !h.is_finite() || w <= 0.0 || h <= 0.0 {
+        return Err(bad());
     }
-    // Calculate grid dimensions (nearly square).
-    let ncols = (total_images as f64).sqrt().ceil() as u32;
-    let nrows = (total_images + ncols - 1) / ncols; // ceiling division
-    let collage_width = ncols * cell_size;
-    let collage_height = nrows * cell_size;
-    let num_pixels = (collage_width * collage_height) as usize;
-    let buffer_size = num_pixels * 4; // 4 channels per pixel (RGBA)
+    Ok(w / h)
+}
 
-    // Create a temporary file to back our memmap.
-    let mut file = tempfile().expect("failed to create temp file");
-    file.set_len(buffer_size as u64)
-        .expect("failed to set file length");
+/// A placed cell: grid position and span in cells.
+#[derive(Clone, Copy)]
+struct CellRect {
+    col: u32,
+    row: u32,
+    span_w: u32,
+    span_h: u32,
+}
 
-    // Memory-map the file.
-    let mut mmap = unsafe { MmapMut::map_mut(&file).expect("failed to map file") };
+/// Places entries into a grid, honoring per-entry spans. Returns the cell
+/// rectangles (one per entry, same order) and the grid size (ncols, nrows).
+/// With all spans at 1x1 this degenerates to plain row-major filling.
+/// Column and snake fill orders only apply to span-free layouts; spanned
+/// entries fall back to row-major first-fit. `aspect` (width over height)
+/// skews the column count towards the requested canvas proportions;
+/// `columns` pins it outright.
+fn place_entries(
+    entries: &[ManifestEntry],
+    fill_order: FillOrder,
+    aspect: f64,
+    columns: Option<u32>,
+    reserved: &[CellRect],
+) -> (Vec<CellRect>, u32, u32) {
+    let total_cells: u32 = entries.iter().map(|e| e.span_cells().0 * e.span_cells().1).sum();
+    let max_span_w = entries.iter().map(|e| e.span_cells().0).max().unwrap_or(1);
+    let chosen = match columns {
+        Some(n) => n,
+        None => (total_cells as f64 * aspect).sqrt().ceil() as u32,
+    };
+    let mut ncols = cmp::max(chosen, max_span_w);
+    // The grid must at least reach every reserved cell.
+    ncols = cmp::max(ncols, reserved.iter().map(|r| r.col + r.span_w).max().unwrap_or(0));
 
-    // Initialize the memory to a “transparent white” background:
-    // Set R, G, B to 255 and Alpha to 0 for every pixel.
-    for i in 0..num_pixels {
-        let offset = i * 4;
-        mmap[offset] = 255;     // R
-        mmap[offset + 1] = 255; // G
-        mmap[offset + 2] = 255; // B
-        mmap[offset + 3] = 0;   // A
+    if fill_order != FillOrder::Row {
+        if entries.iter().any(|e| e.span_cells() != (1, 1)) || !reserved.is_empty() {
+            tracing::warn!("--fill-order only supports 1x1 spans and no pins; using row order");
+        } else {
+            let n = entries.len() as u32;
+            let nrows = n.div_ceil(ncols);
+            let rects = (0..n)
+                .map(|k| {
+                    let (col, row) = match fill_order {
+                        FillOrder::Column => (k / nrows, k % nrows),
+                        _ => {
+                            let (row, col) = (k / ncols, k % ncols);
+                            // Snake: odd rows run right to left.
+                            if row % 2 == 1 {
+                                (ncols - 1 - col, row)
+                            } else {
+                                (col, row)
+                            }
+                        }
+                    };
+                    CellRect { col, row, span_w: 1, span_h: 1 }
+                })
+                .collect();
+            return (rects, ncols, nrows);
+        }
     }
 
-    // Process each image and paste it into its cell in the collage.
-    for (idx, img_path) in image_paths.iter().enumerate() {
-        // Attempt to open the image; if it fails, skip it.
-        let img = match image::open(img_path) {
-            Ok(im) => im,
-            Err(e) => {
-                eprintln!("Error processing {:?}: {}", img_path, e);
-                // Use a 1x1 empty image as fallback.
-                DynamicImage::new_rgba8(1, 1)
-            }
-        };
-
-        let (orig_w, orig_h) = img.dimensions();
-        // Compute scale factor so that the longer side equals the cell size.
-        let scale_factor = cell_size as f32 / (cmp::max(orig_w, orig_h) as f32);
-        let new_w = (orig_w as f32 * scale_factor).round() as u32;
-        let new_h = (orig_h as f32 * scale_factor).round() as u32;
-        let resized = img.resize(new_w, new_h, FilterType::Lanczos3).to_rgba8();
-
-        // Determine which cell (column, row) the image should go in.
-        let col = (idx as u32) % ncols;
-        let row = (idx as u32) / ncols;
-        let cell_x = col * cell_size;
-        let cell_y = row * cell_size;
-        // Center the resized image within its cell.
-        let offset_x = cell_x + (cell_size - new_w) / 2;
-        let offset_y = cell_y + (cell_size - new_h) / 2;
-
-        // Copy pixels from the resized image into the correct region of the memmap.
-        for y in 0..new_h {
-            for x in 0..new_w {
-                let pixel = resized.get_pixel(x, y);
-                let target_x = offset_x + x;
-                let target_y = offset_y + y;
-                if target_x < collage_width && target_y < collage_height {
-                    let index = ((target_y * collage_width + target_x) * 4) as usize;
-                    mmap[index] = pixel[0];
-                    mmap[index + 1] = pixel[1];
-                    mmap[index + 2] = pixel[2];
-                    mmap[index + 3] = pixel[3];
-                }
-            }
-        }
-    }
-    mmap.flush().expect("failed to flush mmap");
-
-    // At this point, the memmap contains the full collage.
-    // Convert the memory-mapped data into a Vec<u8>.
-    // (The final conversion requires an owned buffer.)
-    let data = mmap.to_vec();
-    let collage_buffer = ImageBuffer::<Rgba<u8>, Vec<u8>>::from_raw(collage_width, collage_height, data)
-        .expect("Failed to create ImageBuffer");
-
-    // Save the final collage in WebP format.
-    collage_buffer.save_with_format(output_path, image::ImageFormat::WebP)?;
-    println!("Collage saved to '{}'", output_path);
-    Ok(())
-}
+    // Occupancy grid, grown row by row as needed.
+    let mut occupied: Vec<bool> = Vec::new();
+    let mut nrows = 0u32;
+    let ensure_rows = |occupied: &mut Vec<bool>, rows: u32| {
+        while (occupied.len() as u32) < rows * ncols {
+            occupied.push(false);
+        }
+    };
 
-fn main() {
-    let args = Args::parse();
-
-    let (image_paths, subfolders) = get_sorted_image_paths(&args.input_dir);
-
-    // Count and print images per subfolder.
-    let mut total_count = 0;
-    println!("Image counts per folder:");
-    for folder in subfolders {
-        let count = fs::read_dir(&folder)
-            .unwrap()
-            .filter(|entry| {
-                if let Ok(entry) = entry {
-                    if entry.path().is_file() {
-                        let ext = entry
-                            .path()
-                            .extension()
-                            .and_then(|s| s.to_str())
-                            .unwrap_or("")
-                            .to_lowercase();
-                        return ext == "webp" || ext == "jpg" || ext == "jpeg";
+    let mut rects = Vec::with_capacity(entries.len());
+    for rect in reserved {
+        ensure_rows(&mut occupied, rect.row + rect.span_h);
+        for dy in 0..rect.span_h {
+            for dx in 0..rect.span_w {
+                occupied[((rect.row + dy) * ncols + rect.col + dx) as usize] = true;
+            }
+        }
+        nrows = cmp::max(nrows, rect.row + rect.span_h);
+    }
+    for entry in entries {
+        let (span_w, span_h) = entry.span_cells();
+        let span_w = cmp::min(span_w, ncols);
+        // First-fit scan: top-down, left-to-right.
+        let mut placed = None;
+        let mut row = 0;
+        while placed.is_none() {
+            ensure_rows(&mut occupied, row + span_h);
+            'cols: for col in 0..=(ncols - span_w) {
+                for dy in 0..span_h {
+                    for dx in 0..span_w {
+                        if occupied[((row + dy) * ncols + col + dx) as usize] {
+                            continue 'cols;
+                        }
                     }
                 }
-                false
+                placed = Some((col, row));
+                break;
+            }
+            row += 1;
+        }
+        let (col, row) = placed.unwrap();
+        for dy in 0..span_h {
+            for dx in 0..span_w {
+                occupied[((row + dy) * ncols + col + dx) as usize] = true;
+            }
+        }
+        nrows = cmp::max(nrows, row + span_h);
+        rects.push(CellRect {
+            col,
+            row,
+            span_w,
+            span_h,
+        });
+    }
+    (rects, ncols, nrows)
+}
+
+/// Inserts a width suffix before the file extension: `out.webp` -> `out_800.webp`.
+fn with_width_suffix(path: &str, width: u32) -> String {
+    match path.rsplit_once('.') {
+        Some((stem, ext)) => format!("{}_{}.{}", stem, width, ext),
+        None => format!("{}_{}", path, width),
+    }
+}
+
+/// Fills a cell with a hatched pattern and the (truncated) filename so a
+/// failed image is visibly marked rather than silently blank.
+fn draw_placeholder(
+    buf: &mut [u8],
+    (canvas_w, canvas_h): (u32, u32),
+    (cell_x, cell_y, cell_w, cell_h): (u32, u32, u32, u32),
+    cell_size: u32,
+    path: &std::path::Path,
+) {
+    for y in cell_y..(cell_y + cell_h).min(canvas_h) {
+        for x in cell_x..(cell_x + cell_w).min(canvas_w) {
+            // Diagonal stripes every 16 px on a light grey field.
+            let on_stripe = (x + y) % 16 < 3;
+            let value = if on_stripe { 150 } else { 220 };
+            let index = ((y * canvas_w + x) * 4) as usize;
+            buf[index] = value;
+            buf[index + 1] = value;
+            buf[index + 2] = value;
+            buf[index + 3] = 255;
+        }
+    }
+    let scale = cmp::max(1, cell_size / 200);
+    let mut name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let max_chars = (cell_w / (text::GLYPH_ADVANCE * scale)).saturating_sub(1) as usize;
+    // The budget counts display characters, not bytes: a byte-indexed
+    // truncate both over-trims multibyte names and can split a char.
+    if name.chars().count() > max_chars {
+        name = name.chars().take(max_chars.saturating_sub(1)).collect();
+        name.push('\u{2026}');
+    }
+    let tw = text::text_width(&name, scale);
+    let tx = cell_x as i64 + (cell_w as i64 - tw as i64) / 2;
+    let ty = cell_y as i64 + (cell_h as i64 - text::line_height(scale) as i64) / 2;
+    text::draw_text(
+        buf, (canvas_w, canvas_h), (tx, ty),
+        scale, [64, 64, 64, 255], &name,
+    );
+}
+
+/// Maps grid slots to gaps for --gap-every/--gap-ratio: a `true` slot
+/// stays background-coloured. Exactly `n` slots are `false`, one per
+/// entry, so every image still gets a cell.
+fn gap_slots(n: usize, args: &Args) -> error::Result<Vec<bool>> {
+    if let Some(every) = args.gap_every {
+        if every < 2 {
+            return Err(Error::Usage("--gap-every must be at least 2".to_string()));
+        }
+        let mut slots = Vec::new();
+        let mut placed = 0;
+        while placed < n {
+            if (slots.len() + 1).is_multiple_of(every) {
+                slots.push(true);
+            } else {
+                slots.push(false);
+                placed += 1;
+            }
+        }
+        return Ok(slots);
+    }
+    if let Some(ratio) = args.gap_ratio {
+        if !(0.0..1.0).contains(&ratio) {
+            return Err(Error::Usage("--gap-ratio must be in [0, 1)".to_string()));
+        }
+        // A ratio of the final cell count: g / (n + g) = ratio.
+        let gaps = (n as f64 * ratio / (1.0 - ratio)).round() as usize;
+        let total = n + gaps;
+        let mut slots = vec![false; total];
+        // Seeded partial Fisher-Yates over the slot indices, as --sample.
+        let mut state = args.seed.wrapping_mul(0x9E37_79B9_7F4A_7C15).wrapping_add(1);
+        let mut indices: Vec<usize> = (0..total).collect();
+        for i in 0..gaps {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let j = i + (state % (total - i) as u64) as usize;
+            indices.swap(i, j);
+            slots[indices[i]] = true;
+        }
+        return Ok(slots);
+    }
+    Ok(vec![false; n])
+}
+
+/// Formats a count with thousands separators ("1234" -> "1,234").
+fn group_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, ch) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// Fills a cell with a plain grey field and a centred "+N more" label,
+/// standing in for the images that --max-images cut.
+fn draw_overflow_tile(
+    buf: &mut [u8],
+    (canvas_w, canvas_h): (u32, u32),
+    (cell_x, cell_y, cell_w, cell_h): (u32, u32, u32, u32),
+    cell_size: u32,
+    overflow: usize,
+) {
+    for y in cell_y..(cell_y + cell_h).min(canvas_h) {
+        for x in cell_x..(cell_x + cell_w).min(canvas_w) {
+            let index = ((y * canvas_w + x) * 4) as usize;
+            buf[index..index + 4].copy_from_slice(&[230, 230, 230, 255]);
+        }
+    }
+    let scale = cmp::max(1, cell_size / 200);
+    let label = format!("+{} more", group_thousands(overflow));
+    let tw = text::text_width(&label, scale);
+    let tx = cell_x as i64 + (cell_w as i64 - tw as i64) / 2;
+    let ty = cell_y as i64 + (cell_h as i64 - text::line_height(scale) as i64) / 2;
+    text::draw_text(
+        buf, (canvas_w, canvas_h), (tx, ty),
+        scale, [64, 64, 64, 255], &label,
+    );
+}
+
+/// Draws a caption centred at the bottom of the cell. By default white
+/// text over a 1px offset black shadow; with --label-contrast the text
+/// is black or white, whichever contrasts with the pixels under the
+/// label, over an optional opposite-coloured --label-scrim band.
+fn draw_caption(
+    buf: &mut [u8],
+    (canvas_w, canvas_h): (u32, u32),
+    (cell_x, cell_y, cell_w, cell_h): (u32, u32, u32, u32),
+    cell_size: u32,
+    args: &Args,
+    caption: &str,
+) {
+    let scale = cmp::max(1, cell_size / 200);
+    let tw = text::text_width(caption, scale);
+    let th = text::line_height(scale);
+    let tx = cell_x as i64 + (cell_w as i64 - tw as i64) / 2;
+    let ty = (cell_y + cell_h) as i64 - th as i64 - scale as i64;
+    if !args.label_contrast {
+        text::draw_text(
+            buf, (canvas_w, canvas_h),
+            (tx + scale as i64, ty + scale as i64),
+            scale, [0, 0, 0, 255], caption,
+        );
+        text::draw_text(buf, (canvas_w, canvas_h), (tx, ty), scale, [255, 255, 255, 255], caption);
+        return;
+    }
+
+    // The label's bounding box, padded by one scale unit and clipped to
+    // the canvas; its mean luminance decides the text colour.
+    let pad = scale as i64;
+    let x0 = (tx - pad).clamp(0, canvas_w as i64) as u32;
+    let y0 = (ty - pad).clamp(0, canvas_h as i64) as u32;
+    let x1 = (tx + tw as i64 + pad).clamp(0, canvas_w as i64) as u32;
+    let y1 = (ty + th as i64 + pad).clamp(0, canvas_h as i64) as u32;
+    let mut luma_sum = 0.0f64;
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let index = ((y as u64 * canvas_w as u64 + x as u64) * 4) as usize;
+            luma_sum += 0.299 * buf[index] as f64
+                + 0.587 * buf[index + 1] as f64
+                + 0.114 * buf[index + 2] as f64;
+        }
+    }
+    let pixels = ((x1 - x0) as u64 * (y1 - y0) as u64).max(1);
+    let dark_region = (luma_sum / pixels as f64) < 128.0;
+    let text_color = if dark_region { 255 } else { 0 };
+
+    if let Some(opacity) = args.label_scrim {
+        // The scrim takes the region's own tone so the text pops against it.
+        let scrim = if dark_region { 0.0 } else { 255.0 };
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let index = ((y as u64 * canvas_w as u64 + x as u64) * 4) as usize;
+                for channel in &mut buf[index..index + 3] {
+                    *channel =
+                        (scrim * opacity + *channel as f64 * (1.0 - opacity)).round() as u8;
+                }
+                buf[index + 3] = buf[index + 3].max((opacity * 255.0).round() as u8);
+            }
+        }
+    }
+    text::draw_text(
+        buf, (canvas_w, canvas_h), (tx, ty),
+        scale, [text_color, text_color, text_color, 255], caption,
+    );
+}
+
+/// Stamps the cell's 1-based index in its top-left corner
+/// (--number-cells), white over a shadow so it reads on any photo.
+fn draw_cell_number(
+    buf: &mut [u8],
+    (canvas_w, canvas_h): (u32, u32),
+    (cell_x, cell_y): (u32, u32),
+    cell_size: u32,
+    number: usize,
+) {
+    let scale = cmp::max(1, cell_size / 200);
+    let label = number.to_string();
+    let (tx, ty) = (cell_x as i64 + 2 * scale as i64, cell_y as i64 + 2 * scale as i64);
+    text::draw_text(
+        buf, (canvas_w, canvas_h),
+        (tx + scale as i64, ty + scale as i64),
+        scale, [0, 0, 0, 255], &label,
+    );
+    text::draw_text(buf, (canvas_w, canvas_h), (tx, ty), scale, [255, 255, 255, 255], &label);
+}
+
+/// A stable hash of everything that shapes the grid canvas, keying the
+/// --resume journal so state from a run with different parameters is
+/// never reused.
+fn resume_hash(entries: &[ManifestEntry], args: &Args) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for entry in entries {
+        entry.path.hash(&mut hasher);
+        entry.span.hash(&mut hasher);
+        entry.rotation.map(f64::to_bits).hash(&mut hasher);
+    }
+    args.cell_size.hash(&mut hasher);
+    args.seed.hash(&mut hasher);
+    format!(
+        "{:?}{:?}{:?}{:?}{:?}{:?}{:?}",
+        args.layout, args.fill_order, args.aspect, args.gap_every, args.gap_ratio,
+        args.rotate_jitter, args.pin
+    )
+    .hash(&mut hasher);
+    hasher.finish()
+}
+
+/// One clickable region of the --image-map: link target, alt text, and
+/// the cell rectangle in canvas pixels.
+struct MapArea {
+    href: String,
+    alt: String,
+    rect: (u32, u32, u32, u32),
+}
+
+/// Minimal escaping for attribute values in the HTML image map.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Writes the --image-map file: an HTML <map> fragment, or a JSON array
+/// when the file name ends in .json. Coordinates are canvas pixels,
+/// before any --margin/--frame wrapping.
+fn write_image_map(path: &std::path::Path, areas: &[MapArea]) -> error::Result<()> {
+    let json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let text = if json {
+        let list: Vec<serde_json::Value> = areas
+            .iter()
+            .map(|area| {
+                serde_json::json!({
+                    "href": area.href,
+                    "alt": area.alt,
+                    "x": area.rect.0,
+                    "y": area.rect.1,
+                    "width": area.rect.2,
+                    "height": area.rect.3,
+                })
+            })
+            .collect();
+        serde_json::to_string_pretty(&list).expect("image map serializes")
+    } else {
+        let mut out = String::from("<map name=\"collage\">\n");
+        for area in areas {
+            let (x, y, w, h) = area.rect;
+            out.push_str(&format!(
+                "  <area shape=\"rect\" coords=\"{},{},{},{}\" href=\"{}\" alt=\"{}\">\n",
+                x, y, x + w, y + h,
+                escape_html(&area.href),
+                escape_html(&area.alt),
+            ));
+        }
+        out.push_str("</map>\n");
+        out
+    };
+    fs::write(path, text).map_err(|e| Error::output(&path.to_string_lossy(), e))
+}
+
+/// Writes the --index table: one `index,folder,filename,row,col,x,y,w,h`
+/// CSV row per placed image, to the given file or (with `-`) stdout.
+/// Coordinates are canvas pixels, before any --margin/--frame wrapping.
+fn write_cell_index(
+    target: &str,
+    entries: &[ManifestEntry],
+    rects: &[&CellRect],
+    cell_size: u32,
+) -> error::Result<()> {
+    // Minimal CSV quoting, so commas and quotes in paths survive.
+    let quote = |field: &str| format!("\"{}\"", field.replace('"', "\"\""));
+    let base = NUMBER_BASE.load(Ordering::Relaxed);
+    let mut out = String::from("index,folder,filename,row,col,x,y,w,h\n");
+    for (i, (entry, rect)) in entries.iter().zip(rects.iter()).enumerate() {
+        let folder = entry
+            .path
+            .parent()
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let filename = entry
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            base + i + 1,
+            quote(&folder),
+            quote(&filename),
+            rect.row,
+            rect.col,
+            rect.col * cell_size,
+            rect.row * cell_size,
+            rect.span_w * cell_size,
+            rect.span_h * cell_size,
+        ));
+    }
+    if target == "-" {
+        use std::io::Write;
+        std::io::stdout()
+            .write_all(out.as_bytes())
+            .map_err(|e| Error::output(target, e))
+    } else {
+        fs::write(target, out).map_err(|e| Error::output(target, e))
+    }
+}
+
+/// Writes the --gallery page: a standalone HTML document embedding the
+/// finished collage with a client-side image map, one clickable area
+/// per cell pointing at the manifest url (or the source path). The
+/// image is referenced by file name, so the page works beside the
+/// collage wherever the pair is copied.
+fn write_gallery(
+    path: &std::path::Path,
+    output_path: &str,
+    areas: &[MapArea],
+) -> error::Result<()> {
+    let image = std::path::Path::new(output_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| output_path.to_string());
+    let mut out = String::from(
+        "<!doctype html>\n<html>\n<head><meta charset=\"utf-8\"><title>Collage</title></head>\n<body>\n",
+    );
+    out.push_str(&format!(
+        "<img src=\"{}\" usemap=\"#collage\" style=\"max-width:100%;height:auto\">\n",
+        escape_html(&image)
+    ));
+    out.push_str("<map name=\"collage\">\n");
+    for area in areas {
+        let (x, y, w, h) = area.rect;
+        out.push_str(&format!(
+            "  <area shape=\"rect\" coords=\"{},{},{},{}\" href=\"{}\" alt=\"{}\">\n",
+            x, y, x + w, y + h,
+            escape_html(&area.href),
+            escape_html(&area.alt),
+        ));
+    }
+    out.push_str("</map>\n</body>\n</html>\n");
+    fs::write(path, out).map_err(|e| Error::output(&path.to_string_lossy(), e))
+}
+
+/// Parses `--frame` as `PX:#rrggbb`.
+fn parse_frame(spec: &str) -> error::Result<(u32, [u8; 4])> {
+    let bad = || Error::Usage(format!("invalid --frame {:?}; expected PX:#rrggbb, e.g. 12:#222222", spec));
+    let (px, color) = spec.split_once(':').ok_or_else(bad)?;
+    let px = px.trim().parse().map_err(|_| bad())?;
+    let color = background::parse_color(color.trim()).ok_or_else(bad)?;
+    Ok((px, color))
+}
+
+/// How --cell-border picks each cell's colour.
+#[derive(Clone, Copy)]
+enum CellBorderColor {
+    /// One fixed colour for every cell.
+    Fixed([u8; 4]),
+    /// Each image's own dominant colour.
+    Dominant,
+    /// The parent folder's --palette colour.
+    Folder,
+}
+
+/// Parses `--cell-border` as `PX:COLOR` or a bare `COLOR` (4 px), where
+/// COLOR is `#rrggbb` or `dominant`.
+fn parse_cell_border(spec: &str) -> error::Result<(u32, CellBorderColor)> {
+    let bad = || {
+        Error::Usage(format!(
+            "invalid --cell-border {:?}; expected PX:#rrggbb, PX:dominant, or a bare colour",
+            spec
+        ))
+    };
+    let (px, color) = match spec.split_once(':') {
+        Some((px, color)) => (px.trim().parse().map_err(|_| bad())?, color.trim()),
+        None => (4, spec.trim()),
+    };
+    let color = if color.eq_ignore_ascii_case("dominant") {
+        CellBorderColor::Dominant
+    } else if color.eq_ignore_ascii_case("folder") {
+        CellBorderColor::Folder
+    } else {
+        CellBorderColor::Fixed(background::parse_color(color).ok_or_else(bad)?)
+    };
+    Ok((px, color))
+}
+
+/// Draws a border of `px` pixels just inside the cell's edge.
+fn draw_cell_border(
+    buf: &mut [u8],
+    (canvas_w, canvas_h): (u32, u32),
+    (cell_x, cell_y, cell_w, cell_h): (u32, u32, u32, u32),
+    px: u32,
+    color: [u8; 4],
+) {
+    let px = px.min(cell_w / 2).min(cell_h / 2);
+    for y in 0..cell_h {
+        for x in 0..cell_w {
+            let edge = x.min(cell_w - 1 - x).min(y).min(cell_h - 1 - y);
+            if edge >= px {
+                continue;
+            }
+            let tx = cell_x + x;
+            let ty = cell_y + y;
+            if tx >= canvas_w || ty >= canvas_h {
+                continue;
+            }
+            let index = ((ty as u64 * canvas_w as u64 + tx as u64) * 4) as usize;
+            buf[index..index + 4].copy_from_slice(&color);
+        }
+    }
+}
+
+/// Current page number and page count, stored by the --paginate driver
+/// and read back when save_canvas expands header/footer templates.
+static PAGE: AtomicUsize = AtomicUsize::new(1);
+static PAGES: AtomicUsize = AtomicUsize::new(1);
+
+/// Index of the first entry on the current page, stored by the
+/// --paginate driver so --number-cells keeps counting across pages.
+static NUMBER_BASE: AtomicUsize = AtomicUsize::new(0);
+
+/// Expands a --page-header/--page-footer template: {title} (from --title
+/// or the output file stem), {page} and {pages}.
+fn page_band_text(template: &str, args: &Args, output_path: &str) -> String {
+    let title = args.title.clone().unwrap_or_else(|| {
+        std::path::Path::new(output_path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    });
+    template
+        .replace("{title}", &title)
+        .replace("{page}", &PAGE.load(Ordering::Relaxed).to_string())
+        .replace("{pages}", &PAGES.load(Ordering::Relaxed).to_string())
+}
+
+/// The per-page output path: "out.webp" becomes "out-3.webp" for page 3.
+fn page_output_path(output_path: &str, page: usize) -> String {
+    let path = std::path::Path::new(output_path);
+    let stem = path.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    let numbered = match path.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, page, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, page),
+    };
+    path.with_file_name(numbered).to_string_lossy().into_owned()
+}
+
+/// Encodes the finished canvas to `output_path`, first wrapping it in
+/// the --page-header/--page-footer bands, the outer --margin
+/// (background-filled), and the --frame when set. With --variants the
+/// same composite is flattened onto each variant's background and
+/// written once per variant instead.
+fn save_canvas(
+    pixels: &[u8],
+    (width, height): (u32, u32),
+    args: &Args,
+    output_path: &str,
+) -> error::Result<()> {
+    if args.variants.is_empty() {
+        return save_variant(pixels, (width, height), args, output_path, None);
+    }
+    for &variant in &args.variants {
+        let path = variant_output_path(output_path, variant);
+        save_variant(pixels, (width, height), args, &path, Some(variant))?;
+        tracing::info!("Variant saved to '{}'", path);
+    }
+    Ok(())
+}
+
+/// The per-variant output name: collage.webp -> collage-dark.webp.
+fn variant_output_path(output_path: &str, variant: Variant) -> String {
+    let path = std::path::Path::new(output_path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = variant.name();
+    let suffixed = match path.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, name, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, name),
+    };
+    path.with_file_name(suffixed).to_string_lossy().into_owned()
+}
+
+/// One save_canvas pass; `variant` switches the background and band
+/// ink, then flattens the alpha channel so the variant color shows
+/// through every gap.
+fn save_variant(
+    pixels: &[u8],
+    (width, height): (u32, u32),
+    args: &Args,
+    output_path: &str,
+    variant: Option<Variant>,
+) -> error::Result<()> {
+    let frame = match args.frame.as_deref() {
+        Some(spec) => Some(parse_frame(spec)?),
+        None => None,
+    };
+    let border = args.margin + frame.map_or(0, |(px, _)| px);
+    let scale = cmp::max(1, args.cell_size / 200);
+    let band = 2 * text::line_height(scale);
+    let header = args.page_header.as_deref().map(|t| page_band_text(t, args, output_path));
+    let footer = args
+        .page_footer
+        .as_deref()
+        .map(|t| page_band_text(t, args, output_path))
+        .or_else(|| if args.stats_footer { preset::stats() } else { None });
+    let header_band = if header.is_some() { band } else { 0 };
+    let footer_band = if footer.is_some() { band } else { 0 };
+    // Zero-copy WebP: with no frame, bands, variant flatten, or
+    // quantization there is nothing to rewrite, so the encoder reads
+    // straight from the mapped canvas instead of a full-size copy —
+    // roughly half the peak memory on huge collages.
+    let ext = std::path::Path::new(output_path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase());
+    if border == 0
+        && header_band == 0
+        && footer_band == 0
+        && variant.is_none()
+        && args.quantize.is_none()
+        && args.target_size.is_none()
+        && args.flatten.is_none()
+        && args.alpha_threshold.is_none()
+        && !matches!(ext.as_deref(), Some("png") | Some("jpg") | Some("jpeg"))
+    {
+        return encode_webp_direct(pixels, (width, height), output_path);
+    }
+    let buffer = if border == 0 && header_band == 0 && footer_band == 0 {
+        ImageBuffer::<Rgba<u8>, Vec<u8>>::from_raw(width, height, pixels.to_vec())
+            .expect("buffer size matches canvas dimensions")
+    } else {
+        // Frame outermost, margin inside it, then the header and footer
+        // bands above and below the composition.
+        let (out_w, out_h) = (width + 2 * border, height + 2 * border + header_band + footer_band);
+        let mut out = vec![0u8; (out_w as u64 * out_h as u64 * 4) as usize];
+        match variant {
+            Some(variant) => {
+                for pixel in out.chunks_exact_mut(4) {
+                    pixel.copy_from_slice(&variant.background());
+                }
+            }
+            None => background::fill(&mut out, (out_w, out_h)),
+        }
+        if let Some((frame_px, color)) = frame.filter(|&(px, _)| px > 0) {
+            for y in 0..out_h {
+                for x in 0..out_w {
+                    let edge = x.min(y).min(out_w - 1 - x).min(out_h - 1 - y);
+                    if edge < frame_px {
+                        let index = ((y as u64 * out_w as u64 + x as u64) * 4) as usize;
+                        out[index..index + 4].copy_from_slice(&color);
+                    }
+                }
+            }
+        }
+        for y in 0..height {
+            let src = (y as u64 * width as u64 * 4) as usize;
+            let dst = ((y + border + header_band) as u64 * out_w as u64 + border as u64) as usize * 4;
+            out[dst..dst + width as usize * 4]
+                .copy_from_slice(&pixels[src..src + width as usize * 4]);
+        }
+        for (text, band_y) in [
+            (&header, border as i64),
+            (&footer, (border + header_band + height) as i64),
+        ] {
+            if let Some(text) = text {
+                let tw = text::text_width(text, scale);
+                text::draw_text(
+                    &mut out,
+                    (out_w, out_h),
+                    (
+                        (out_w as i64 - tw as i64) / 2,
+                        band_y + (text::line_height(scale) / 2) as i64,
+                    ),
+                    scale,
+                    variant.map_or([32, 32, 32, 255], |v| v.ink()),
+                    text,
+                );
+            }
+        }
+        ImageBuffer::from_raw(out_w, out_h, out).expect("buffer size matches canvas dimensions")
+    };
+    let mut buffer = buffer;
+    if let Some(variant) = variant {
+        // Flatten: the thumbnail layer was composited over transparency
+        // once; each variant only re-blends it onto its own background.
+        let bg = variant.background();
+        for pixel in buffer.pixels_mut() {
+            let alpha = pixel[3] as u32;
+            for c in 0..3 {
+                pixel[c] = ((pixel[c] as u32 * alpha + bg[c] as u32 * (255 - alpha)) / 255) as u8;
+            }
+            pixel[3] = 255;
+        }
+    }
+    write_output(buffer, output_path, args)
+}
+
+/// Feeds the WebP encoder directly from a borrowed canvas (the memmap
+/// in practice), skipping the owned ImageBuffer the general path needs.
+/// This build's encoder is VP8L lossless either way, so the output
+/// matches the buffered path byte for byte.
+fn encode_webp_direct(
+    pixels: &[u8],
+    (width, height): (u32, u32),
+    output_path: &str,
+) -> error::Result<()> {
+    let file = fs::File::create(output_path).map_err(|e| Error::output(output_path, e))?;
+    let writer = std::io::BufWriter::new(file);
+    image::codecs::webp::WebPEncoder::new_lossless(writer)
+        .encode(pixels, width, height, image::ColorType::Rgba8)
+        .map_err(|e| Error::output(output_path, e))
+}
+
+/// Encodes a finished canvas into the container its extension implies
+/// (.png and .jpg/.jpeg are honoured; anything else stays WebP). With
+/// --lossless, WebP goes through the lossless encoder, PNG needs no
+/// change, and JPEG is refused outright. With --quantize the pixels
+/// collapse to the palette first, and PNG outputs switch to indexed
+/// PNG8.
+fn write_output(
+    mut buffer: ImageBuffer<Rgba<u8>, Vec<u8>>,
+    output_path: &str,
+    args: &Args,
+) -> error::Result<()> {
+    let lossless = args.lossless;
+    let ext = std::path::Path::new(output_path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase());
+    if args.target_size.is_some() && !matches!(ext.as_deref(), Some("jpg") | Some("jpeg")) {
+        return Err(Error::Usage(
+            "--target-size needs a .jpg output; this build's WebP encoder is \
+             lossless and PNG has no quality knob"
+                .to_string(),
+        ));
+    }
+    // --alpha-threshold binarizes the alpha channel so soft mask and
+    // anti-aliasing edges don't flatten into halos; --flatten then
+    // composites everything over one colour, which is what alpha-less
+    // outputs like JPEG show instead of raw (often black) RGB.
+    if let Some(threshold) = args.alpha_threshold {
+        for pixel in buffer.pixels_mut() {
+            pixel[3] = if pixel[3] < threshold { 0 } else { 255 };
+        }
+    }
+    if let Some(spec) = &args.flatten {
+        let color = background::parse_color(spec)
+            .ok_or_else(|| Error::Usage(format!("invalid --flatten color {:?}", spec)))?;
+        for pixel in buffer.pixels_mut() {
+            let alpha = pixel[3] as u32;
+            for (channel, &under) in pixel.0.iter_mut().zip(&color[..3]) {
+                *channel = ((*channel as u32 * alpha + under as u32 * (255 - alpha)) / 255) as u8;
+            }
+            pixel[3] = 255;
+        }
+    }
+    let quantized = args.quantize.map(|colors| {
+        let (width, height) = buffer.dimensions();
+        quantize::apply(&mut buffer, (width, height), colors as usize, args.dither)
+    });
+    if let (Some("png"), Some((palette, indices))) = (ext.as_deref(), &quantized) {
+        let (width, height) = buffer.dimensions();
+        return quantize::write_png8(output_path, (width, height), palette, indices);
+    }
+    match ext.as_deref() {
+        Some("png") => buffer
+            .save_with_format(output_path, image::ImageFormat::Png)
+            .map_err(|e| Error::output(output_path, e)),
+        Some("jpg") | Some("jpeg") => {
+            if lossless {
+                return Err(Error::Usage(
+                    "--lossless cannot target JPEG; use a .png or .webp output".to_string(),
+                ));
+            }
+            // The JPEG encoder takes no alpha; flatten to RGB first.
+            let rgb = image::DynamicImage::ImageRgba8(buffer).to_rgb8();
+            if let Some(spec) = &args.target_size {
+                let budget = parse_byte_size(spec)?;
+                return encode_jpeg_under(&rgb, budget, output_path);
+            }
+            rgb.save_with_format(output_path, image::ImageFormat::Jpeg)
+                .map_err(|e| Error::output(output_path, e))
+        }
+        _ if lossless => {
+            let file = fs::File::create(output_path).map_err(|e| Error::output(output_path, e))?;
+            image::codecs::webp::WebPEncoder::new_lossless(file)
+                .encode(
+                    buffer.as_raw(),
+                    buffer.width(),
+                    buffer.height(),
+                    image::ColorType::Rgba8,
+                )
+                .map_err(|e| Error::output(output_path, e))
+        }
+        _ => buffer
+            .save_with_format(output_path, image::ImageFormat::WebP)
+            .map_err(|e| Error::output(output_path, e)),
+    }
+}
+
+/// Parses a --target-size byte budget: a plain count or a KB/MB/GB
+/// suffix (1024-based), e.g. `8MB`, `500KB`, `2000000`.
+fn parse_byte_size(spec: &str) -> error::Result<u64> {
+    let bad = || {
+        Error::Usage(format!(
+            "invalid --target-size {:?}; expected e.g. 8MB, 500KB or a byte count",
+            spec
+        ))
+    };
+    let lower = spec.trim().to_lowercase();
+    let (digits, unit) = match lower.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(at) => lower.split_at(at),
+        None => (lower.as_str(), ""),
+    };
+    let value: f64 = digits.parse().map_err(|_| bad())?;
+    let scale = match unit.trim() {
+        "" | "b" => 1u64,
+        "k" | "kb" => 1 << 10,
+        "m" | "mb" => 1 << 20,
+        "g" | "gb" => 1 << 30,
+        _ => return Err(bad()),
+    };
+    let bytes = (value * scale as f64) as u64;
+    if bytes == 0 {
+        return Err(bad());
+    }
+    Ok(bytes)
+}
+
+/// Parses a --time-budget duration: a number with an s/m/h suffix
+/// (seconds without one), e.g. `90s`, `8m`, `0.5h`.
+fn parse_duration(spec: &str) -> error::Result<std::time::Duration> {
+    let bad = || {
+        Error::Usage(format!(
+            "invalid --time-budget {:?}; expected e.g. 90s, 8m or 1h",
+            spec
+        ))
+    };
+    let lower = spec.trim().to_lowercase();
+    let (digits, unit) = match lower.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(at) => lower.split_at(at),
+        None => (lower.as_str(), ""),
+    };
+    let value: f64 = digits.parse().map_err(|_| bad())?;
+    let scale = match unit.trim() {
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => return Err(bad()),
+    };
+    let seconds = value * scale;
+    if !seconds.is_finite() || seconds <= 0.0 {
+        return Err(bad());
+    }
+    Ok(std::time::Duration::from_secs_f64(seconds))
+}
+
+/// Encodes the canvas as JPEG under the --target-size budget by binary
+/// searching the quality: encodes to memory, keeps the highest quality
+/// that fits, and writes that one out. If even quality 1 is over budget
+/// it is written anyway with a warning — shrinking further needs a
+/// smaller canvas, not a lower quality.
+fn encode_jpeg_under(
+    rgb: &image::RgbImage,
+    budget: u64,
+    output_path: &str,
+) -> error::Result<()> {
+    let encode = |quality: u8| -> error::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, quality)
+            .encode(rgb.as_raw(), rgb.width(), rgb.height(), image::ColorType::Rgb8)
+            .map_err(|e| Error::output(output_path, e))?;
+        Ok(bytes)
+    };
+    let (mut lo, mut hi) = (1u8, 100u8);
+    let mut best: Option<(u8, Vec<u8>)> = None;
+    while lo <= hi {
+        let quality = lo + (hi - lo) / 2;
+        let bytes = encode(quality)?;
+        if bytes.len() as u64 <= budget {
+            best = Some((quality, bytes));
+            lo = quality + 1;
+        } else {
+            if quality == 1 {
+                break;
+            }
+            hi = quality - 1;
+        }
+    }
+    let (quality, bytes) = match best {
+        Some(best) => best,
+        None => {
+            let bytes = encode(1)?;
+            tracing::warn!(
+                "Even quality 1 encodes to {} bytes, over the {} byte --target-size; \
+                 shrink the canvas with --cell-size or --max-images",
+                bytes.len(),
+                budget
+            );
+            (1, bytes)
+        }
+    };
+    fs::write(output_path, &bytes).map_err(|e| Error::output(output_path, e))?;
+    tracing::info!(
+        "Encoded at JPEG quality {} ({} bytes, budget {})",
+        quality,
+        bytes.len(),
+        budget
+    );
+    Ok(())
+}
+
+/// Writes one cell's thumbnail (--dump-thumbs) as DIR/INDEX.webp: the
+/// same fit-resize `paste_image` performs, saved standalone.
+fn dump_thumb(
+    dir: &std::path::Path,
+    number: usize,
+    img: &image::DynamicImage,
+    (cell_w, cell_h): (u32, u32),
+) -> error::Result<()> {
+    let (orig_w, orig_h) = img.dimensions();
+    let scale_factor = (cell_w as f32 / orig_w as f32).min(cell_h as f32 / orig_h as f32);
+    let new_w = (orig_w as f32 * scale_factor).round() as u32;
+    let new_h = (orig_h as f32 * scale_factor).round() as u32;
+    let path = dir.join(format!("{}.webp", number));
+    img.resize(new_w, new_h, FilterType::Lanczos3)
+        .to_rgba8()
+        .save_with_format(&path, image::ImageFormat::WebP)
+        .map_err(|e| Error::output(&path.to_string_lossy(), e))
+}
+
+/// Cell-level paste effects (--cell-mask, --cell-shape, --vignette),
+/// resolved once from the flags before rendering starts so every layout
+/// picks them up through `paste_image`.
+#[derive(Default)]
+struct PasteEffects {
+    /// Grayscale alpha mask, scaled to the cell when sampling.
+    mask: Option<image::GrayImage>,
+    /// Circle crop, with the ring width in pixels (0 for no ring).
+    circle: Option<u32>,
+    /// Edge-darkening strength, 0 (off) to 1.
+    vignette: f64,
+    /// How the letterbox bars around a fitted image are filled.
+    fill: Option<CellFill>,
+}
+
+static EFFECTS: std::sync::OnceLock<PasteEffects> = std::sync::OnceLock::new();
+
+/// The active paste effects; defaults (all off) if none were configured.
+fn effects() -> &'static PasteEffects {
+    EFFECTS.get_or_init(PasteEffects::default)
+}
+
+/// Fit-resizes `img` into the given pixel rectangle, centered, and copies
+/// it into the canvas. With --cell-mask set, the mask (scaled to the
+/// cell) modulates each pixel's alpha and the result is blended rather
+/// than copied.
+fn paste_image(
+    buf: &mut [u8],
+    (canvas_w, canvas_h): (u32, u32),
+    (cell_x, cell_y, cell_w, cell_h): (u32, u32, u32, u32),
+    img: &image::DynamicImage,
+) {
+    let (orig_w, orig_h) = img.dimensions();
+    // Compute scale factor so the image fits inside its cell rectangle.
+    let scale_factor = (cell_w as f32 / orig_w as f32).min(cell_h as f32 / orig_h as f32);
+    let new_w = (orig_w as f32 * scale_factor).round() as u32;
+    let new_h = (orig_h as f32 * scale_factor).round() as u32;
+    let resized = img.resize(new_w, new_h, FilterType::Lanczos3).to_rgba8();
+    // `resize` preserves the aspect ratio with its own rounding and can
+    // come back a pixel short of the requested box; every loop below
+    // must follow the pixels that actually exist.
+    let (new_w, new_h) = resized.dimensions();
+
+    // Center the resized image within its cell.
+    let offset_x = cell_x + (cell_w - new_w) / 2;
+    let offset_y = cell_y + (cell_h - new_h) / 2;
+
+    // Copy pixels from the resized image into the correct region of the canvas.
+    let fx = effects();
+    // --cell-fill paints the letterbox bars from the image itself before
+    // the paste, so the bars read as part of the photo, not background.
+    if let Some(fill) = fx.fill {
+        if new_w < cell_w || new_h < cell_h {
+            let average = match fill {
+                CellFill::Average => {
+                    let mut sums = [0u64; 3];
+                    for pixel in resized.pixels() {
+                        for (sum, &channel) in sums.iter_mut().zip(&pixel.0[..3]) {
+                            *sum += channel as u64;
+                        }
+                    }
+                    let count = (new_w as u64 * new_h as u64).max(1);
+                    Some([
+                        (sums[0] / count) as u8,
+                        (sums[1] / count) as u8,
+                        (sums[2] / count) as u8,
+                        255,
+                    ])
+                }
+                CellFill::EdgeExtend => None,
+            };
+            for y in cell_y..(cell_y + cell_h).min(canvas_h) {
+                for x in cell_x..(cell_x + cell_w).min(canvas_w) {
+                    let inside = (offset_x..offset_x + new_w).contains(&x)
+                        && (offset_y..offset_y + new_h).contains(&y);
+                    if inside {
+                        continue;
+                    }
+                    let index = ((y * canvas_w + x) * 4) as usize;
+                    let color = match average {
+                        Some(color) => color,
+                        None => {
+                            // Clamp to the nearest image pixel, extending
+                            // the edges outwards.
+                            let sx = x.saturating_sub(offset_x).min(new_w - 1);
+                            let sy = y.saturating_sub(offset_y).min(new_h - 1);
+                            resized.get_pixel(sx, sy).0
+                        }
+                    };
+                    buf[index..index + 4].copy_from_slice(&color);
+                }
+            }
+        }
+    }
+    let mask = fx.mask.as_ref();
+    let circle = fx.circle;
+    let radius = cell_w.min(cell_h) as f64 / 2.0;
+    let center_x = cell_x as f64 + cell_w as f64 / 2.0;
+    let center_y = cell_y as f64 + cell_h as f64 / 2.0;
+    for y in 0..new_h {
+        for x in 0..new_w {
+            let pixel = resized.get_pixel(x, y);
+            let target_x = offset_x + x;
+            let target_y = offset_y + y;
+            if target_x >= canvas_w || target_y >= canvas_h {
+                continue;
+            }
+            let index = ((target_y * canvas_w + target_x) * 4) as usize;
+            if mask.is_none() && circle.is_none() && fx.vignette == 0.0 {
+                buf[index] = pixel[0];
+                buf[index + 1] = pixel[1];
+                buf[index + 2] = pixel[2];
+                buf[index + 3] = pixel[3];
+                continue;
+            }
+            // The mask spans the whole cell; sample it at this pixel's
+            // cell position. The circle fades over its last pixel. The
+            // vignette darkens quadratically towards the tile corners.
+            let mut factor = 1.0f64;
+            let mut shade = 1.0f64;
+            if fx.vignette > 0.0 {
+                let nx = (x as f64 + 0.5) / new_w as f64 * 2.0 - 1.0;
+                let ny = (y as f64 + 0.5) / new_h as f64 * 2.0 - 1.0;
+                shade = 1.0 - fx.vignette * ((nx * nx + ny * ny) / 2.0).min(1.0);
+            }
+            if let Some(mask) = mask {
+                let mx = ((target_x - cell_x) as u64 * mask.width() as u64
+                    / cell_w.max(1) as u64)
+                    .min(mask.width() as u64 - 1) as u32;
+                let my = ((target_y - cell_y) as u64 * mask.height() as u64
+                    / cell_h.max(1) as u64)
+                    .min(mask.height() as u64 - 1) as u32;
+                factor *= mask.get_pixel(mx, my)[0] as f64 / 255.0;
+            }
+            if circle.is_some() {
+                let dist = ((target_x as f64 + 0.5 - center_x).powi(2)
+                    + (target_y as f64 + 0.5 - center_y).powi(2))
+                .sqrt();
+                factor *= (radius - dist + 0.5).clamp(0.0, 1.0);
+            }
+            let alpha = pixel[3] as f64 / 255.0 * factor;
+            for (dst, &src) in buf[index..index + 3].iter_mut().zip(&pixel.0[..3]) {
+                *dst = (src as f64 * shade * alpha + *dst as f64 * (1.0 - alpha)).round() as u8;
+            }
+            let dst_alpha = buf[index + 3] as f64 / 255.0;
+            buf[index + 3] = ((alpha + dst_alpha * (1.0 - alpha)) * 255.0).round() as u8;
+        }
+    }
+
+    // Optional ring border hugging the inside of the circle, anti-aliased
+    // at both edges.
+    if let Some(ring) = circle.filter(|&ring| ring > 0) {
+        let ring = ring as f64;
+        for target_y in cell_y..(cell_y + cell_h).min(canvas_h) {
+            for target_x in cell_x..(cell_x + cell_w).min(canvas_w) {
+                let dist = ((target_x as f64 + 0.5 - center_x).powi(2)
+                    + (target_y as f64 + 0.5 - center_y).powi(2))
+                .sqrt();
+                let inner = (dist - (radius - ring) + 0.5).clamp(0.0, 1.0);
+                let outer = (radius - dist + 0.5).clamp(0.0, 1.0);
+                let alpha = inner.min(outer);
+                if alpha <= 0.0 {
+                    continue;
+                }
+                let index = ((target_y * canvas_w + target_x) * 4) as usize;
+                for dst in buf[index..index + 3].iter_mut() {
+                    *dst = (32.0 * alpha + *dst as f64 * (1.0 - alpha)).round() as u8;
+                }
+                let dst_alpha = buf[index + 3] as f64 / 255.0;
+                buf[index + 3] = ((alpha + dst_alpha * (1.0 - alpha)) * 255.0).round() as u8;
+            }
+        }
+    }
+}
+
+/// Creates the collage using a disk‑backed memory map to reduce in‑memory usage.
+/// Free bytes on the filesystem holding `path`, or None where the
+/// answer isn't available (non-unix, or statvfs failing).
+#[cfg(all(unix, not(target_arch = "wasm32")))]
+fn free_space(path: &std::path::Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let cstr = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(cstr.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(all(not(unix), not(target_arch = "wasm32")))]
+fn free_space(_path: &std::path::Path) -> Option<u64> {
+    None
+}
+
+/// Fails early if the temp dir cannot hold the raw canvas or the output
+/// location its (roughly estimated) encoded size, rather than dying on
+/// ENOSPC halfway through the composite.
+#[cfg(not(target_arch = "wasm32"))]
+/// Parses --mirror (`2x2` or `4x4`) into the grid factor.
+fn parse_mirror(spec: &str) -> error::Result<u32> {
+    match spec {
+        "2x2" => Ok(2),
+        "4x4" => Ok(4),
+        _ => Err(Error::Usage(format!(
+            "invalid --mirror {:?}; expected 2x2 or 4x4",
+            spec
+        ))),
+    }
+}
+
+/// Applies --mirror after the render: the finished collage is blitted
+/// into an NxN grid of alternately flipped copies, so every seam lines
+/// up and the whole canvas is symmetric.
+fn apply_mirror(args: &Args) -> error::Result<()> {
+    let Some(spec) = args.mirror.as_deref() else {
+        return Ok(());
+    };
+    let factor = parse_mirror(spec)?;
+    let output = args
+        .output_file
+        .clone()
+        .or_else(|| args.input_dir.clone())
+        .ok_or_else(|| Error::Usage("missing output file argument".to_string()))?;
+    if output.contains("{folder}") {
+        return Err(Error::Usage(
+            "--mirror cannot follow --per-folder outputs".to_string(),
+        ));
+    }
+    let base = image::open(&output)
+        .map_err(|e| Error::output(&output, e))?
+        .to_rgba8();
+    let (width, height) = base.dimensions();
+    if let Some(limit) = format_side_limit(&output) {
+        if width * factor > limit || height * factor > limit {
+            return Err(Error::Usage(format!(
+                "the mirrored {}x{} px canvas exceeds the container's {} px side limit; \
+                 shrink the collage or use a .png output",
+                width * factor,
+                height * factor,
+                limit
+            )));
+        }
+    }
+    let flipped_h = image::imageops::flip_horizontal(&base);
+    let flipped_v = image::imageops::flip_vertical(&base);
+    let flipped_both = image::imageops::flip_vertical(&flipped_h);
+    let mut out = image::RgbaImage::new(width * factor, height * factor);
+    for j in 0..factor {
+        for i in 0..factor {
+            let tile = match (i % 2, j % 2) {
+                (0, 0) => &base,
+                (1, 0) => &flipped_h,
+                (0, 1) => &flipped_v,
+                _ => &flipped_both,
+            };
+            image::imageops::replace(&mut out, tile, (i * width) as i64, (j * height) as i64);
+        }
+    }
+    write_output(out, &output, args)?;
+    tracing::info!("Mirrored into a {0}x{0} kaleidoscope at '{1}'", factor, output);
+    Ok(())
+}
+
+/// The per-side pixel limit the output container implies: 16383 for
+/// WebP (the default container), 65535 for JPEG, none worth enforcing
+/// for PNG.
+fn format_side_limit(output_path: &str) -> Option<u32> {
+    let ext = std::path::Path::new(output_path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase());
+    match ext.as_deref() {
+        Some("png") => None,
+        Some("jpg") | Some("jpeg") => Some(65_535),
+        _ => Some(16_383),
+    }
+}
+
+fn preflight_space(canvas_bytes: u64, output_path: &str) -> error::Result<()> {
+    let temp = std::env::temp_dir();
+    if let Some(free) = free_space(&temp) {
+        if free < canvas_bytes {
+            return Err(Error::output(
+                output_path,
+                format!(
+                    "the canvas needs {} MiB in {:?} but only {} MiB are free; \
+                     split the run with --paginate or --max-images, or stream tiles with --tiles",
+                    canvas_bytes >> 20,
+                    temp,
+                    free >> 20
+                ),
+            ));
+        }
+    }
+    // Encoded size is content-dependent; an eighth of the raw canvas is
+    // a generous ceiling for WebP and keeps the check from crying wolf.
+    let estimate = canvas_bytes / 8;
+    let out_dir = std::path::Path::new(output_path)
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| std::path::PathBuf::from("."));
+    if let Some(free) = free_space(&out_dir) {
+        if free < estimate {
+            return Err(Error::output(
+                output_path,
+                format!(
+                    "the encoded collage may need {} MiB in {:?} but only {} MiB are free",
+                    estimate >> 20,
+                    out_dir,
+                    free >> 20
+                ),
+            ));
+        }
+    }
+    // The canvas itself is a file-backed mmap the kernel can evict, but
+    // encoding copies it onto the heap, and that copy counts in full
+    // against a container's cgroup limit.
+    if let Some(limit) = resources::cgroup_memory_limit() {
+        if canvas_bytes > limit {
+            tracing::warn!(
+                "The {} MiB canvas exceeds the {} MiB cgroup memory limit; \
+                 the run may be OOM-killed while encoding — split it with \
+                 --paginate or --max-images, or stream tiles with --tiles",
+                canvas_bytes >> 20,
+                limit >> 20
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Resolves --threads: an explicit count as-is, 0 as the core count
+/// capped at 8 so the per-thread decode and resize scratch (a couple
+/// hundred MB in the worst case) stays within reach of small machines.
+#[cfg(not(target_arch = "wasm32"))]
+fn effective_threads(args: &Args) -> usize {
+    if args.threads > 0 {
+        return args.threads;
+    }
+    let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let mut threads = cores.min(8);
+    // Inside a container the cgroup limit, not the host's core count,
+    // is what the OOM killer measures against; budget ~512 MiB of
+    // scratch per worker and shed threads to fit.
+    if let Some(limit) = resources::cgroup_memory_limit() {
+        let budget = cmp::max(1, (limit >> 29) as usize);
+        if budget < threads {
+            tracing::info!(
+                "Capping decode threads at {} for the {} MiB cgroup memory limit",
+                budget,
+                limit >> 20
+            );
+            threads = budget;
+        }
+    }
+    threads
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn create_collage(
+    entries: &[ManifestEntry],
+    args: &Args,
+    output_path: &str,
+    run: &mut RunSummary,
+    overflow: usize,
+) -> error::Result<()> {
+    let cell_size = args.cell_size;
+    if entries.is_empty() {
+        return Err(Error::NoImages);
+    }
+    // Calculate grid dimensions (nearly square unless --aspect says
+    // otherwise), honoring cell spans. The "+N more" tile, if any,
+    // reserves one extra 1x1 cell at the end.
+    let aspect = match args.aspect.as_deref() {
+        Some(spec) => parse_aspect(spec)?,
+        None => 1.0,
+    };
+    let slots = gap_slots(entries.len(), args)?;
+    let tile = overflow > 0 && args.overflow_tile;
+    let placed_owned: Vec<ManifestEntry>;
+    let placed = if tile || slots.len() > entries.len() {
+        let mut with_extras = Vec::with_capacity(slots.len() + 1);
+        let mut real = entries.iter();
+        for &gap in &slots {
+            with_extras.push(if gap {
+                ManifestEntry::from_path(PathBuf::new())
+            } else {
+                real.next().cloned().expect("one slot per entry")
+            });
+        }
+        if tile {
+            with_extras.push(ManifestEntry::from_path(PathBuf::new()));
+        }
+        placed_owned = with_extras;
+        &placed_owned[..]
+    } else {
+        entries
+    };
+    let pins = pin::parse(&args.pin)?;
+    let reserved: Vec<CellRect> = pins.iter().map(|p| p.rect).collect();
+    let (rects, ncols, nrows) =
+        place_entries(placed, args.fill_order, aspect, args.columns, &reserved);
+    // Rectangles of the real entries, gap slots skipped, in entry order.
+    let entry_rects: Vec<&CellRect> = slots
+        .iter()
+        .zip(rects.iter())
+        .filter(|(gap, _)| !**gap)
+        .map(|(_, rect)| rect)
+        .collect();
+    if let Some(export_path) = &args.export_layout {
+        let cells = entries
+            .iter()
+            .zip(entry_rects.iter())
+            .map(|(entry, rect)| template::GeometryCell {
+                path: entry.path.to_string_lossy().into_owned(),
+                col: rect.col,
+                row: rect.row,
+                span_w: rect.span_w,
+                span_h: rect.span_h,
+            })
+            .collect();
+        template::export_geometry(export_path, (ncols, nrows), cells)?;
+    }
+    // Container pixel limits (--too-big), enforced before any
+    // compositing work rather than failing at encode time.
+    let mut cell_size = cell_size;
+    let mut switched_output = None;
+    if let Some(limit) = format_side_limit(output_path) {
+        if ncols * cell_size > limit || nrows * cell_size > limit {
+            match args.too_big {
+                TooBig::Downscale => {
+                    cell_size = cmp::max(1, cmp::min(limit / ncols, limit / nrows));
+                    tracing::warn!(
+                        "A {}x{} grid at {} px cells would exceed the container's {} px side limit; cells downscaled to {} px",
+                        ncols, nrows, args.cell_size, limit, cell_size
+                    );
+                }
+                TooBig::SwitchFormat => {
+                    let switched = std::path::Path::new(output_path)
+                        .with_extension("png")
+                        .to_string_lossy()
+                        .into_owned();
+                    tracing::warn!(
+                        "The {}x{} px canvas exceeds the container's {} px side limit; output switched to '{}'",
+                        ncols * cell_size, nrows * cell_size, limit, switched
+                    );
+                    switched_output = Some(switched);
+                }
+                // Paginate should already have split the run into
+                // fitting pages; a forced column count can still bust a
+                // page, and that reads as an error.
+                TooBig::Fail | TooBig::Paginate => {
+                    return Err(Error::Usage(format!(
+                        "the {}x{} px canvas exceeds the output container's {} px side limit; \
+                         use --too-big downscale|paginate|switch-format, or a .png output",
+                        ncols * cell_size, nrows * cell_size, limit
+                    )));
+                }
+            }
+        }
+    }
+    let output_path = switched_output.as_deref().unwrap_or(output_path);
+    let collage_width = ncols * cell_size;
+    let collage_height = nrows * cell_size;
+    tracing::debug!(
+        "layout: {} images in a {}x{} grid, canvas {}x{} px",
+        entries.len(), ncols, nrows, collage_width, collage_height
+    );
+    run.total_images = entries.len();
+    run.grid_cols = ncols;
+    run.grid_rows = nrows;
+    run.canvas_width = collage_width;
+    run.canvas_height = collage_height;
+    let num_pixels = (collage_width * collage_height) as usize;
+    let buffer_size = num_pixels * 4; // 4 channels per pixel (RGBA)
+    preflight_space(buffer_size as u64, output_path)?;
+
+    // Create the file backing our memmap: a tempfile normally, or a
+    // named .partial file (plus a journal of completed cells) under
+    // --resume so a crashed run can pick up where it stopped.
+    let mut done: std::collections::HashSet<usize> = std::collections::HashSet::new();
+    let mut journal = None;
+    let file = if args.resume {
+        let hash = format!("{:016x}", resume_hash(entries, args));
+        let partial_path = format!("{}.partial", output_path);
+        let journal_path = format!("{}.journal", output_path);
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&partial_path)?;
+        let restorable = file.metadata()?.len() == buffer_size as u64;
+        match fs::read_to_string(&journal_path) {
+            Ok(text) if restorable && text.lines().next() == Some(hash.as_str()) => {
+                done = text.lines().skip(1).filter_map(|line| line.trim().parse().ok()).collect();
+            }
+            Ok(_) => {
+                tracing::warn!("Journal {:?} is from a different run; starting over", journal_path);
+                fs::write(&journal_path, format!("{}\n", hash))?;
+            }
+            Err(_) => fs::write(&journal_path, format!("{}\n", hash))?,
+        }
+        if !done.is_empty() {
+            tracing::info!("Resuming: {} of {} cells already composited", done.len(), entries.len());
+        }
+        journal = Some(fs::OpenOptions::new().append(true).open(&journal_path)?);
+        file
+    } else {
+        tempfile()?
+    };
+    file.set_len(buffer_size as u64)?;
+    resources::add_temp(buffer_size as u64);
+
+    // Memory-map the file.
+    let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+
+    // Paint the background (--background; the default is the
+    // “transparent white” of R, G, B at 255 with alpha 0) — unless we
+    // are resuming onto an already-painted canvas.
+    if done.is_empty() {
+        background::fill(&mut mmap, (collage_width, collage_height));
+    }
+
+    // With --gpu, the canvas lives in a GPU texture from here until the
+    // readback after the loop; per-entry rotation and captions would
+    // draw on the stale CPU canvas, so they bail out instead.
+    #[cfg(feature = "gpu")]
+    let mut gpu = if args.gpu {
+        if entries.iter().any(|e| e.rotation.is_some() || e.caption.is_some()) {
+            return Err(Error::Usage(
+                "--gpu covers the plain grid paste only; the manifest sets rotation or captions".to_string(),
+            ));
+        }
+        match gpu::Compositor::new((collage_width, collage_height), &mmap) {
+            Ok(compositor) => Some(compositor),
+            Err(e) => {
+                tracing::warn!("GPU unavailable ({}); compositing on the CPU", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    #[cfg(feature = "gpu")]
+    let gpu_active = gpu.is_some();
+    #[cfg(not(feature = "gpu"))]
+    let gpu_active = false;
+
+    // Process each image and paste it into its cell in the collage.
+    progress::emit(progress::Event::Scanned(entries.len()));
+    let composite_start = std::time::Instant::now();
+    let mut map_areas = Vec::new();
+    // Spanned-aware row bands let --threads composite in parallel;
+    // rotated tiles paint outside their cells and cross band borders, so
+    // rotation falls back to the serial loop (as does --resume, via its
+    // conflict, and --dump-thumbs, which writes per index in loop order).
+    let threads = effective_threads(args);
+    let parallel = threads > 1
+        && !gpu_active
+        && journal.is_none()
+        && args.rotate_jitter.is_none()
+        && args.dump_thumbs.is_none()
+        && !entries.iter().any(|e| e.rotation.is_some());
+    let cell_border = match args.cell_border.as_deref() {
+        Some(spec) => Some(parse_cell_border(spec)?),
+        None => None,
+    };
+    if parallel {
+        cancel::check()?;
+        let outcome = parallel::composite(
+            &mut mmap,
+            (collage_width, collage_height),
+            cell_size,
+            entries,
+            &entry_rects,
+            args,
+            threads,
+        )?;
+        run.total_images += outcome.pasted;
+        for (path, e) in &outcome.skipped {
+            run.skip(path, e);
+        }
+        if args.image_map.is_some() || args.gallery.is_some() {
+            for (entry, rect) in entries.iter().zip(entry_rects.iter()) {
+                map_areas.push(MapArea {
+                    href: entry
+                        .url
+                        .clone()
+                        .unwrap_or_else(|| entry.path.to_string_lossy().into_owned()),
+                    alt: entry
+                        .caption
+                        .clone()
+                        .or_else(|| entry.path.file_stem().map(|s| s.to_string_lossy().into_owned()))
+                        .unwrap_or_default(),
+                    rect: (
+                        rect.col * cell_size,
+                        rect.row * cell_size,
+                        rect.span_w * cell_size,
+                        rect.span_h * cell_size,
+                    ),
+                });
+            }
+        }
+    } else {
+        for (index, (entry, rect)) in entries.iter().zip(entry_rects.iter().copied()).enumerate() {
+            cancel::check()?;
+            if done.contains(&index) {
+                continue;
+            }
+            let image_start = std::time::Instant::now();
+
+            // The target rectangle for this entry, in pixels.
+            let cell_w = rect.span_w * cell_size;
+            let cell_h = rect.span_h * cell_size;
+            let cell_x = rect.col * cell_size;
+            let cell_y = rect.row * cell_size;
+
+            // Attempt to open the image; failures follow the --on-error policy.
+            let img = match entry.load_image() {
+                Ok(im) => im,
+                Err(e) => {
+                    if args.strict || args.on_error == OnError::Abort {
+                        return Err(Error::Decode(entry.path.clone(), e));
+                    }
+                    tracing::error!("Error processing {:?}: {}", entry.path, e);
+                    run.skip(&entry.path, &e);
+                    if args.on_error == OnError::Placeholder {
+                        draw_placeholder(
+                            &mut mmap,
+                            (collage_width, collage_height),
+                            (cell_x, cell_y, cell_w, cell_h),
+                            cell_size,
+                            &entry.path,
+                        );
+                    }
+                    // Skipped cells keep their number so the count
+                    // stays aligned with the manifest.
+                    if args.number_cells {
+                        let base = NUMBER_BASE.load(Ordering::Relaxed);
+                        draw_cell_number(
+                            &mut mmap,
+                            (collage_width, collage_height),
+                            (cell_x, cell_y),
+                            cell_size,
+                            base + index + 1,
+                        );
+                    }
+                    continue;
+                }
+            };
+
+            if let Some(dir) = &args.dump_thumbs {
+                let base = NUMBER_BASE.load(Ordering::Relaxed);
+                dump_thumb(dir, base + index + 1, &img, (cell_w, cell_h))?;
+            }
+
+            // Explicit manifest rotation wins over the seeded jitter.
+            let angle = entry
+                .rotation
+                .or_else(|| args.rotate_jitter.map(|j| rotate::jitter_angle(args.seed, index, j)));
+            match angle {
+                Some(angle) if angle != 0.0 => rotate::paste_rotated(
+                    &mut mmap,
+                    (collage_width, collage_height),
+                    (cell_x, cell_y, cell_w, cell_h),
+                    angle,
+                    &img,
+                ),
+                // Rotation is ruled out up front when the GPU is active.
+                _ if gpu_active => {
+                    #[cfg(feature = "gpu")]
+                    gpu.as_mut().unwrap().paste((cell_x, cell_y, cell_w, cell_h), &img);
+                }
+                _ => paste_image(
+                    &mut mmap,
+                    (collage_width, collage_height),
+                    (cell_x, cell_y, cell_w, cell_h),
+                    &img,
+                ),
+            }
+
+            if let Some((px, color)) = cell_border {
+                let color = match color {
+                    CellBorderColor::Fixed(c) => c,
+                    CellBorderColor::Dominant => palette::dominant(&img),
+                    CellBorderColor::Folder => {
+                        palette::folder_color(&entry.path).unwrap_or([128, 128, 128, 255])
+                    }
+                };
+                draw_cell_border(
+                    &mut mmap,
+                    (collage_width, collage_height),
+                    (cell_x, cell_y, cell_w, cell_h),
+                    px,
+                    color,
+                );
+            }
+
+            if args.image_map.is_some() || args.gallery.is_some() {
+                map_areas.push(MapArea {
+                    href: entry
+                        .url
+                        .clone()
+                        .unwrap_or_else(|| entry.path.to_string_lossy().into_owned()),
+                    alt: entry
+                        .caption
+                        .clone()
+                        .or_else(|| entry.path.file_stem().map(|s| s.to_string_lossy().into_owned()))
+                        .unwrap_or_default(),
+                    rect: (cell_x, cell_y, cell_w, cell_h),
+                });
+            }
+
+            // Draw the caption (if any) centered at the bottom of the cell.
+            if let Some(caption) = &entry.caption {
+                draw_caption(
+                    &mut mmap,
+                    (collage_width, collage_height),
+                    (cell_x, cell_y, cell_w, cell_h),
+                    cell_size,
+                    args,
+                    caption,
+                );
+            }
+
+            if args.number_cells {
+                let base = NUMBER_BASE.load(Ordering::Relaxed);
+                draw_cell_number(
+                    &mut mmap,
+                    (collage_width, collage_height),
+                    (cell_x, cell_y),
+                    cell_size,
+                    base + index + 1,
+                );
+            }
+
+            // Record the finished cell; flush the canvas now and then so the
+            // journal never runs far ahead of the pixels.
+            if let Some(journal) = &mut journal {
+                use std::io::Write;
+                writeln!(journal, "{}", index)?;
+                if index.is_multiple_of(256) {
+                    mmap.flush()?;
+                }
+            }
+
+            progress::emit(progress::Event::ImageDone {
+                index,
+                total: entries.len(),
+                path: &entry.path,
+                seconds: image_start.elapsed().as_secs_f64(),
+            });
+            tracing::debug!(
+                "pasted {:?} at cell ({}, {}) in {:.1} ms",
+                entry.path, rect.col, rect.row,
+                image_start.elapsed().as_secs_f64() * 1000.0
+            );
+        }
+    }
+
+    // Bring the GPU canvas back before anything else touches the pixels.
+    #[cfg(feature = "gpu")]
+    if let Some(compositor) = gpu.take() {
+        compositor
+            .finish(&mut mmap)
+            .map_err(std::io::Error::other)?;
+    }
+
+    // Pinned artwork lands after the composite so nothing paints over it.
+    if !pins.is_empty() {
+        pin::paste_all(&mut mmap, (collage_width, collage_height), cell_size, &pins, args, run)?;
+    }
+
+    // The reserved tile sits in the first cell after the real images.
+    if tile {
+        let rect = &rects[slots.len()];
+        draw_overflow_tile(
+            &mut mmap,
+            (collage_width, collage_height),
+            (
+                rect.col * cell_size,
+                rect.row * cell_size,
+                rect.span_w * cell_size,
+                rect.span_h * cell_size,
+            ),
+            cell_size,
+            overflow,
+        );
+    }
+    mmap.flush()?;
+    run.phase_seconds
+        .insert("composite".to_string(), composite_start.elapsed().as_secs_f64());
+
+    // Tiled pyramid output reads regions straight from the memmap and
+    // never materializes the whole canvas.
+    if let Some(layout) = args.tiles {
+        return match layout {
+            TileLayout::Dzi => tiles::write_dzi(&mmap, collage_width, collage_height, output_path),
+            TileLayout::Iiif => tiles::write_iiif(&mmap, collage_width, collage_height, output_path),
+            TileLayout::Tiff => {
+                bigtiff::write_pyramidal_bigtiff(&mmap, collage_width, collage_height, output_path)
+                    .map_err(|e| Error::output(output_path, e))
+            }
+        };
+    }
+
+    // Build-up animation replays the composite straight from the memmap:
+    // one delta frame per cell, in paste order.
+    if args.animate == Some(Animate::BuildUp) {
+        let cells: Vec<(u32, u32, u32, u32)> = entry_rects
+            .iter()
+            .map(|rect| {
+                (
+                    rect.col * cell_size,
+                    rect.row * cell_size,
+                    rect.span_w * cell_size,
+                    rect.span_h * cell_size,
+                )
+            })
+            .collect();
+        return animate::write_build_up(&mmap, (collage_width, collage_height), &cells, output_path);
+    }
+
+    // At this point, the memmap contains the full collage; encode it
+    // (applying any outer margin and frame) in WebP format.
+    progress::emit(progress::Event::Encoding);
+    let encode_start = std::time::Instant::now();
+    save_canvas(&mmap, (collage_width, collage_height), args, output_path)?;
+    if args.resume {
+        let _ = fs::remove_file(format!("{}.partial", output_path));
+        let _ = fs::remove_file(format!("{}.journal", output_path));
+    }
+    if let Some(map_path) = &args.image_map {
+        write_image_map(map_path, &map_areas)?;
+        tracing::info!("Image map saved to {:?}", map_path);
+    }
+    if let Some(gallery_path) = &args.gallery {
+        write_gallery(gallery_path, output_path, &map_areas)?;
+        tracing::info!("Gallery page saved to {:?}", gallery_path);
+    }
+    if let Some(target) = &args.index {
+        write_cell_index(target, entries, &entry_rects, cell_size)?;
+        if target != "-" {
+            tracing::info!("Cell index saved to {:?}", target);
+        }
+    }
+    run.phase_seconds
+        .insert("encode".to_string(), encode_start.elapsed().as_secs_f64());
+    run.output_file = output_path.to_string();
+    run.output_bytes = fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!("Collage saved to '{}'", output_path);
+
+    // Extra scaled renditions from the same composited canvas.
+    if !args.sizes.is_empty() {
+        let collage_buffer =
+            ImageBuffer::<Rgba<u8>, Vec<u8>>::from_raw(collage_width, collage_height, mmap.to_vec())
+                .expect("buffer size matches canvas dimensions");
+        let mut srcset = Vec::new();
+        for &width in &args.sizes {
+            if width == 0 || width > collage_width {
+                tracing::error!("Skipping invalid rendition width {}", width);
+                continue;
+            }
+            let height = cmp::max(
+                1,
+                (collage_height as u64 * width as u64 / collage_width as u64) as u32,
+            );
+            let scaled = image::imageops::resize(&collage_buffer, width, height, FilterType::Lanczos3);
+            let rendition_path = with_width_suffix(output_path, width);
+            write_output(scaled, &rendition_path, args)?;
+            tracing::info!("Rendition saved to '{}'", rendition_path);
+            srcset.push(format!("{} {}w", rendition_path, width));
+        }
+        if let Some(srcset_path) = &args.srcset_file {
+            let snippet = format!(
+                "<img src=\"{}\" srcset=\"{}\" alt=\"collage\">\n",
+                output_path,
+                srcset.join(", ")
+            );
+            fs::write(srcset_path, snippet)
+                .map_err(|e| Error::output(&srcset_path.display().to_string(), e))?;
+            tracing::info!("Srcset snippet saved to {:?}", srcset_path);
+        }
+    }
+    Ok(())
+}
+
+/// Variance of the 4-neighbour Laplacian over a downscaled grayscale copy:
+/// the usual cheap blur metric. Sharp images score in the hundreds, blurry
+/// ones near zero.
+fn sharpness(img: &image::DynamicImage) -> f64 {
+    let gray = img.thumbnail(256, 256).to_luma8();
+    let (w, h) = gray.dimensions();
+    if w < 3 || h < 3 {
+        return f64::MAX;
+    }
+    let mut sum = 0.0f64;
+    let mut sum_sq = 0.0f64;
+    let n = ((w - 2) * (h - 2)) as f64;
+    for y in 1..h - 1 {
+        for x in 1..w - 1 {
+            let center = gray.get_pixel(x, y)[0] as f64;
+            let lap = 4.0 * center
+                - gray.get_pixel(x - 1, y)[0] as f64
+                - gray.get_pixel(x + 1, y)[0] as f64
+                - gray.get_pixel(x, y - 1)[0] as f64
+                - gray.get_pixel(x, y + 1)[0] as f64;
+            sum += lap;
+            sum_sq += lap * lap;
+        }
+    }
+    let mean = sum / n;
+    sum_sq / n - mean * mean
+}
+
+/// Splits a total sample target across folders in proportion to their
+/// sizes, by largest remainder, giving every non-empty folder at least one
+/// image (capped at the folder's size).
+fn balance_allocation(sizes: &[usize], weights: &[f64], target: usize) -> Vec<usize> {
+    let weighted: Vec<f64> = sizes
+        .iter()
+        .zip(weights)
+        .map(|(&size, &weight)| size as f64 * weight)
+        .collect();
+    let total: f64 = weighted.iter().sum();
+    if total <= 0.0 || target == 0 {
+        return vec![0; sizes.len()];
+    }
+    let exact: Vec<f64> = weighted.iter().map(|w| target as f64 * w / total).collect();
+    let mut shares: Vec<usize> = sizes
+        .iter()
+        .zip(&exact)
+        .map(|(&size, &share)| {
+            if size == 0 || share <= 0.0 {
+                0
+            } else {
+                (share.floor() as usize).clamp(1, size)
+            }
+        })
+        .collect();
+    // Largest-remainder top-up until the target (or every weighted
+    // folder) is full.
+    let mut remainders: Vec<(f64, usize)> = exact
+        .iter()
+        .enumerate()
+        .map(|(i, share)| (share.fract(), i))
+        .collect();
+    remainders.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(cmp::Ordering::Equal));
+    let mut assigned: usize = shares.iter().sum();
+    while assigned < target {
+        let mut grew = false;
+        for &(_, i) in &remainders {
+            if assigned == target {
+                break;
+            }
+            if shares[i] < sizes[i] && weighted[i] > 0.0 {
+                shares[i] += 1;
+                assigned += 1;
+                grew = true;
+            }
+        }
+        if !grew {
+            break;
+        }
+    }
+    shares
+}
+
+/// Parses --folder-weight into (pattern, factor) pairs.
+fn parse_folder_weights(spec: &str) -> error::Result<Vec<(String, f64)>> {
+    let bad = |part: &str| {
+        Error::Usage(format!(
+            "invalid --folder-weight part {:?}; expected name=factor, e.g. vacation=3",
+            part
+        ))
+    };
+    spec.split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let (name, factor) = part.split_once('=').ok_or_else(|| bad(part))?;
+            let factor: f64 = factor.trim().parse().map_err(|_| bad(part))?;
+            if name.trim().is_empty() || !factor.is_finite() || factor < 0.0 {
+                return Err(bad(part));
+            }
+            Ok((name.trim().to_string(), factor))
+        })
+        .collect()
+}
+
+/// The weight --folder-weight assigns this folder (first matching
+/// pattern wins; unlisted folders weigh 1).
+fn folder_weight(folder: &std::path::Path, weights: &[(String, f64)]) -> f64 {
+    let name = folder.file_name().unwrap_or_default().to_string_lossy();
+    weights
+        .iter()
+        .find(|(pattern, _)| glob_match(pattern, &name))
+        .map_or(1.0, |&(_, factor)| factor)
+}
+
+/// Matches a shell-style pattern (`*` and `?` only) against a string.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pat: Vec<char> = pattern.chars().collect();
+    let txt: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < txt.len() {
+        if p < pat.len() && (pat[p] == '?' || pat[p] == txt[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pat.len() && pat[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((sp, st)) = star {
+            // Backtrack: let the last `*` swallow one more character.
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    while p < pat.len() && pat[p] == '*' {
+        p += 1;
+    }
+    p == pat.len()
+}
+
+/// Reorders entries according to an --order file: entries matching a line
+/// (against their full path, a trailing path fragment, or bare filename)
+/// are moved to the front in the file's line order; everything else keeps
+/// its existing order.
+fn apply_order(entries: &mut [ManifestEntry], order_path: &std::path::Path) -> error::Result<()> {
+    let text = fs::read_to_string(order_path)?;
+    let patterns: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    // Rank of each entry: index of the first matching pattern, or
+    // "after all patterns" for unlisted entries.
+    let rank = |entry: &ManifestEntry| {
+        let path = entry.path.to_string_lossy();
+        let name = entry
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        patterns
+            .iter()
+            .position(|pat| {
+                glob_match(pat, &path)
+                    || glob_match(pat, &name)
+                    || glob_match(&format!("*/{}", pat), &path)
             })
-            .count();
-        total_count += count;
-        println!("  {:?}: {} images", folder, count);
+            .unwrap_or(patterns.len())
+    };
+    entries.sort_by_key(|entry| rank(entry));
+    Ok(())
+}
+
+/// Sorts entries newest first (--newest-first): by EXIF capture day
+/// with an mtime fallback, mtime breaking ties inside a day. Undatable
+/// entries sink to the back; the sort is stable, so they keep their
+/// scan order there.
+fn sort_newest_first(entries: &mut [ManifestEntry]) {
+    let key = |entry: &ManifestEntry| {
+        let day = date::capture_day(entry);
+        let mtime = fs::metadata(&entry.path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs());
+        (day.map(date::days_from_civil), mtime)
+    };
+    // Decorate-sort via indices so each key is computed once.
+    let keyed: Vec<(Option<i64>, Option<u64>)> = entries.iter().map(key).collect();
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by(|&a, &b| keyed[b].cmp(&keyed[a]));
+    let mut sorted: Vec<ManifestEntry> = order.iter().map(|&i| entries[i].clone()).collect();
+    entries.swap_with_slice(&mut sorted);
+}
+
+/// Merges same-named albums under different roots (--merge-folders):
+/// entries regroup by their parent folder's base name, groups keeping
+/// the scan order of their first member, so `phone/2024-06` and
+/// `camera/2024-06` read as one section. Within a merged group the
+/// images sort by filename (by-name) or capture day (by-date, undated
+/// last and stable).
+fn merge_folders(entries: &mut [ManifestEntry], mode: MergeFolders) {
+    let folder_name = |entry: &ManifestEntry| {
+        entry
+            .path
+            .parent()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    // First-seen order of folder names keeps the overall album order.
+    let mut names: Vec<String> = Vec::new();
+    let group = |name: String, names: &mut Vec<String>| match names.iter().position(|n| *n == name)
+    {
+        Some(at) => at,
+        None => {
+            names.push(name);
+            names.len() - 1
+        }
+    };
+    let keyed: Vec<(usize, Option<i64>)> = entries
+        .iter()
+        .map(|entry| {
+            let day = match mode {
+                MergeFolders::ByName => None,
+                MergeFolders::ByDate => date::capture_day(entry).map(date::days_from_civil),
+            };
+            (group(folder_name(entry), &mut names), day)
+        })
+        .collect();
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by(|&a, &b| {
+        keyed[a].0.cmp(&keyed[b].0).then_with(|| match mode {
+            MergeFolders::ByName => natural_cmp(
+                &entries[a].path.file_name().unwrap_or_default().to_string_lossy(),
+                &entries[b].path.file_name().unwrap_or_default().to_string_lossy(),
+            ),
+            // Undatable entries sort after dated ones; ties keep their
+            // scan order (the sort is stable).
+            MergeFolders::ByDate => match (keyed[a].1, keyed[b].1) {
+                (Some(x), Some(y)) => x.cmp(&y),
+                (Some(_), None) => cmp::Ordering::Less,
+                (None, Some(_)) => cmp::Ordering::Greater,
+                (None, None) => cmp::Ordering::Equal,
+            },
+        })
+    });
+    let mut sorted: Vec<ManifestEntry> = order.iter().map(|&i| entries[i].clone()).collect();
+    entries.swap_with_slice(&mut sorted);
+}
+
+/// Applies --feature and --feature-every spans to matching entries, so
+/// selected images occupy multi-cell blocks; the placement pass resolves
+/// the irregular grid. Explicit manifest spans are left alone.
+fn apply_features(entries: &mut [ManifestEntry], args: &Args) -> error::Result<()> {
+    let mut rules = Vec::new();
+    for rule in &args.feature {
+        let (pattern, span) = rule
+            .split_once('=')
+            .ok_or_else(|| Error::Usage(format!("invalid --feature {:?}, expected PATTERN=SPAN", rule)))?;
+        let valid = span
+            .to_lowercase()
+            .split_once('x')
+            .and_then(|(w, h)| Some((w.trim().parse::<u32>().ok()?, h.trim().parse::<u32>().ok()?)))
+            .is_some_and(|(w, h)| w >= 1 && h >= 1);
+        if !valid {
+            return Err(Error::Usage(format!("invalid --feature span {:?}, expected e.g. 2x2", span)));
+        }
+        rules.push((pattern, span));
+    }
+    for (i, entry) in entries.iter_mut().enumerate() {
+        if entry.span.is_some() {
+            continue;
+        }
+        let path = entry.path.to_string_lossy().into_owned();
+        let name = entry
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let matched = rules.iter().find(|(pat, _)| {
+            glob_match(pat, &path)
+                || glob_match(pat, &name)
+                || glob_match(&format!("*/{}", pat), &path)
+        });
+        if let Some((_, span)) = matched {
+            entry.span = Some(span.to_string());
+        } else if args.feature_every.is_some_and(|n| n > 0 && i % n == 0) {
+            entry.span = Some("2x2".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Converts per-entry weights into cell spans: the span edge scales with
+/// the square root of the weight relative to the median, clamped to 3x3,
+/// so areas stay roughly proportional. Explicit spans are left alone.
+fn apply_weights(entries: &mut [ManifestEntry], by: WeightBy) {
+    let weights: Vec<f64> = entries
+        .iter()
+        .map(|entry| match by {
+            WeightBy::Size => match &entry.data {
+                Some(bytes) => bytes.len() as f64,
+                None => fs::metadata(&entry.path).map(|m| m.len() as f64).unwrap_or(0.0),
+            },
+            WeightBy::Rating => date::rating(entry).unwrap_or(1).max(1) as f64,
+            WeightBy::Manifest => entry.weight.map(f64::from).filter(|w| *w > 0.0).unwrap_or(1.0),
+        })
+        .collect();
+    let mut sorted: Vec<f64> = weights.iter().copied().filter(|w| *w > 0.0).collect();
+    if sorted.is_empty() {
+        return;
+    }
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let median = sorted[sorted.len() / 2];
+    for (entry, weight) in entries.iter_mut().zip(weights) {
+        if entry.span.is_some() {
+            continue;
+        }
+        let edge = ((weight / median).sqrt().round() as u32).clamp(1, 3);
+        if edge > 1 {
+            entry.span = Some(format!("{}x{}", edge, edge));
+        }
+    }
+}
+
+/// Caps the set to what --time-budget can process on schedule: a probe
+/// decode of the first few entries measures per-image cost, 20% of the
+/// budget is held back for layout and encode, and the rest divides into
+/// an affordable image count that sample_entries trims to. The estimate
+/// is single-threaded, so parallel runs finish early rather than late.
+fn apply_time_budget(
+    entries: &mut Vec<ManifestEntry>,
+    budget: std::time::Duration,
+    args: &Args,
+) {
+    const PROBE: usize = 4;
+    if entries.is_empty() {
+        return;
+    }
+    let probe_n = entries.len().min(PROBE);
+    let probe_start = std::time::Instant::now();
+    for entry in entries.iter().take(probe_n) {
+        if let Ok(img) = entry.load_image() {
+            // The resize dominates compositing; include it in the cost.
+            let _ = img.thumbnail(args.cell_size, args.cell_size);
+        }
+    }
+    let per_image = probe_start.elapsed().div_f64(probe_n as f64);
+    let available = budget.mul_f64(0.8).saturating_sub(probe_start.elapsed());
+    let affordable = (available.as_secs_f64() / per_image.as_secs_f64().max(1e-6)) as usize;
+    let affordable = affordable.max(1);
+    if affordable < entries.len() {
+        tracing::info!(
+            "Time budget {:.0?}: keeping {} of {} images (~{:.0?} per image)",
+            budget, affordable, entries.len(), per_image
+        );
+        sample_entries(entries, affordable, args.seed);
+    } else {
+        tracing::debug!(
+            "Time budget {:.0?} fits all {} images (~{:.0?} per image)",
+            budget, entries.len(), per_image
+        );
+    }
+}
+
+/// Shuffles entries with a seeded Fisher-Yates (--shuffle): the whole
+/// set, or each run of consecutive same-folder entries separately so
+/// the album order survives and only the images inside each album move.
+fn shuffle_entries(entries: &mut [ManifestEntry], scope: Shuffle, seed: u64) {
+    let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15).wrapping_add(3);
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let mut fisher_yates = |slice: &mut [ManifestEntry]| {
+        for i in (1..slice.len()).rev() {
+            slice.swap(i, (next() as usize) % (i + 1));
+        }
+    };
+    match scope {
+        Shuffle::All => fisher_yates(entries),
+        Shuffle::WithinFolders => {
+            let mut start = 0;
+            while start < entries.len() {
+                let folder = entries[start].path.parent().map(PathBuf::from);
+                let mut end = start + 1;
+                while end < entries.len()
+                    && entries[end].path.parent().map(PathBuf::from) == folder
+                {
+                    end += 1;
+                }
+                fisher_yates(&mut entries[start..end]);
+                start = end;
+            }
+        }
     }
-    println!("\nTotal images found: {}", total_count);
+    tracing::debug!("Shuffled {} images ({:?}, seed {})", entries.len(), scope, seed);
+}
 
-    if total_count == 0 {
-        eprintln!("No .webp or .jpg images found in the provided folders.");
+/// Deterministically keeps at most `n` entries, chosen by a seeded
+/// xorshift shuffle; the survivors stay in their original order so the
+/// same seed always yields the same collage.
+fn sample_entries(entries: &mut Vec<ManifestEntry>, n: usize, seed: u64) {
+    if n >= entries.len() {
         return;
     }
+    let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15).wrapping_add(1);
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    // Partial Fisher-Yates over the index space picks n without replacement.
+    let mut indices: Vec<usize> = (0..entries.len()).collect();
+    for i in 0..n {
+        let j = i + (next() as usize) % (indices.len() - i);
+        indices.swap(i, j);
+    }
+    let mut keep = vec![false; entries.len()];
+    for &idx in &indices[..n] {
+        keep[idx] = true;
+    }
+    let mut it = keep.into_iter();
+    entries.retain(|_| it.next().unwrap());
+    tracing::info!("Sampled {} images (seed {})", n, seed);
+}
+
+/// Applies the date-range, dimension, aspect-ratio, and blur filters.
+/// The size checks read headers only; --skip-blurry decodes a thumbnail
+/// per image. Unreadable files pass through; the decode stage deals
+/// with them.
+fn filter_entries(entries: &[ManifestEntry], args: &Args) -> error::Result<Vec<ManifestEntry>> {
+    let parse_bound = |flag: &str, spec: Option<&str>| {
+        spec.map(|spec| {
+            date::parse_day(spec).ok_or_else(|| {
+                Error::Usage(format!("invalid {} {:?}; expected YYYY-MM-DD", flag, spec))
+            })
+        })
+        .transpose()
+    };
+    let since = parse_bound("--since", args.since.as_deref())?;
+    let until = parse_bound("--until", args.until.as_deref())?;
+    if let (Some(since), Some(until)) = (since, until) {
+        if since > until {
+            return Err(Error::Usage("--since is later than --until".to_string()));
+        }
+    }
+    let known_bad = match &args.skip_list {
+        Some(path) if !args.retry_skipped => skiplist::load(path),
+        _ => std::collections::HashSet::new(),
+    };
+    let before = entries.len();
+    let kept: Vec<ManifestEntry> = entries
+        .iter()
+        .filter(|entry| {
+            if known_bad.contains(&entry.path) {
+                tracing::debug!("Filtered out {:?}: on the skip list", entry.path);
+                report::note(&entry.path, "on the skip list");
+                return false;
+            }
+            if since.is_some() || until.is_some() {
+                match date::capture_day(entry) {
+                    Some((y, m, d)) => {
+                        if since.is_some_and(|s| (y, m, d) < s) || until.is_some_and(|u| (y, m, d) > u) {
+                            tracing::debug!(
+                                "Filtered out {:?}: captured {:04}-{:02}-{:02}", entry.path, y, m, d
+                            );
+                            report::note(
+                                &entry.path,
+                                format!("captured {:04}-{:02}-{:02}, outside --since/--until", y, m, d),
+                            );
+                            return false;
+                        }
+                    }
+                    None => {
+                        tracing::debug!("Filtered out {:?}: no capture date", entry.path);
+                        report::note(&entry.path, "no capture date for --since/--until");
+                        return false;
+                    }
+                }
+            }
+            if let Some((w, h)) = entry.dimensions() {
+                if args.degenerate == Degenerate::Skip && manifest::is_degenerate((w, h)) {
+                    tracing::debug!("Filtered out {:?}: degenerate {}x{}", entry.path, w, h);
+                    report::note(&entry.path, format!("degenerate dimensions {}x{}", w, h));
+                    return false;
+                }
+                if w < args.min_width.unwrap_or(0) || h < args.min_height.unwrap_or(0) {
+                    tracing::debug!("Filtered out {:?}: {}x{} below minimum size", entry.path, w, h);
+                    report::note(&entry.path, format!("{}x{} below minimum size", w, h));
+                    return false;
+                }
+                if let Some(max_ratio) = args.max_aspect_ratio {
+                    let ratio = cmp::max(w, h) as f32 / cmp::min(w, h).max(1) as f32;
+                    if ratio > max_ratio {
+                        tracing::debug!("Filtered out {:?}: aspect ratio {:.2}", entry.path, ratio);
+                        report::note(&entry.path, format!("aspect ratio {:.2} over the limit", ratio));
+                        return false;
+                    }
+                }
+            }
+            if let Some(threshold) = args.skip_blurry {
+                if let Ok(img) = entry.load_image() {
+                    let score = sharpness(&img);
+                    if score < threshold {
+                        tracing::debug!("Filtered out {:?}: sharpness {:.1}", entry.path, score);
+                        report::note(&entry.path, format!("sharpness {:.1} below threshold", score));
+                        return false;
+                    }
+                }
+            }
+            true
+        })
+        .cloned()
+        .collect();
+    if kept.len() < before {
+        tracing::info!("Filtered out {} of {} images by date/size/aspect/sharpness", before - kept.len(), before);
+    }
+    Ok(kept)
+}
+
+/// Renders the entries to the output path in the selected mode.
+#[cfg(not(target_arch = "wasm32"))]
+fn render(entries: &mut [ManifestEntry], args: &Args, output_path: &str) -> error::Result<()> {
+    let filters_active = args.min_width.is_some()
+        || args.min_height.is_some()
+        || args.max_aspect_ratio.is_some()
+        || args.skip_blurry.is_some()
+        || args.since.is_some()
+        || args.until.is_some()
+        || args.degenerate == Degenerate::Skip
+        || (args.skip_list.is_some() && !args.retry_skipped);
+    // One header-probe pass before anything decodes pixels: the size
+    // and aspect filters, the rows layout, and {width}/{height} caption
+    // fields then read cached dimensions instead of each re-opening the
+    // headers during layout.
+    if args.layout == Layout::Rows
+        || args.min_width.is_some()
+        || args.min_height.is_some()
+        || args.max_aspect_ratio.is_some()
+        || args.degenerate == Degenerate::Skip
+        || args
+            .label_template
+            .as_deref()
+            .is_some_and(|t| t.contains("{width}") || t.contains("{height}"))
+    {
+        manifest::probe_dimensions(entries);
+    }
+    let featured =
+        !args.feature.is_empty() || args.feature_every.is_some() || args.weight_by.is_some();
+    let processed;
+    let mut overflow = 0usize;
+    let entries = if filters_active
+        || args.sample.is_some()
+        || args.shuffle.is_some()
+        || args.time_budget.is_some()
+        || args.newest_first
+        || args.merge_folders.is_some()
+        || args.order.is_some()
+        || featured
+        || args.max_images.is_some()
+        || args.captions
+        || args.label_template.is_some()
+        || args.dedup.is_some()
+        || args.best_of_burst
+        || !args.qr_tile.is_empty()
+        || !args.text_tile.is_empty()
+        || args.pdf_all_pages
+        || entries.iter().any(|entry| entry.text.is_some())
+    {
+        let mut owned = if filters_active {
+            filter_entries(entries, args)?
+        } else {
+            entries.to_vec()
+        };
+        // PDFs fan out into per-page entries before anything counts or
+        // samples them, so each page is a full citizen of the collage.
+        #[cfg(all(feature = "pdf", not(target_arch = "wasm32")))]
+        if args.pdf_all_pages {
+            pdf::expand(&mut owned)?;
+        }
+        // Manifest text tiles become in-memory cards up front so the
+        // rest of the pipeline treats them as plain images.
+        text_tile::apply(&mut owned, args.cell_size);
+        if args.dedup == Some(Dedup::Exact) {
+            dedup_entries(&mut owned);
+        }
+        if args.best_of_burst {
+            burst::apply(&mut owned);
+        }
+        if let Some(mode) = args.merge_folders {
+            merge_folders(&mut owned, mode);
+        }
+        if args.newest_first {
+            sort_newest_first(&mut owned);
+        }
+        if let Some(scope) = args.shuffle {
+            shuffle_entries(&mut owned, scope, args.seed);
+        }
+        if let Some(n) = args.sample {
+            sample_entries(&mut owned, n, args.seed);
+        }
+        if let Some(spec) = &args.time_budget {
+            apply_time_budget(&mut owned, parse_duration(spec)?, args);
+        }
+        if let Some(order_path) = &args.order {
+            apply_order(&mut owned, order_path)?;
+        }
+        if featured {
+            apply_features(&mut owned, args)?;
+            if let Some(by) = args.weight_by {
+                apply_weights(&mut owned, by);
+            }
+        }
+        if let Some(cap) = args.max_images {
+            if owned.len() > cap {
+                overflow = owned.len() - cap;
+                owned.truncate(cap);
+                tracing::info!("Using the first {} images ({} over the cap)", cap, overflow);
+            }
+        }
+        if args.captions {
+            captions::apply(&mut owned);
+        }
+        if let Some(template) = &args.label_template {
+            captions::apply_template(&mut owned, template)?;
+        }
+        // QR and text tiles join after sampling and the cap, so they
+        // never push a real photo out of the collage.
+        for (i, url) in args.qr_tile.iter().enumerate() {
+            let entry = qr::tile_entry(url, args.cell_size)?;
+            match args.qr_position {
+                QrPosition::First => owned.insert(i, entry),
+                QrPosition::Last => owned.push(entry),
+            }
+        }
+        for content in &args.text_tile {
+            owned.push(text_tile::entry(content, args.cell_size));
+        }
+        if owned.is_empty() {
+            return Err(Error::NoImages);
+        }
+        processed = owned;
+        &processed[..]
+    } else {
+        entries
+    };
+    if let Some(manifest_path) = &args.write_manifest {
+        manifest::write_manifest(entries, manifest_path, args.blurhash, args.checksums)?;
+        tracing::info!("Manifest saved to {:?}", manifest_path);
+    }
+    if args.stats_footer {
+        preset::set_stats(entries);
+    }
+    if let Some(mode) = args.animate {
+        let ext = std::path::Path::new(output_path)
+            .extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_lowercase())
+            .unwrap_or_default();
+        if ext != "png" && ext != "apng" {
+            return Err(Error::Usage(
+                "--animate writes APNG, so the output extension must be .png or .apng".to_string(),
+            ));
+        }
+        if args.sprite || args.atlas || args.tiles.is_some() {
+            return Err(Error::Usage(
+                "--animate cannot be combined with --sprite, --atlas, or --tiles".to_string(),
+            ));
+        }
+        if mode == Animate::Pages && args.paginate.is_none() {
+            return Err(Error::Usage(
+                "--animate pages requires --paginate".to_string(),
+            ));
+        }
+        if mode == Animate::BuildUp
+            && (args.layout != Layout::Grid
+                || args.contact_sheet
+                || args.group_by.is_some()
+                || args.folder_separator.is_some()
+                || args.import_layout.is_some()
+                || args.layout_file.is_some()
+                || layout::custom().is_some())
+        {
+            return Err(Error::Usage(
+                "--animate build-up only supports the plain grid layout".to_string(),
+            ));
+        }
+    }
+    if args.sprite {
+        sprite::create_sprite_sheet(entries, output_path, args.sprite_css.as_deref())?;
+        Ok(())
+    } else if args.atlas {
+        atlas::create_atlas(
+            entries,
+            output_path,
+            args.atlas_json.as_deref(),
+            args.power_of_two,
+            args.trim,
+        )?;
+        Ok(())
+    } else {
+        if args.paginate == Some(0) {
+            return Err(Error::Usage("--paginate must be at least 1".to_string()));
+        }
+        // --too-big paginate: cap each page so its near-square grid
+        // stays inside the container's side limit (an explicit
+        // --paginate wins).
+        let mut per_page = args.paginate;
+        if per_page.is_none() && args.too_big == TooBig::Paginate {
+            if let Some(limit) = format_side_limit(output_path) {
+                let cap = (cmp::max(1, limit / args.cell_size) as usize).pow(2);
+                if entries.len() > cap {
+                    tracing::warn!(
+                        "The full canvas would exceed the container's {} px side limit; splitting into pages of {} images",
+                        limit, cap
+                    );
+                    per_page = Some(cap);
+                }
+            }
+        }
+        let pages: Vec<&[ManifestEntry]> = match per_page {
+            Some(per_page) => entries.chunks(per_page).collect(),
+            None => vec![entries],
+        };
+        PAGES.store(pages.len(), Ordering::Relaxed);
+        let mut run = RunSummary::default();
+        let mut result = Ok(());
+        for (page_no, page) in pages.iter().enumerate() {
+            PAGE.store(page_no + 1, Ordering::Relaxed);
+            NUMBER_BASE.store(page_no * per_page.unwrap_or(0), Ordering::Relaxed);
+            let numbered;
+            let page_path = if pages.len() == 1 {
+                output_path
+            } else {
+                numbered = page_output_path(output_path, page_no + 1);
+                &numbered
+            };
+            result = if args.contact_sheet {
+                contact::create_contact_sheet(page, args, page_path, &mut run)
+            } else if let Some(geometry_path) = &args.import_layout {
+                template::create_from_geometry(page, args, geometry_path, page_path, &mut run)
+            } else if let Some(layout_path) = &args.layout_file {
+                template::create_from_layout_file(page, args, layout_path, page_path, &mut run)
+            } else if let Some(custom) = layout::custom() {
+                layout::create_custom(page, args, custom, page_path, &mut run)
+            } else {
+                match args.layout {
+                // --group-by reuses the timeline renderer at its
+                // granularity, whatever the folder structure looked like.
+                Layout::Grid if args.group_by.is_some() => {
+                    timeline::create_timeline(page, args, page_path, &mut run)
+                }
+                Layout::Grid if args.folder_separator.is_some() => {
+                    separator::create_separated(page, args, page_path, &mut run)
+                }
+                Layout::Grid => create_collage(page, args, page_path, &mut run, overflow),
+                Layout::Timeline => timeline::create_timeline(page, args, page_path, &mut run),
+                Layout::Calendar => calendar::create_calendar(page, args, page_path, &mut run),
+                Layout::Voronoi => voronoi::create_voronoi(page, args, page_path, &mut run),
+                Layout::Radial => radial::create_radial(page, args, page_path, &mut run),
+                Layout::Brick => brick::create_brick(page, args, page_path, &mut run),
+                Layout::Diagonal => diagonal::create_diagonal(page, args, page_path, &mut run),
+                Layout::Rows => rows::create_rows(page, args, page_path, &mut run),
+                Layout::Scatter => scatter::create_scatter(page, args, page_path, &mut run),
+                Layout::Embedding => embedding::create_embedding(page, args, page_path, &mut run),
+                Layout::Geo => geo::create_geo(page, args, page_path, &mut run),
+                }
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+        let skipped = run.skipped.len();
+        if let Some(summary_path) = &args.summary {
+            run.write(summary_path)?;
+            tracing::info!("Run summary saved to {:?}", summary_path);
+        }
+        result?;
+        if args.animate == Some(Animate::Pages) {
+            let page_paths: Vec<String> = if pages.len() == 1 {
+                vec![output_path.to_string()]
+            } else {
+                (1..=pages.len())
+                    .map(|page| page_output_path(output_path, page))
+                    .collect()
+            };
+            animate::write_page_cycle(&page_paths, output_path)?;
+        }
+        if skipped > 0 {
+            return Err(Error::PartialFailures(skipped));
+        }
+        Ok(())
+    }
+}
+
+/// Drops later byte-identical copies (--dedup exact), keeping input
+/// order. Synced photo libraries commonly hold the same file in several
+/// folders; contents are compared by size plus a 64-bit content hash.
+/// Unreadable files are kept for the decode stage and its --on-error
+/// policy.
+fn dedup_entries(entries: &mut Vec<ManifestEntry>) {
+    use std::hash::{Hash, Hasher};
+    let before = entries.len();
+    let mut seen: std::collections::HashMap<(u64, u64), PathBuf> =
+        std::collections::HashMap::new();
+    entries.retain(|entry| {
+        let bytes = match &entry.data {
+            Some(bytes) => std::borrow::Cow::Borrowed(bytes.as_slice()),
+            None => match source::read(&entry.path) {
+                Ok(bytes) => std::borrow::Cow::Owned(bytes),
+                Err(_) => return true,
+            },
+        };
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        match seen.entry((bytes.len() as u64, hasher.finish())) {
+            std::collections::hash_map::Entry::Occupied(kept) => {
+                report::note(&entry.path, format!("duplicate of {:?}", kept.get()));
+                false
+            }
+            std::collections::hash_map::Entry::Vacant(slot) => {
+                slot.insert(entry.path.clone());
+                true
+            }
+        }
+    });
+    if entries.len() < before {
+        tracing::info!("Dropped {} exact duplicates", before - entries.len());
+    }
+}
+
+/// Installs the tracing subscriber according to the logging flags.
+fn init_logging(args: &Args) {
+    let level = if args.quiet {
+        tracing::Level::ERROR
+    } else {
+        match args.verbose {
+            0 => tracing::Level::INFO,
+            1 => tracing::Level::DEBUG,
+            _ => tracing::Level::TRACE,
+        }
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_target(false)
+        .with_writer(std::io::stderr);
+    match args.log_format {
+        LogFormat::Text => builder.without_time().init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Parses the CLI with every long option also readable from an
+/// `IMAGE_COLLAGE_*` environment variable (--cell-size becomes
+/// IMAGE_COLLAGE_CELL_SIZE, and so on), for container and cron
+/// deployments where editing flags is awkward. Explicit flags always
+/// win over the environment.
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_args() -> Args {
+    let command = <Args as clap::CommandFactory>::command().mut_args(|arg| {
+        match arg.get_long() {
+            Some(long) if long != "help" && long != "version" => {
+                let var = format!("IMAGE_COLLAGE_{}", long.to_uppercase().replace('-', "_"));
+                arg.env(var)
+            }
+            _ => arg,
+        }
+    });
+    let matches = command.get_matches();
+    match <Args as clap::FromArgMatches>::from_arg_matches(&matches) {
+        Ok(args) => args,
+        Err(e) => e.exit(),
+    }
+}
+
+fn main() {
+    let mut args = parse_args();
+    if args.preset.is_some() {
+        preset::apply(&mut args);
+    }
+    init_logging(&args);
+
+    #[allow(unused_mut)]
+    let mut result = run(&args);
+    if let Some(report_path) = args.report.as_ref().filter(|_| args.command.is_none()) {
+        // A render failure still gets its report; an unwritable report
+        // only becomes the run's error when the render itself was fine.
+        result = result.and(report::write(report_path));
+    }
+    if let Some(list_path) = args.skip_list.as_ref().filter(|_| args.command.is_none()) {
+        result = result.and(skiplist::write(list_path, args.retry_skipped));
+    }
+    if result.is_ok() && args.mirror.is_some() && args.command.is_none() {
+        result = apply_mirror(&args);
+    }
+    if result.is_ok() && args.wallpaper && args.command.is_none() {
+        result = wallpaper::apply(&args);
+    }
+    #[cfg(feature = "preview")]
+    if result.is_ok() && args.preview && args.command.is_none() {
+        result = preview::rerun_loop(&mut args);
+    }
+    if args.report_resources {
+        resources::report();
+    }
+    if let Err(e) = result {
+        tracing::error!("{}", e);
+        std::process::exit(e.exit_code());
+    }
+}
+
+/// On wasm32 the binary is inert; the collage engine is driven through
+/// [`wasm::render`] by the embedding page instead.
+#[cfg(target_arch = "wasm32")]
+fn main() {}
+
+/// The whole CLI after logging is up; errors map to exit codes.
+#[cfg(not(target_arch = "wasm32"))]
+fn run(args: &Args) -> error::Result<()> {
+    if args.generate_man {
+        let man = clap_mangen::Man::new(<Args as clap::CommandFactory>::command());
+        man.render(&mut std::io::stdout())?;
+        return Ok(());
+    }
+
+    match &args.command {
+        Some(Command::Bench { images, input }) => {
+            return bench::run_bench(args, *images, input.as_deref());
+        }
+        Some(Command::Diff { before, after, before_manifest, after_manifest, image }) => {
+            return diff::run_diff(
+                before,
+                after,
+                (before_manifest.as_deref(), after_manifest.as_deref()),
+                args.cell_size,
+                image.as_deref(),
+            );
+        }
+        Some(Command::Montage { args }) => {
+            return montage::run_montage(args);
+        }
+        Some(Command::Palette { input, colors, swatch }) => {
+            return palette::run_palette(args, input, *colors, swatch.as_deref());
+        }
+        Some(Command::Tui { input, output }) => {
+            #[cfg(feature = "tui")]
+            return tui::run_tui(args, input, output);
+            #[cfg(not(feature = "tui"))]
+            {
+                let _ = (input, output);
+                return Err(Error::Usage(
+                    "the tui subcommand requires building with --features tui".to_string(),
+                ));
+            }
+        }
+        Some(Command::Serve { port }) => {
+            return server::serve(*port);
+        }
+        Some(Command::Completions { shell }) => {
+            let mut cmd = <Args as clap::CommandFactory>::command();
+            let name = cmd.get_name().to_string();
+            clap_complete::generate(*shell, &mut cmd, name, &mut std::io::stdout());
+            return Ok(());
+        }
+        None => {}
+    }
+
+    // Resolve the background and paste effects up front so bad flags
+    // fail before any work.
+    if let Some(spec) = &args.background {
+        background::configure(spec)?;
+    }
+    if let Some(texture) = &args.background_image {
+        background::configure_image(texture, args.background_mode)?;
+    }
+    if !args.font.is_empty() {
+        text::configure(&args.font, args.font_size)?;
+    }
+    #[cfg(all(feature = "svg", not(target_arch = "wasm32")))]
+    svg::configure(args.cell_size);
+    if args.tile_rotate.is_some() || args.tile_flip.is_some() {
+        let rotate = args.tile_rotate.unwrap_or(0);
+        if !matches!(rotate, 0 | 90 | 180 | 270) {
+            return Err(Error::Usage(format!(
+                "invalid --tile-rotate {}; expected 90, 180 or 270",
+                rotate
+            )));
+        }
+        let flip = args.tile_flip.as_deref().unwrap_or("").to_lowercase();
+        if !matches!(flip.as_str(), "" | "h" | "v" | "hv" | "vh") {
+            return Err(Error::Usage(format!(
+                "invalid --tile-flip {:?}; expected h, v or hv",
+                flip
+            )));
+        }
+        manifest::configure_orientation(rotate, flip.contains('h'), flip.contains('v'));
+    }
+    if let Some(tolerance) = args.trim_borders {
+        manifest::configure_trim(tolerance);
+    }
+    if args.use_exif_thumbs {
+        manifest::configure_exif_thumbs(args.cell_size);
+    }
+    manifest::configure_degenerate(args.degenerate == Degenerate::Pad);
+    if let Some(spec) = &args.palette {
+        palette::configure_categorical(spec)?;
+    } else if let Some(spec) = args.cell_border.as_deref() {
+        if matches!(parse_cell_border(spec), Ok((_, CellBorderColor::Folder))) {
+            return Err(Error::Usage(
+                "--cell-border PX:folder needs --palette to pick the colours".to_string(),
+            ));
+        }
+    }
+    if let Some(dir) = &args.dump_thumbs {
+        fs::create_dir_all(dir).map_err(|e| {
+            Error::Usage(format!("cannot create --dump-thumbs {:?}: {}", dir, e))
+        })?;
+    }
+    if args.report.is_some() {
+        report::configure();
+    }
+    if args.skip_list.is_some() {
+        skiplist::configure();
+    }
+    if args.deterministic {
+        date::configure_deterministic();
+    }
+    if let Some(code) = &args.locale {
+        date::configure_locale(code)?;
+    }
+    FOLLOW_SYMLINKS.store(args.follow_symlinks, Ordering::Relaxed);
+    INCLUDE_HIDDEN.store(args.include_hidden, Ordering::Relaxed);
+    if let Some(colors) = args.quantize {
+        if !(2..=256).contains(&colors) {
+            return Err(Error::Usage(format!(
+                "--quantize must be between 2 and 256, got {}",
+                colors
+            )));
+        }
+    }
+    if args.preview && cfg!(not(feature = "preview")) {
+        return Err(Error::Usage(
+            "--preview requires building with --features preview".to_string(),
+        ));
+    }
+    if args.pdf_all_pages && cfg!(not(feature = "pdf")) {
+        return Err(Error::Usage(
+            "--pdf-all-pages requires building with --features pdf".into(),
+        ));
+    }
+    #[cfg(all(feature = "pdf", not(target_arch = "wasm32")))]
+    pdf::configure(args.cell_size);
+    if let Some(choice) = &args.animated_frame {
+        let parsed = match choice.as_str() {
+            "first" => manifest::AnimatedFrame::First,
+            "middle" => manifest::AnimatedFrame::Middle,
+            n => manifest::AnimatedFrame::Index(n.parse().map_err(|_| {
+                Error::Usage(format!(
+                    "invalid --animated-frame {:?}; expected first, middle, or a frame number",
+                    choice
+                ))
+            })?),
+        };
+        manifest::configure_animated_frame(parsed);
+    }
+    if args.video_timestamp.is_some() && cfg!(not(feature = "ffmpeg")) {
+        return Err(Error::Usage(
+            "video input requires building with `--features ffmpeg`".to_string(),
+        ));
+    }
+    #[cfg(feature = "ffmpeg")]
+    if let Some(choice) = args.video_timestamp.as_deref() {
+        let parsed = match choice {
+            "first" => video::Timestamp::First,
+            "middle" => video::Timestamp::Middle,
+            s => video::Timestamp::Seconds(s.parse().map_err(|_| {
+                Error::Usage(format!(
+                    "invalid --video-timestamp {:?}; expected first, middle, or seconds",
+                    choice
+                ))
+            })?),
+        };
+        video::configure(parsed);
+    }
+    if args.label_scrim.is_some_and(|opacity| !(0.0..=1.0).contains(&opacity)) {
+        return Err(Error::Usage("--label-scrim must be between 0 and 1".to_string()));
+    }
+    if !(0.0..=1.0).contains(&args.vignette) {
+        return Err(Error::Usage("--vignette must be between 0 and 1".to_string()));
+    }
+    if let Some(spec) = &args.mirror {
+        parse_mirror(spec)?;
+    }
+    if let Some(spec) = &args.time_budget {
+        parse_duration(spec)?;
+    }
+    if let Some(spec) = &args.flatten {
+        if background::parse_color(spec).is_none() {
+            return Err(Error::Usage(format!("invalid --flatten color {:?}", spec)));
+        }
+    }
+    if let Some(spec) = &args.target_size {
+        parse_byte_size(spec)?;
+    }
+    if args.gpu {
+        if cfg!(not(feature = "gpu")) {
+            return Err(Error::Usage(
+                "this build has no GPU support; rebuild with --features gpu".to_string(),
+            ));
+        }
+        if args.layout != Layout::Grid
+            || args.captions
+            || args.label_template.is_some()
+            || args.rotate_jitter.is_some()
+            || args.cell_mask.is_some()
+            || args.cell_shape != CellShape::Square
+            || args.cell_border.is_some()
+            || args.vignette > 0.0
+        {
+            return Err(Error::Usage(
+                "--gpu covers the plain grid paste only; drop the layout, rotation, caption, and cell-effect flags".to_string(),
+            ));
+        }
+    }
+    let mask = match &args.cell_mask {
+        Some(mask_path) => Some(
+            image::open(mask_path)
+                .map_err(|e| {
+                    Error::Usage(format!("cannot read --cell-mask {:?}: {}", mask_path, e))
+                })?
+                .to_luma8(),
+        ),
+        None => None,
+    };
+    let _ = EFFECTS.set(PasteEffects {
+        mask,
+        circle: (args.cell_shape == CellShape::Circle).then_some(args.ring),
+        vignette: args.vignette,
+        fill: args.cell_fill,
+    });
+
+    // --pairs replaces the input directory entirely; as with
+    // --from-manifest, the single positional argument is the output file.
+    if let Some(roots) = &args.pairs {
+        let output = args
+            .output_file
+            .clone()
+            .or_else(|| args.input_dir.clone())
+            .ok_or_else(|| Error::Usage("missing output file argument".to_string()))?;
+        let mut run = RunSummary::default();
+        let result = pairs::create_pairs(&roots[0], &roots[1], args, &output, &mut run);
+        let skipped = run.skipped.len();
+        if let Some(summary_path) = &args.summary {
+            run.write(summary_path)?;
+            tracing::info!("Run summary saved to {:?}", summary_path);
+        }
+        result?;
+        if skipped > 0 {
+            return Err(Error::PartialFailures(skipped));
+        }
+        return Ok(());
+    }
+
+    // With --from-manifest the single positional argument is the output file.
+    let (mut entries, output_file) = if let Some(manifest_path) = &args.from_manifest {
+        let output = args
+            .output_file
+            .clone()
+            .or_else(|| args.input_dir.clone())
+            .ok_or_else(|| Error::Usage("missing output file argument".to_string()))?;
+        let mut entries = manifest::load_manifest(manifest_path)?;
+        tracing::info!("Images in manifest: {}", entries.len());
+        // Download any http(s) entries into the cache before compositing.
+        let cache_dir = args
+            .download_cache
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("image_collage_cache"));
+        fetch::resolve_urls(&mut entries, &cache_dir, args.download_concurrency)?;
+        (entries, output)
+    } else {
+        let input_dir = args
+            .input_dir
+            .clone()
+            .ok_or_else(|| Error::Usage("missing input directory argument".to_string()))?;
+        let output = args
+            .output_file
+            .clone()
+            .ok_or_else(|| Error::Usage("missing output file argument".to_string()))?;
+
+        // An s3://bucket/prefix root streams objects straight from the
+        // bucket (requires building with the `s3` feature).
+        if input_dir.starts_with("s3://") {
+            #[cfg(feature = "s3")]
+            {
+                let mut entries = s3_input::load_s3_entries(&input_dir, args.download_concurrency)?;
+                if entries.is_empty() {
+                    return Err(Error::NoImages);
+                }
+                return render(&mut entries, args, &output);
+            }
+            #[cfg(not(feature = "s3"))]
+            return Err(Error::Usage(
+                "S3 input requires building with `--features s3`".to_string(),
+            ));
+        }
+
+        // An archive file can be passed in place of a directory; its image
+        // entries are read straight from the archive.
+        if archive::is_archive(std::path::Path::new(&input_dir)) {
+            let mut entries = archive::load_archive_entries(std::path::Path::new(&input_dir))?;
+            tracing::info!("Images in archive: {}", entries.len());
+            if entries.is_empty() {
+                return Err(Error::NoImages);
+            }
+            return render(&mut entries, args, &output);
+        }
+
+        let (image_paths, subfolders) =
+            get_sorted_image_paths(&input_dir, args.limit_per_folder, args.lexicographic)?;
+
+        // Count and print images per subfolder.
+        let mut total_count = 0;
+        tracing::info!("Image counts per folder:");
+        for folder in &subfolders {
+            let count = images_in_folder(folder, args.lexicographic).len();
+            total_count += count;
+            tracing::info!("  {:?}: {} images", folder, count);
+        }
+        tracing::info!("\nTotal images found: {}", total_count);
+
+        if total_count == 0 {
+            return Err(Error::NoImages);
+        }
+
+        // Batch mode: one collage per subfolder, named from the template.
+        if args.per_folder {
+            if !output.contains("{folder}") {
+                return Err(Error::Usage(
+                    "--per-folder requires an output template containing {folder}, e.g. {folder}.webp"
+                        .to_string(),
+                ));
+            }
+            let mut first_error = None;
+            for folder in &subfolders {
+                let mut imgs = images_in_folder(folder, args.lexicographic);
+                if let Some(limit) = args.limit_per_folder {
+                    imgs.truncate(limit);
+                }
+                if imgs.is_empty() {
+                    continue;
+                }
+                let name = folder.file_name().unwrap_or_default().to_string_lossy();
+                let folder_output = output.replace("{folder}", &name);
+                let mut entries: Vec<ManifestEntry> =
+                    imgs.into_iter().map(ManifestEntry::from_path).collect();
+                if let Err(e) = render(&mut entries, args, &folder_output) {
+                    if args.strict {
+                        return Err(e);
+                    }
+                    tracing::error!("Error creating collage for {:?}: {}", folder, e);
+                    first_error.get_or_insert(e);
+                }
+            }
+            return match first_error {
+                Some(e) => Err(e),
+                None => Ok(()),
+            };
+        }
+
+        // Two-level mode: each subfolder collapses to one mini-collage
+        // cell, captioned with the folder name.
+        if args.nested {
+            let mut entries = nested::build_entries(&subfolders, args)?;
+            return render(&mut entries, args, &output);
+        }
 
-    if let Err(e) = create_collage(&image_paths, args.cell_size, &args.output_file) {
-        eprintln!("Error creating collage: {}", e);
+        // Stratified sampling: take a share of each subfolder rather than
+        // letting the first-sorting folders crowd everything else out.
+        if let Some(target) = args.balance {
+            let folder_images: Vec<Vec<PathBuf>> = subfolders
+                .iter()
+                .map(|folder| {
+                    let mut imgs = images_in_folder(folder, args.lexicographic);
+                    if let Some(limit) = args.limit_per_folder {
+                        imgs.truncate(limit);
+                    }
+                    imgs
+                })
+                .collect();
+            let sizes: Vec<usize> = folder_images.iter().map(|imgs| imgs.len()).collect();
+            let folder_weights = match args.folder_weight.as_deref() {
+                Some(spec) => parse_folder_weights(spec)?,
+                None => Vec::new(),
+            };
+            let weights: Vec<f64> = subfolders
+                .iter()
+                .map(|folder| folder_weight(folder, &folder_weights))
+                .collect();
+            let shares = balance_allocation(&sizes, &weights, target);
+            let mut entries = Vec::new();
+            for (i, imgs) in folder_images.into_iter().enumerate() {
+                let mut folder_entries: Vec<ManifestEntry> =
+                    imgs.into_iter().map(ManifestEntry::from_path).collect();
+                sample_entries(&mut folder_entries, shares[i], args.seed.wrapping_add(i as u64));
+                entries.extend(folder_entries);
+            }
+            tracing::info!("Balanced sample: {} of {} images", entries.len(), total_count);
+            return render(&mut entries, args, &output);
+        }
+
+        let entries = image_paths
+            .into_iter()
+            .map(ManifestEntry::from_path)
+            .collect();
+        (entries, output)
+    };
+
+    render(&mut entries, args, &output_file)
+}
+
+#[cfg(test)]
+mod tests {
+    /// --trim-borders leaves sizes like 400x429 whose f32 fit into a
+    /// 200px cell requests a 186x200 resize that `resize` (rounding the
+    /// aspect ratio in f64) answers with 186x199; the paste must follow
+    /// the dimensions that came back instead of indexing one row past
+    /// the end.
+    #[test]
+    fn paste_image_follows_actual_resized_dimensions() {
+        for (w, h, cell) in [(400, 429, 200), (50, 71, 66)] {
+            let img = image::DynamicImage::new_rgba8(w, h);
+            let mut buf = vec![0u8; (cell * cell * 4) as usize];
+            crate::paste_image(&mut buf, (cell, cell), (0, 0, cell, cell), &img);
+        }
     }
 }
@@ -0,0 +1,639 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Which frame to decode from animated GIF/WebP sources.
+#[derive(Clone, Copy)]
+pub enum AnimatedFrame {
+    /// Frame 0 — what the plain decode path yields anyway.
+    First,
+    /// The frame halfway through the animation.
+    Middle,
+    /// A specific frame, clamped to the last one.
+    Index(usize),
+}
+
+static ANIMATED_FRAME: OnceLock<AnimatedFrame> = OnceLock::new();
+
+/// Registers the frame choice from --animated-frame. Call once before
+/// rendering starts; later calls are ignored.
+pub fn configure_animated_frame(choice: AnimatedFrame) {
+    let _ = ANIMATED_FRAME.set(choice);
+}
+
+/// Decodes the chosen frame of an animated source, or None when no
+/// choice beyond the default is registered, the format has no
+/// animation, or frame extraction fails (the plain path then decides).
+fn animated_frame(bytes: &[u8], format: image::ImageFormat) -> Option<image::DynamicImage> {
+    use image::AnimationDecoder;
+    let choice = *ANIMATED_FRAME.get()?;
+    if matches!(choice, AnimatedFrame::First) {
+        return None;
+    }
+    let cursor = std::io::Cursor::new(bytes);
+    let frames = match format {
+        image::ImageFormat::Gif => image::codecs::gif::GifDecoder::new(cursor)
+            .ok()?
+            .into_frames()
+            .collect_frames()
+            .ok()?,
+        image::ImageFormat::WebP => image::codecs::webp::WebPDecoder::new(cursor)
+            .ok()?
+            .into_frames()
+            .collect_frames()
+            .ok()?,
+        _ => return None,
+    };
+    if frames.is_empty() {
+        return None;
+    }
+    let index = match choice {
+        AnimatedFrame::First => 0,
+        AnimatedFrame::Middle => frames.len() / 2,
+        AnimatedFrame::Index(n) => n.min(frames.len() - 1),
+    };
+    Some(image::DynamicImage::ImageRgba8(
+        frames.into_iter().nth(index)?.into_buffer(),
+    ))
+}
+
+/// One input image as described by a manifest row (or synthesized from a
+/// directory scan). Everything except `path` is optional.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManifestEntry {
+    /// Path to the image file.
+    pub path: PathBuf,
+
+    /// Optional caption drawn at the bottom of the cell.
+    #[serde(default)]
+    pub caption: Option<String>,
+
+    /// Optional sort key; entries with a key are ordered by it (stable),
+    /// entries without keep their manifest order.
+    #[serde(default)]
+    pub sort: Option<String>,
+
+    /// Optional relative weight (reserved for weighted layouts).
+    #[serde(default)]
+    pub weight: Option<f32>,
+
+    /// Optional cell span like "2x2" (columns x rows).
+    #[serde(default)]
+    pub span: Option<String>,
+
+    /// Optional rotation in degrees (counter-clockwise), overriding
+    /// --rotate-jitter for this image.
+    #[serde(default)]
+    pub rotation: Option<f64>,
+
+    /// Optional right-angle orientation fix (`90`, `180`, `270`, flip
+    /// letters `h`/`v`, or both, e.g. `90h`), overriding --tile-rotate
+    /// and --tile-flip for this image.
+    #[serde(default)]
+    pub orient: Option<String>,
+
+    /// Optional link target used by --image-map instead of `path`.
+    #[serde(default)]
+    pub url: Option<String>,
+
+    /// Optional text rendered as a text-only tile instead of an image;
+    /// `path` may be empty for these entries.
+    #[serde(default)]
+    pub text: Option<String>,
+
+    /// In-memory image bytes (e.g. read from an archive), decoded instead
+    /// of opening `path` when present.
+    #[serde(skip)]
+    pub data: Option<Vec<u8>>,
+
+    /// Dimensions cached by [`probe_dimensions`] so the filter and
+    /// layout stages read each header once.
+    #[serde(skip)]
+    pub dims: Option<(u32, u32)>,
+}
+
+impl ManifestEntry {
+    /// Wraps a plain path (e.g. from the directory scanner) as an entry.
+    pub fn from_path(path: PathBuf) -> Self {
+        ManifestEntry {
+            path,
+            caption: None,
+            sort: None,
+            weight: None,
+            span: None,
+            rotation: None,
+            orient: None,
+            url: None,
+            text: None,
+            data: None,
+            dims: None,
+        }
+    }
+
+    /// Decodes the entry's image and applies the uniform --tile-rotate /
+    /// --tile-flip orientation; a manifest `orient` value overrides both
+    /// for its image.
+    pub fn load_image(&self) -> image::ImageResult<image::DynamicImage> {
+        let img = self.decode_image()?;
+        let img = match TRIM_TOLERANCE.get() {
+            Some(&tolerance) => trim_borders(img, tolerance),
+            None => img,
+        };
+        let img = if is_degenerate((img.width(), img.height())) {
+            if DEGENERATE_PAD.get().copied().unwrap_or(true) {
+                pad_degenerate(img, &self.path)
+            } else {
+                let (w, h) = (img.width(), img.height());
+                return Err(image::ImageError::IoError(std::io::Error::other(format!(
+                    "degenerate image ({}x{})",
+                    w, h
+                ))));
+            }
+        } else {
+            img
+        };
+        let (rotate, flip_h, flip_v) = match self.orient.as_deref() {
+            Some(spec) => parse_orient(spec).unwrap_or_else(|| {
+                tracing::warn!("Ignoring invalid orient {:?} for {:?}", spec, self.path);
+                orientation()
+            }),
+            None => orientation(),
+        };
+        let img = match rotate {
+            90 => img.rotate90(),
+            180 => img.rotate180(),
+            270 => img.rotate270(),
+            _ => img,
+        };
+        let img = if flip_h { img.fliph() } else { img };
+        Ok(if flip_v { img.flipv() } else { img })
+    }
+
+    /// Decodes the entry's image, from the in-memory bytes if present,
+    /// otherwise through the registered [`crate::source`]. The decoder is
+    /// chosen by sniffing the magic bytes (extensions are often wrong); if
+    /// that fails, the remaining decoders are tried before the file is
+    /// declared unreadable.
+    fn decode_image(&self) -> image::ImageResult<image::DynamicImage> {
+        // RAW files go through rawloader/imagepipe (raw feature).
+        #[cfg(all(feature = "raw", not(target_arch = "wasm32")))]
+        if self.data.is_none() && crate::raw::is_raw(&self.path) {
+            let img = crate::raw::decode(&self.path).map_err(|e| {
+                image::ImageError::IoError(std::io::Error::other(e))
+            })?;
+            count_decoded(&img);
+            return Ok(img);
+        }
+        // HEIC/HEIF files go through the system libheif (heic feature).
+        #[cfg(all(feature = "heic", not(target_arch = "wasm32")))]
+        if self.data.is_none() && crate::heic::is_heic(&self.path) {
+            let img = crate::heic::decode(&self.path).map_err(|e| {
+                image::ImageError::IoError(std::io::Error::other(e))
+            })?;
+            count_decoded(&img);
+            return Ok(img);
+        }
+        // PDFs contribute their rendered first page; --pdf-all-pages
+        // expands them into per-page entries earlier, with data set
+        // (pdf feature).
+        #[cfg(all(feature = "pdf", not(target_arch = "wasm32")))]
+        if self.data.is_none() && crate::pdf::is_pdf(&self.path) {
+            let page = crate::pdf::render_page(&self.path, 1)
+                .map_err(image::ImageError::IoError)?;
+            let img = image::load_from_memory(&page)?;
+            count_decoded(&img);
+            return Ok(img);
+        }
+        // Video files contribute one extracted frame instead of their
+        // own bytes (ffmpeg feature).
+        #[cfg(all(feature = "ffmpeg", not(target_arch = "wasm32")))]
+        if self.data.is_none() && crate::video::is_video(&self.path) {
+            let frame = crate::video::extract_frame(&self.path)
+                .map_err(image::ImageError::IoError)?;
+            let img = image::load_from_memory(&frame)?;
+            count_decoded(&img);
+            return Ok(img);
+        }
+        let bytes = match &self.data {
+            Some(bytes) => std::borrow::Cow::Borrowed(bytes.as_slice()),
+            None => std::borrow::Cow::Owned(
+                crate::source::read(&self.path).map_err(image::ImageError::IoError)?,
+            ),
+        };
+        // --use-exif-thumbs: a JPEG's embedded thumbnail decodes in
+        // microseconds; use it whenever it already covers the cell.
+        if let Some(&min_side) = EXIF_THUMB_SIZE.get() {
+            if bytes.get(..2) == Some(&[0xFF, 0xD8]) {
+                if let Some(thumb) = crate::date::exif_thumbnail(&bytes) {
+                    if let Ok(img) =
+                        image::load_from_memory_with_format(&thumb, image::ImageFormat::Jpeg)
+                    {
+                        if img.width() >= min_side || img.height() >= min_side {
+                            tracing::debug!("Using the EXIF thumbnail of {:?}", self.path);
+                            count_decoded(&img);
+                            return Ok(img);
+                        }
+                    }
+                }
+            }
+        }
+        // SVG files are rasterized at the cell resolution (svg feature).
+        #[cfg(all(feature = "svg", not(target_arch = "wasm32")))]
+        if crate::svg::is_svg(&self.path) {
+            let img = crate::svg::decode(&self.path, &bytes).map_err(|e| {
+                image::ImageError::IoError(std::io::Error::other(e))
+            })?;
+            count_decoded(&img);
+            return Ok(img);
+        }
+        let sniffed = sniff_format(&bytes);
+        if let Some(format) = sniffed {
+            if let Some(img) = animated_frame(&bytes, format) {
+                count_decoded(&img);
+                return Ok(img);
+            }
+        }
+        let first = match sniffed {
+            Some(format) => image::load_from_memory_with_format(&bytes, format),
+            None => image::load_from_memory(&bytes),
+        };
+        let err = match first {
+            Ok(img) => {
+                count_decoded(&img);
+                return Ok(img);
+            }
+            Err(e) => e,
+        };
+        for format in FALLBACK_FORMATS {
+            if Some(format) == sniffed {
+                continue;
+            }
+            if let Ok(img) = image::load_from_memory_with_format(&bytes, format) {
+                tracing::warn!("Decoded {:?} as {:?} despite its header/extension", self.path, format);
+                count_decoded(&img);
+                return Ok(img);
+            }
+        }
+        Err(err)
+    }
+
+    /// Reads the image dimensions from the header only (no full decode).
+    /// Returns None for unreadable files; those are left for the decode
+    /// stage and its --on-error policy.
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        if let Some(dims) = self.dims {
+            return Some(dims);
+        }
+        let cursor_dimensions = |bytes: &[u8]| {
+            image::io::Reader::new(std::io::Cursor::new(bytes))
+                .with_guessed_format()
+                .ok()?
+                .into_dimensions()
+                .ok()
+        };
+        let dims = match &self.data {
+            Some(bytes) => cursor_dimensions(bytes),
+            // Header-only read for plain files; a custom source hands
+            // over the whole buffer either way.
+            None if crate::source::is_custom() => match crate::source::read(&self.path) {
+                Ok(bytes) => cursor_dimensions(&bytes),
+                Err(_) => None,
+            },
+            None => image::image_dimensions(&self.path).ok(),
+        };
+        // A quarter-turn orientation swaps the sides load_image hands out.
+        let rotate = match self.orient.as_deref().and_then(parse_orient) {
+            Some((rotate, _, _)) => rotate,
+            None => orientation().0,
+        };
+        dims.map(|(w, h)| if rotate == 90 || rotate == 270 { (h, w) } else { (w, h) })
+    }
+
+    /// Parses the span field into (columns, rows), defaulting to 1x1.
+    pub fn span_cells(&self) -> (u32, u32) {
+        if let Some(span) = &self.span {
+            if let Some((w, h)) = span.to_lowercase().split_once('x') {
+                if let (Ok(w), Ok(h)) = (w.trim().parse(), h.trim().parse()) {
+                    if w >= 1 && h >= 1 {
+                        return (w, h);
+                    }
+                }
+            }
+            tracing::warn!("Ignoring invalid span {:?} for {:?}", span, self.path);
+        }
+        (1, 1)
+    }
+}
+
+/// Feeds the decoded RGBA size into the resource counters.
+fn count_decoded(img: &image::DynamicImage) {
+    #[cfg(not(target_arch = "wasm32"))]
+    crate::resources::add_decoded(img.width() as u64 * img.height() as u64 * 4);
+    #[cfg(target_arch = "wasm32")]
+    let _ = img;
+}
+
+/// Decoders tried, most common first, when the sniffed format fails.
+const FALLBACK_FORMATS: [image::ImageFormat; 6] = [
+    image::ImageFormat::Jpeg,
+    image::ImageFormat::Png,
+    image::ImageFormat::WebP,
+    image::ImageFormat::Gif,
+    image::ImageFormat::Bmp,
+    image::ImageFormat::Tiff,
+];
+
+/// Identifies the image format from its magic bytes, if recognized.
+fn sniff_format(bytes: &[u8]) -> Option<image::ImageFormat> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => Some(image::ImageFormat::Png),
+        [0xFF, 0xD8, 0xFF, ..] => Some(image::ImageFormat::Jpeg),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => {
+            Some(image::ImageFormat::WebP)
+        }
+        [b'G', b'I', b'F', b'8', ..] => Some(image::ImageFormat::Gif),
+        [b'B', b'M', ..] => Some(image::ImageFormat::Bmp),
+        [b'I', b'I', 42, 0, ..] | [b'M', b'M', 0, 42, ..] => Some(image::ImageFormat::Tiff),
+        _ => None,
+    }
+}
+
+/// The uniform tile orientation, registered once from --tile-rotate and
+/// --tile-flip before rendering starts.
+static ORIENTATION: std::sync::OnceLock<(u32, bool, bool)> = std::sync::OnceLock::new();
+
+/// Registers the uniform orientation (quarter-turn degrees, flip
+/// horizontal, flip vertical).
+pub fn configure_orientation(rotate: u32, flip_h: bool, flip_v: bool) {
+    let _ = ORIENTATION.set((rotate, flip_h, flip_v));
+}
+
+/// The active uniform orientation; identity if none was configured.
+fn orientation() -> (u32, bool, bool) {
+    ORIENTATION.get().copied().unwrap_or((0, false, false))
+}
+
+/// Parses an orientation spec: a right-angle rotation (90/180/270),
+/// flip letters (h/v), or both, e.g. `90`, `h`, `270v`.
+pub fn parse_orient(spec: &str) -> Option<(u32, bool, bool)> {
+    let spec = spec.trim().to_lowercase();
+    let digits: String = spec.chars().take_while(|c| c.is_ascii_digit()).collect();
+    let rotate = if digits.is_empty() { 0 } else { digits.parse().ok()? };
+    if !matches!(rotate, 0 | 90 | 180 | 270) {
+        return None;
+    }
+    let (mut flip_h, mut flip_v) = (false, false);
+    for c in spec[digits.len()..].chars() {
+        match c {
+            'h' => flip_h = true,
+            'v' => flip_v = true,
+            _ => return None,
+        }
+    }
+    Some((rotate, flip_h, flip_v))
+}
+
+/// The cell size --use-exif-thumbs must cover, registered once before
+/// rendering starts.
+static EXIF_THUMB_SIZE: std::sync::OnceLock<u32> = std::sync::OnceLock::new();
+
+/// Registers --use-exif-thumbs with the cell size an embedded thumbnail
+/// has to reach before it replaces the full decode.
+pub fn configure_exif_thumbs(cell_size: u32) {
+    let _ = EXIF_THUMB_SIZE.set(cell_size);
+}
+
+/// The --trim-borders tolerance, registered once before rendering
+/// starts.
+static TRIM_TOLERANCE: std::sync::OnceLock<u8> = std::sync::OnceLock::new();
+
+/// Registers the --trim-borders tolerance (max per-channel distance
+/// from the border colour).
+pub fn configure_trim(tolerance: u8) {
+    let _ = TRIM_TOLERANCE.set(tolerance);
+}
+
+/// Aspect ratio beyond which an image counts as degenerate: at 100:1
+/// the short side of any reasonable cell rounds to zero pixels.
+const MAX_ASPECT: u32 = 100;
+
+/// Whether `--degenerate` pads degenerate images (true, the default) or
+/// turns them into decode failures; set once from run().
+static DEGENERATE_PAD: OnceLock<bool> = OnceLock::new();
+
+pub fn configure_degenerate(pad: bool) {
+    let _ = DEGENERATE_PAD.set(pad);
+}
+
+/// Whether these dimensions would break the cell-fitting math: zero or
+/// one-pixel images, or an aspect ratio over [`MAX_ASPECT`].
+pub fn is_degenerate((w, h): (u32, u32)) -> bool {
+    w == 0
+        || h == 0
+        || (w == 1 && h == 1)
+        || w > h.saturating_mul(MAX_ASPECT)
+        || h > w.saturating_mul(MAX_ASPECT)
+}
+
+/// Centers a degenerate image on a transparent canvas of workable shape:
+/// at least 2x2, aspect capped at [`MAX_ASPECT`], so every later resize
+/// keeps both sides non-zero.
+fn pad_degenerate(img: image::DynamicImage, path: &Path) -> image::DynamicImage {
+    let (w, h) = (img.width(), img.height());
+    let target_w = w.max(h.div_ceil(MAX_ASPECT)).max(2);
+    let target_h = h.max(w.div_ceil(MAX_ASPECT)).max(2);
+    tracing::debug!("Padded degenerate {}x{} image {:?} to {}x{}", w, h, path, target_w, target_h);
+    let mut canvas = image::RgbaImage::new(target_w, target_h);
+    image::imageops::overlay(
+        &mut canvas,
+        &img.to_rgba8(),
+        ((target_w - w) / 2) as i64,
+        ((target_h - h) / 2) as i64,
+    );
+    image::DynamicImage::ImageRgba8(canvas)
+}
+
+/// Crops uniform-colour margins off the image (--trim-borders): each
+/// edge takes its outermost row or column as the border colour and
+/// moves inwards while every pixel stays within `tolerance` per channel
+/// of it, so scans with white or black mats fill their cells. An image
+/// that trims down to nothing — one solid colour — is left alone.
+fn trim_borders(img: image::DynamicImage, tolerance: u8) -> image::DynamicImage {
+    let rgb = img.to_rgb8();
+    let (w, h) = rgb.dimensions();
+    let close = |a: &image::Rgb<u8>, b: &image::Rgb<u8>| {
+        a.0.iter().zip(b.0.iter()).all(|(x, y)| x.abs_diff(*y) <= tolerance)
+    };
+    let row_uniform = |y: u32, reference: &image::Rgb<u8>| {
+        (0..w).all(|x| close(rgb.get_pixel(x, y), reference))
+    };
+    let (mut top, mut bottom) = (0u32, h);
+    let reference = *rgb.get_pixel(0, 0);
+    while top < bottom && row_uniform(top, &reference) {
+        top += 1;
+    }
+    let reference = *rgb.get_pixel(0, h - 1);
+    while bottom > top && row_uniform(bottom - 1, &reference) {
+        bottom -= 1;
+    }
+    // Columns only need to be uniform across the surviving rows.
+    let col_uniform = |x: u32, reference: &image::Rgb<u8>| {
+        (top..bottom).all(|y| close(rgb.get_pixel(x, y), reference))
+    };
+    let (mut left, mut right) = (0u32, w);
+    let reference = *rgb.get_pixel(0, top.min(h - 1));
+    while left < right && col_uniform(left, &reference) {
+        left += 1;
+    }
+    let reference = *rgb.get_pixel(w - 1, top.min(h - 1));
+    while right > left && col_uniform(right - 1, &reference) {
+        right -= 1;
+    }
+    if top >= bottom || left >= right || (top, left, bottom, right) == (0, 0, h, w) {
+        return img;
+    }
+    img.crop_imm(left, top, right - left, bottom - top)
+}
+
+/// Header-only dimension pass over the whole set, run once up front so
+/// the size filters, dimension-driven layouts, and {width}/{height}
+/// caption fields hit the cache instead of re-opening files mid-layout.
+/// Unreadable files stay uncached for the decode stage to report.
+pub fn probe_dimensions(entries: &mut [ManifestEntry]) {
+    let mut probed = 0usize;
+    for entry in entries.iter_mut() {
+        if entry.dims.is_none() {
+            entry.dims = entry.dimensions();
+            if entry.dims.is_some() {
+                probed += 1;
+            }
+        }
+    }
+    tracing::debug!("Probed dimensions for {} of {} images from headers", probed, entries.len());
+}
+
+/// Writes the entries back out as a JSON manifest (the format
+/// `--from-manifest` reads), omitting unset fields. With `blurhash`,
+/// each readable image gets a BlurHash computed from a small thumbnail,
+/// for frontends that want instant placeholders. With `checksums`, each
+/// readable file gets its byte size and CRC-32 so an archived manifest
+/// can be verified against the source files later.
+pub fn write_manifest(
+    entries: &[ManifestEntry],
+    path: &Path,
+    blurhash: bool,
+    checksums: bool,
+) -> crate::error::Result<()> {
+    let rows: Vec<serde_json::Value> = entries
+        .iter()
+        .map(|entry| {
+            let mut row = serde_json::Map::new();
+            row.insert("path".into(), entry.path.to_string_lossy().into());
+            if let Some(caption) = &entry.caption {
+                row.insert("caption".into(), caption.as_str().into());
+            }
+            if let Some(sort) = &entry.sort {
+                row.insert("sort".into(), sort.as_str().into());
+            }
+            if let Some(weight) = entry.weight {
+                row.insert("weight".into(), weight.into());
+            }
+            if let Some(span) = &entry.span {
+                row.insert("span".into(), span.as_str().into());
+            }
+            if let Some(rotation) = entry.rotation {
+                row.insert("rotation".into(), rotation.into());
+            }
+            if let Some(orient) = &entry.orient {
+                row.insert("orient".into(), orient.as_str().into());
+            }
+            if let Some(url) = &entry.url {
+                row.insert("url".into(), url.as_str().into());
+            }
+            if let Some(text) = &entry.text {
+                row.insert("text".into(), text.as_str().into());
+            }
+            if blurhash {
+                if let Ok(img) = entry.load_image() {
+                    let thumb = img.thumbnail(32, 32);
+                    row.insert("blurhash".into(), crate::blurhash::encode(&thumb, 4, 3).into());
+                }
+            }
+            if checksums {
+                let bytes = match &entry.data {
+                    Some(bytes) => Ok(std::borrow::Cow::Borrowed(bytes.as_slice())),
+                    None => crate::source::read(&entry.path).map(std::borrow::Cow::Owned),
+                };
+                if let Ok(bytes) = bytes {
+                    let mut crc = flate2::Crc::new();
+                    crc.update(&bytes);
+                    row.insert("bytes".into(), bytes.len().into());
+                    row.insert("crc32".into(), format!("{:08x}", crc.sum()).into());
+                }
+            }
+            serde_json::Value::Object(row)
+        })
+        .collect();
+    let text = serde_json::to_string_pretty(&rows).expect("manifest serializes");
+    fs::write(path, text)
+        .map_err(|e| crate::error::Error::output(&path.to_string_lossy(), e))
+}
+
+/// Loads a manifest file. JSON files must contain an array of entry objects;
+/// anything else is parsed as CSV with a
+/// `path,caption,sort,weight,span,rotation,orient,url` header (only `path` is
+/// required). Passing `-` reads CSV from stdin.
+pub fn load_manifest(path: &str) -> crate::error::Result<Vec<ManifestEntry>> {
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let bad_row = |e: csv::Error| {
+        crate::error::Error::Usage(format!("invalid manifest {:?}: {}", path, e))
+    };
+
+    let mut entries = if path == "-" {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .flexible(true)
+            .from_reader(std::io::stdin());
+        reader
+            .deserialize()
+            .map(|row| row.map_err(bad_row))
+            .collect::<crate::error::Result<Vec<ManifestEntry>>>()?
+    } else if ext == "json" {
+        let data = fs::read_to_string(path)?;
+        serde_json::from_str::<Vec<ManifestEntry>>(&data).map_err(|e| {
+            crate::error::Error::Usage(format!("invalid manifest {:?}: {}", path, e))
+        })?
+    } else {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .flexible(true)
+            .from_reader(fs::File::open(path)?);
+        reader
+            .deserialize()
+            .map(|row| row.map_err(bad_row))
+            .collect::<crate::error::Result<Vec<ManifestEntry>>>()?
+    };
+
+    for entry in &entries {
+        if let Some(w) = entry.weight {
+            if w <= 0.0 {
+                tracing::warn!("Ignoring non-positive weight {} for {:?}", w, entry.path);
+            }
+        }
+    }
+
+    // Stable sort on the optional sort key: keyed entries are ordered by key,
+    // unkeyed entries keep their manifest order at the end.
+    entries.sort_by(|a, b| match (&a.sort, &b.sort) {
+        (Some(x), Some(y)) => x.cmp(y),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+    Ok(entries)
+}
@@ -0,0 +1,216 @@
+//! `montage` subcommand: ImageMagick `montage` compatibility.
+//!
+//! Scripts built around `montage -tile 4x -geometry +5+5 -label '%f'`
+//! can switch to this tool by swapping the binary name: the familiar
+//! single-dash options are translated into the equivalent internal
+//! flags and the normal pipeline renders the result. File arguments are
+//! fed through a temporary --from-manifest listing, so shell globs work
+//! exactly as they do with montage; a single directory argument scans
+//! as usual. The translation is approximate where the models differ —
+//! cells here are square, so `-geometry WxH` takes the larger edge, and
+//! tile spacing becomes a background-coloured --cell-border.
+
+use std::io::Write;
+use std::path::Path;
+
+use clap::Parser;
+
+use crate::error::{self, Error};
+
+/// The montage options the translator understands.
+const SUPPORTED: &str = "-tile, -geometry, -label, -background, -title";
+
+/// Parses montage-style arguments, translates them into the internal
+/// CLI, and runs the normal pipeline.
+pub fn run_montage(argv: &[String]) -> error::Result<()> {
+    let mut tile = None;
+    let mut geometry = None;
+    let mut label = None;
+    let mut background = None;
+    let mut title = None;
+    let mut positional = Vec::new();
+    let mut tokens = argv.iter();
+    while let Some(token) = tokens.next() {
+        let slot = match token.as_str() {
+            "-tile" => &mut tile,
+            "-geometry" => &mut geometry,
+            "-label" => &mut label,
+            "-background" => &mut background,
+            "-title" => &mut title,
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(Error::Usage(format!(
+                    "montage option {} is not supported; supported options: {}",
+                    flag, SUPPORTED
+                )));
+            }
+            _ => {
+                positional.push(token.clone());
+                continue;
+            }
+        };
+        *slot = Some(
+            tokens
+                .next()
+                .ok_or_else(|| Error::Usage(format!("montage option {} needs a value", token)))?
+                .clone(),
+        );
+    }
+    if positional.len() < 2 {
+        return Err(Error::Usage(
+            "montage needs at least one input and an output file".to_string(),
+        ));
+    }
+    let output = positional.pop().expect("checked above");
+
+    let mut internal: Vec<String> = vec!["rust_img_collage".to_string()];
+    // A lone directory scans the usual subfolder tree; anything else is
+    // a montage-style file list, listed in a temporary manifest so the
+    // given order is kept.
+    let _manifest: Option<tempfile::NamedTempFile> =
+        if positional.len() == 1 && Path::new(&positional[0]).is_dir() {
+            internal.push(positional.pop().expect("checked above"));
+            None
+        } else {
+            let mut csv = String::from("path\n");
+            for path in &positional {
+                // Minimal CSV quoting, so commas and quotes in paths survive.
+                csv.push('"');
+                csv.push_str(&path.replace('"', "\"\""));
+                csv.push_str("\"\n");
+            }
+            let mut file = tempfile::NamedTempFile::new()?;
+            file.write_all(csv.as_bytes())?;
+            internal.push("--from-manifest".to_string());
+            internal.push(file.path().to_string_lossy().into_owned());
+            Some(file)
+        };
+    internal.push(output);
+
+    if let Some(spec) = &tile {
+        let (cols, rows) = parse_tile(spec)?;
+        internal.push("--columns".to_string());
+        internal.push(cols.to_string());
+        if let Some(rows) = rows {
+            // montage starts a new page once a CxR sheet is full.
+            internal.push("--paginate".to_string());
+            internal.push((cols as usize * rows as usize).to_string());
+        }
+    }
+    // montage composes on white unless told otherwise.
+    let background = background.unwrap_or_else(|| "#ffffff".to_string());
+    internal.push("--background".to_string());
+    internal.push(background.clone());
+    if let Some(spec) = &geometry {
+        let (size, spacing) = parse_geometry(spec)?;
+        if let Some(size) = size {
+            internal.push("--cell-size".to_string());
+            internal.push(size.to_string());
+        }
+        if spacing > 0 {
+            internal.push("--cell-border".to_string());
+            internal.push(format!("{}:{}", spacing, background));
+        }
+    }
+    if let Some(template) = &label {
+        internal.push("--label-template".to_string());
+        internal.push(translate_label(template));
+    }
+    if let Some(title) = title {
+        internal.push("--page-header".to_string());
+        internal.push(title);
+    }
+
+    tracing::debug!("montage translated to: {:?}", &internal[1..]);
+    let args = crate::Args::try_parse_from(&internal)
+        .map_err(|e| Error::Usage(format!("montage arguments did not translate: {}", e)))?;
+    crate::run(&args)
+}
+
+/// Parses `-tile`: `4x6`, `4x`, or a bare `4` (columns, optional rows).
+fn parse_tile(spec: &str) -> error::Result<(u32, Option<u32>)> {
+    let bad = || Error::Usage(format!("invalid -tile {:?}; expected 4x, 4x6, or 4", spec));
+    let (cols, rows) = match spec.split_once('x') {
+        Some((cols, "")) => (cols, None),
+        Some((cols, rows)) => (cols, Some(rows)),
+        None => (spec, None),
+    };
+    if cols.is_empty() {
+        // `x6` fixes only the row count, which has no equivalent here.
+        return Err(Error::Usage(
+            "-tile with rows only (x6) is not supported; give the column count".to_string(),
+        ));
+    }
+    let cols: u32 = cols.parse().map_err(|_| bad())?;
+    let rows = rows.map(|r| r.parse::<u32>().map_err(|_| bad())).transpose()?;
+    if cols == 0 || rows == Some(0) {
+        return Err(bad());
+    }
+    Ok((cols, rows))
+}
+
+/// Parses `-geometry` as `[WxH][+X+Y]`, returning the cell size (the
+/// larger of W and H) and the tile spacing (the larger of X and Y).
+fn parse_geometry(spec: &str) -> error::Result<(Option<u32>, u32)> {
+    let bad = || {
+        Error::Usage(format!(
+            "invalid -geometry {:?}; expected forms like 200x200+5+5 or +5+5",
+            spec
+        ))
+    };
+    let (size, offsets) = match spec.find(['+', '-']) {
+        Some(at) => (&spec[..at], &spec[at..]),
+        None => (spec, ""),
+    };
+    let size = match size {
+        "" => None,
+        _ => {
+            let (w, h) = size.split_once('x').ok_or_else(bad)?;
+            let w: u32 = w.parse().map_err(|_| bad())?;
+            let h: u32 = if h.is_empty() { w } else { h.parse().map_err(|_| bad())? };
+            Some(w.max(h).max(1))
+        }
+    };
+    let mut spacing = 0u32;
+    let mut rest = offsets;
+    while !rest.is_empty() {
+        let positive = rest.starts_with('+');
+        let body = &rest[1..];
+        let end = body.find(['+', '-']).unwrap_or(body.len());
+        let value: u32 = body[..end].parse().map_err(|_| bad())?;
+        // Negative offsets (overlapping tiles) have no equivalent here.
+        if positive {
+            spacing = spacing.max(value);
+        }
+        rest = &body[end..];
+    }
+    Ok((size, spacing))
+}
+
+/// Rewrites montage's `%` label escapes into --label-template fields.
+fn translate_label(template: &str) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('f') => out.push_str("{name}"),
+            Some('t') => out.push_str("{stem}"),
+            Some('e') => out.push_str("{ext}"),
+            Some('d') => out.push_str("{folder}"),
+            Some('i') | Some('p') => out.push_str("{index}"),
+            Some('w') => out.push_str("{width}"),
+            Some('h') => out.push_str("{height}"),
+            Some('%') => out.push('%'),
+            Some(other) => {
+                tracing::warn!("montage label escape %{} has no equivalent; kept as is", other);
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+    out
+}
@@ -0,0 +1,62 @@
+//! Collage-of-collages (`--nested`).
+//!
+//! Each subfolder is first rendered into its own mini-collage through
+//! the in-memory pipeline, and those mini-collages become the cells of
+//! the top-level collage, captioned with the folder names — a two-level
+//! overview of a deep library in one image. The mini grids are sized so
+//! each lands at roughly one cell's resolution before cover-fitting.
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use std::path::PathBuf;
+
+/// Builds one entry per non-empty subfolder, carrying its mini-collage
+/// as in-memory PNG bytes and the folder name as the caption.
+pub fn build_entries(subfolders: &[PathBuf], args: &crate::Args) -> error::Result<Vec<ManifestEntry>> {
+    let mut entries = Vec::new();
+    for folder in subfolders {
+        crate::cancel::check()?;
+        let mut paths = crate::images_in_folder(folder, args.lexicographic);
+        if let Some(limit) = args.limit_per_folder {
+            paths.truncate(limit);
+        }
+        if paths.is_empty() {
+            continue;
+        }
+        let mut images = Vec::with_capacity(paths.len());
+        for path in &paths {
+            match crate::source::read(path) {
+                Ok(bytes) => images.push(bytes),
+                Err(e) => {
+                    if args.strict || args.on_error == crate::OnError::Abort {
+                        return Err(Error::Io(e));
+                    }
+                    tracing::error!("Error reading {:?}: {}", path, e);
+                }
+            }
+        }
+        if images.is_empty() {
+            continue;
+        }
+        let ncols = std::cmp::max(1, (images.len() as f64).sqrt().ceil() as u32);
+        let mini = crate::wasm::render(
+            &images,
+            &crate::wasm::Options {
+                cell_size: std::cmp::max(16, args.cell_size / ncols),
+                ..Default::default()
+            },
+        )
+        .map_err(|e| Error::Usage(format!("nested collage for {:?} failed: {}", folder, e)))?;
+        tracing::info!("Rendered nested collage for {:?} ({} images)", folder, images.len());
+        let mut entry = ManifestEntry::from_path(folder.clone());
+        entry.data = Some(mini);
+        entry.caption = folder
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        entries.push(entry);
+    }
+    if entries.is_empty() {
+        return Err(Error::NoImages);
+    }
+    Ok(entries)
+}
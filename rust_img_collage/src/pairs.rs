@@ -0,0 +1,203 @@
+//! Pair comparison mode (`--pairs before after`): files in the two roots
+//! are matched by name (ignoring the extension) and each match is laid
+//! side by side, one pair per row — before/after sheets for edits,
+//! upscales and the like. An optional divider bar separates the halves
+//! and optional column headings label them. Unmatched files are logged
+//! and left out.
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use crate::summary::RunSummary;
+use crate::text;
+use memmap2::MmapMut;
+use std::cmp;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempfile;
+
+/// Recursively collects image files under `root`, keyed by file stem.
+/// The first file seen for a stem wins; later ones are logged.
+fn collect_by_stem(root: &Path, found: &mut HashMap<String, PathBuf>) -> error::Result<()> {
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_dir() {
+            collect_by_stem(&path, found)?;
+            continue;
+        }
+        let ext = path
+            .extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        if ext != "webp" && ext != "jpg" && ext != "jpeg" {
+            continue;
+        }
+        let stem = match path.file_stem() {
+            Some(stem) => stem.to_string_lossy().into_owned(),
+            None => continue,
+        };
+        match found.entry(stem) {
+            std::collections::hash_map::Entry::Vacant(slot) => {
+                slot.insert(path);
+            }
+            std::collections::hash_map::Entry::Occupied(first) => {
+                tracing::warn!("Duplicate name {:?} ignored (already have {:?})", path, first.get());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Loads and pastes one half of a pair, honouring the error policy.
+#[allow(clippy::too_many_arguments)]
+fn paste_half(
+    mmap: &mut [u8],
+    (width, height): (u32, u32),
+    (x, y): (u32, u32),
+    cell_size: u32,
+    entry: &ManifestEntry,
+    args: &crate::Args,
+    run: &mut RunSummary,
+) -> error::Result<()> {
+    match entry.load_image() {
+        Ok(img) => {
+            crate::paste_image(mmap, (width, height), (x, y, cell_size, cell_size), &img);
+        }
+        Err(e) => {
+            if args.strict || args.on_error == crate::OnError::Abort {
+                return Err(Error::Decode(entry.path.clone(), e));
+            }
+            tracing::error!("Error processing {:?}: {}", entry.path, e);
+            run.skip(&entry.path, &e);
+            if args.on_error == crate::OnError::Placeholder {
+                crate::draw_placeholder(
+                    mmap,
+                    (width, height),
+                    (x, y, cell_size, cell_size),
+                    cell_size,
+                    &entry.path,
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Renders the before/after sheet to `output_path`.
+pub fn create_pairs(
+    before_root: &str,
+    after_root: &str,
+    args: &crate::Args,
+    output_path: &str,
+    run: &mut RunSummary,
+) -> error::Result<()> {
+    let mut before = HashMap::new();
+    collect_by_stem(Path::new(before_root), &mut before)?;
+    let mut after = HashMap::new();
+    collect_by_stem(Path::new(after_root), &mut after)?;
+
+    // Match by stem and keep the usual sort order over the matched names.
+    let mut pairs: Vec<(ManifestEntry, ManifestEntry)> = Vec::new();
+    let mut stems: Vec<&String> = before.keys().filter(|s| after.contains_key(*s)).collect();
+    stems.sort_by(|a, b| crate::path_cmp(&PathBuf::from(a), &PathBuf::from(b), args.lexicographic));
+    for stem in &stems {
+        pairs.push((
+            ManifestEntry::from_path(before[*stem].clone()),
+            ManifestEntry::from_path(after[*stem].clone()),
+        ));
+    }
+    let unmatched = before.len() + after.len() - 2 * pairs.len();
+    if unmatched > 0 {
+        tracing::warn!("{} files have no counterpart and were left out", unmatched);
+    }
+    if pairs.is_empty() {
+        return Err(Error::NoImages);
+    }
+    tracing::info!("Matched pairs: {}", pairs.len());
+
+    // One pair per row: before | divider | after, headings on top.
+    let cell_size = args.cell_size;
+    let scale = cmp::max(1, cell_size / 200);
+    let headings: Vec<&str> = args
+        .labels
+        .as_deref()
+        .map(|l| l.splitn(2, ',').collect())
+        .unwrap_or_default();
+    let band_height = if headings.is_empty() {
+        0
+    } else {
+        text::line_height(scale) * 2
+    };
+    let width = cell_size * 2 + args.divider;
+    let nrows = pairs.len() as u32;
+    let height = band_height + nrows * cell_size;
+    tracing::debug!(
+        "pairs layout: {} rows, canvas {}x{} px",
+        nrows, width, height
+    );
+    run.total_images = pairs.len() * 2;
+    run.grid_cols = 2;
+    run.grid_rows = nrows;
+    run.canvas_width = width;
+    run.canvas_height = height;
+
+    let num_pixels = (width as u64 * height as u64) as usize;
+    let file = tempfile()?;
+    file.set_len(num_pixels as u64 * 4)?;
+    crate::resources::add_temp(num_pixels as u64 * 4);
+    let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+    crate::background::fill(&mut mmap, (width, height));
+
+    // Column headings, centred over their half.
+    for (i, heading) in headings.iter().enumerate() {
+        let column_center = i as i64 * (cell_size + args.divider) as i64 + cell_size as i64 / 2;
+        text::draw_text(
+            &mut mmap,
+            (width, height),
+            (
+                column_center - text::text_width(heading, scale) as i64 / 2,
+                (text::line_height(scale) / 2) as i64,
+            ),
+            scale,
+            [0, 0, 0, 255],
+            heading,
+        );
+    }
+
+    let composite_start = std::time::Instant::now();
+    for (row, (before, after)) in pairs.iter().enumerate() {
+        let y = band_height + row as u32 * cell_size;
+        paste_half(&mut mmap, (width, height), (0, y), cell_size, before, args, run)?;
+        paste_half(
+            &mut mmap,
+            (width, height),
+            (cell_size + args.divider, y),
+            cell_size,
+            after,
+            args,
+            run,
+        )?;
+    }
+
+    // The divider bar runs the full height below the headings.
+    for y in band_height..height {
+        for x in cell_size..cell_size + args.divider {
+            let index = ((y as u64 * width as u64 + x as u64) * 4) as usize;
+            mmap[index..index + 4].copy_from_slice(&[32, 32, 32, 255]);
+        }
+    }
+    mmap.flush()?;
+    run.phase_seconds
+        .insert("composite".to_string(), composite_start.elapsed().as_secs_f64());
+
+    let encode_start = std::time::Instant::now();
+    crate::save_canvas(&mmap, (width, height), args, output_path)?;
+    run.phase_seconds
+        .insert("encode".to_string(), encode_start.elapsed().as_secs_f64());
+    run.output_file = output_path.to_string();
+    run.output_bytes = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!("Pair sheet saved to '{}' ({} pairs)", output_path, pairs.len());
+    Ok(())
+}
@@ -0,0 +1,273 @@
+//! `palette` subcommand: the library's dominant colours.
+//!
+//! Every image is decoded to a small thumbnail (the same decode path as
+//! a collage build), the pooled pixels are clustered with k-means in RGB,
+//! and the result comes out two ways: a horizontal swatch image whose
+//! band widths reflect each colour's share, and JSON on stdout for
+//! scripting — e.g. feeding a colour-sorted run or theming a gallery
+//! page around the library.
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+
+/// Thumbnail edge for sampling; detail doesn't matter, colour mix does.
+const SAMPLE_SIZE: u32 = 32;
+/// K-means rounds; centroids settle well before this on photo data.
+const ITERATIONS: usize = 20;
+/// Swatch image height in pixels.
+const SWATCH_HEIGHT: u32 = 128;
+
+/// One dominant colour and its share of the sampled pixels.
+struct Swatch {
+    rgb: [u8; 3],
+    share: f64,
+}
+
+/// K-means over the pooled sample pixels. Centroids start evenly spaced
+/// along the sample (deterministic); empty clusters are dropped at the
+/// end rather than reseeded.
+fn kmeans(pixels: &[[u8; 3]], k: usize) -> Vec<Swatch> {
+    let k = k.min(pixels.len()).max(1);
+    let mut centroids: Vec<[f64; 3]> = (0..k)
+        .map(|i| {
+            let p = pixels[i * pixels.len() / k];
+            [p[0] as f64, p[1] as f64, p[2] as f64]
+        })
+        .collect();
+    let mut assignment = vec![0usize; pixels.len()];
+    for _ in 0..ITERATIONS {
+        let mut changed = false;
+        for (pi, p) in pixels.iter().enumerate() {
+            let mut best = 0;
+            let mut best_d = f64::MAX;
+            for (ci, c) in centroids.iter().enumerate() {
+                let d = (0..3)
+                    .map(|i| (p[i] as f64 - c[i]).powi(2))
+                    .sum::<f64>();
+                if d < best_d {
+                    best_d = d;
+                    best = ci;
+                }
+            }
+            if assignment[pi] != best {
+                assignment[pi] = best;
+                changed = true;
+            }
+        }
+        let mut sums = vec![[0.0f64; 3]; k];
+        let mut counts = vec![0u64; k];
+        for (pi, p) in pixels.iter().enumerate() {
+            let c = assignment[pi];
+            counts[c] += 1;
+            for i in 0..3 {
+                sums[c][i] += p[i] as f64;
+            }
+        }
+        for (c, centroid) in centroids.iter_mut().enumerate() {
+            if counts[c] > 0 {
+                for i in 0..3 {
+                    centroid[i] = sums[c][i] / counts[c] as f64;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut counts = vec![0u64; k];
+    for &c in &assignment {
+        counts[c] += 1;
+    }
+    let total = pixels.len() as f64;
+    let mut swatches: Vec<Swatch> = centroids
+        .iter()
+        .zip(&counts)
+        .filter(|(_, &count)| count > 0)
+        .map(|(c, &count)| Swatch {
+            rgb: [c[0].round() as u8, c[1].round() as u8, c[2].round() as u8],
+            share: count as f64 / total,
+        })
+        .collect();
+    swatches.sort_by(|a, b| b.share.total_cmp(&a.share));
+    swatches
+}
+
+/// Writes the palette as a horizontal swatch strip; each colour's width
+/// is proportional to its share.
+fn write_swatch(swatches: &[Swatch], width: u32, path: &str) -> error::Result<()> {
+    let mut img = image::RgbaImage::new(width, SWATCH_HEIGHT);
+    let mut x0 = 0u32;
+    for (i, swatch) in swatches.iter().enumerate() {
+        let x1 = if i + 1 == swatches.len() {
+            width
+        } else {
+            (x0 + (swatch.share * width as f64) as u32).min(width)
+        };
+        for x in x0..x1 {
+            for y in 0..SWATCH_HEIGHT {
+                img.put_pixel(x, y, image::Rgba([swatch.rgb[0], swatch.rgb[1], swatch.rgb[2], 255]));
+            }
+        }
+        x0 = x1;
+    }
+    img.save(path).map_err(|e| {
+        Error::output(path, std::io::Error::other(e.to_string()))
+    })?;
+    Ok(())
+}
+
+/// The image's single dominant colour — the largest k-means cluster
+/// over a small thumbnail — for `--cell-border dominant`.
+pub fn dominant(img: &image::DynamicImage) -> [u8; 4] {
+    let thumb = img.thumbnail(16, 16).to_rgb8();
+    let pixels: Vec<[u8; 3]> = thumb.pixels().map(|p| p.0).collect();
+    let rgb = kmeans(&pixels, 4)[0].rgb;
+    [rgb[0], rgb[1], rgb[2], 255]
+}
+
+/// The categorical palette --palette registers for automatic colour
+/// assignments (folder separators, per-folder borders). Okabe-Ito and
+/// the viridis samples are colour-blind safe; both are fixed lists, so
+/// folder N keeps its colour across runs.
+static CATEGORICAL: std::sync::OnceLock<Vec<[u8; 4]>> = std::sync::OnceLock::new();
+
+/// Okabe & Ito's eight-colour qualitative palette.
+const OKABE_ITO: [[u8; 4]; 8] = [
+    [230, 159, 0, 255],
+    [86, 180, 233, 255],
+    [0, 158, 115, 255],
+    [240, 228, 66, 255],
+    [0, 114, 178, 255],
+    [213, 94, 0, 255],
+    [204, 121, 167, 255],
+    [0, 0, 0, 255],
+];
+
+/// Eight evenly spaced samples of the viridis colormap.
+const VIRIDIS: [[u8; 4]; 8] = [
+    [68, 1, 84, 255],
+    [70, 50, 127, 255],
+    [54, 92, 141, 255],
+    [39, 127, 142, 255],
+    [31, 161, 135, 255],
+    [74, 194, 109, 255],
+    [159, 218, 58, 255],
+    [253, 231, 37, 255],
+];
+
+/// Registers --palette: `okabe-ito`, `viridis`, or
+/// `custom:#rrggbb,#rrggbb,...`.
+pub fn configure_categorical(spec: &str) -> error::Result<()> {
+    let bad = || {
+        Error::Usage(format!(
+            "invalid --palette {:?}; expected okabe-ito, viridis, or custom:#rrggbb,...",
+            spec
+        ))
+    };
+    let colors = match spec.trim().to_lowercase().as_str() {
+        "okabe-ito" => OKABE_ITO.to_vec(),
+        "viridis" => VIRIDIS.to_vec(),
+        lower => {
+            let list = lower.strip_prefix("custom:").ok_or_else(bad)?;
+            let colors: Vec<[u8; 4]> = list
+                .split(',')
+                .map(|c| crate::background::parse_color(c.trim()))
+                .collect::<Option<_>>()
+                .ok_or_else(bad)?;
+            if colors.is_empty() {
+                return Err(bad());
+            }
+            colors
+        }
+    };
+    let _ = CATEGORICAL.set(colors);
+    Ok(())
+}
+
+/// The categorical colour for slot `index`, cycling through the
+/// registered palette; None until --palette configures one.
+pub fn categorical(index: usize) -> Option<[u8; 4]> {
+    CATEGORICAL.get().map(|colors| colors[index % colors.len()])
+}
+
+/// Folders in first-seen order; each keeps its palette slot for the
+/// whole run, whichever page or thread asks first.
+static FOLDERS: std::sync::Mutex<Vec<std::path::PathBuf>> = std::sync::Mutex::new(Vec::new());
+
+/// The palette colour of the image's parent folder; None until
+/// --palette configures one. Scan order is stable, so the assignment
+/// repeats across runs.
+pub fn folder_color(path: &std::path::Path) -> Option<[u8; 4]> {
+    CATEGORICAL.get()?;
+    let folder = path.parent().unwrap_or(std::path::Path::new("")).to_path_buf();
+    let mut folders = FOLDERS.lock().unwrap();
+    let index = folders.iter().position(|f| *f == folder).unwrap_or_else(|| {
+        folders.push(folder);
+        folders.len() - 1
+    });
+    categorical(index)
+}
+
+/// Runs the `palette` subcommand: sample, cluster, report.
+pub fn run_palette(
+    args: &crate::Args,
+    input: &str,
+    colors: usize,
+    swatch_path: Option<&str>,
+) -> error::Result<()> {
+    if colors == 0 {
+        return Err(Error::Usage("--colors must be at least 1".to_string()));
+    }
+    let (paths, _) = crate::get_sorted_image_paths(input, args.max_images, args.lexicographic)?;
+    if paths.is_empty() {
+        return Err(Error::NoImages);
+    }
+
+    let mut pixels: Vec<[u8; 3]> = Vec::new();
+    let mut sampled = 0usize;
+    for path in &paths {
+        crate::cancel::check()?;
+        let entry = ManifestEntry::from_path(path.clone());
+        match entry.load_image() {
+            Ok(img) => {
+                let thumb = img.thumbnail(SAMPLE_SIZE, SAMPLE_SIZE).to_rgb8();
+                pixels.extend(thumb.pixels().map(|p| p.0));
+                sampled += 1;
+            }
+            Err(e) => {
+                if args.strict || args.on_error == crate::OnError::Abort {
+                    return Err(Error::Decode(path.clone(), e));
+                }
+                tracing::error!("Error processing {:?}: {}", path, e);
+            }
+        }
+    }
+    if pixels.is_empty() {
+        return Err(Error::NoImages);
+    }
+    tracing::info!("Sampled {} pixels from {} images", pixels.len(), sampled);
+
+    let swatches = kmeans(&pixels, colors);
+    let rows: Vec<serde_json::Value> = swatches
+        .iter()
+        .map(|s| {
+            serde_json::json!({
+                "hex": format!("#{:02x}{:02x}{:02x}", s.rgb[0], s.rgb[1], s.rgb[2]),
+                "rgb": [s.rgb[0], s.rgb[1], s.rgb[2]],
+                "share": (s.share * 1000.0).round() / 1000.0,
+            })
+        })
+        .collect();
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&serde_json::json!({ "images": sampled, "palette": rows }))
+            .expect("palette serializes")
+    );
+
+    if let Some(path) = swatch_path {
+        write_swatch(&swatches, SWATCH_HEIGHT * swatches.len() as u32, path)?;
+        tracing::info!("Palette swatch saved to '{}'", path);
+    }
+    Ok(())
+}
@@ -0,0 +1,223 @@
+//! Multi-threaded compositing (`--threads`).
+//!
+//! The serial loop holds `&mut mmap` for the whole run, so decoding and
+//! pasting serialize even on many-core machines. Here the canvas is
+//! partitioned into disjoint row bands — maximal horizontal strips that
+//! no cell crosses, so spanned cells merge their rows — and the bands
+//! are handed to worker threads via `split_at_mut`. Each worker decodes
+//! and pastes only the cells inside its bands; no two threads ever see
+//! the same pixels, so there is no locking and no unsafe aliasing.
+//!
+//! Rotated tiles paint outside their cell and would cross band borders,
+//! so `create_collage` falls back to the serial loop when rotation is in
+//! play (and with `--resume`, whose journal wants loop order).
+//!
+//! The calling thread's cancellation token and progress observer are
+//! captured before the workers start and shared with them, so server
+//! jobs cancel and report progress the same as on the serial path.
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use crate::progress;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// What the workers did, merged for the run summary.
+pub struct Outcome {
+    /// Cells composited.
+    pub pasted: usize,
+    /// Decode failures, in entry order.
+    pub skipped: Vec<(std::path::PathBuf, image::ImageError)>,
+}
+
+/// One band: a range of canvas pixel rows plus the entries whose cells
+/// sit entirely inside it.
+struct Band {
+    rows: Range<u32>,
+    tasks: Vec<usize>,
+}
+
+/// Builds the disjoint bands: entry intervals sorted by top edge, then
+/// merged wherever they touch or overlap.
+fn plan_bands(rects: &[&crate::CellRect], cell_size: u32) -> Vec<Band> {
+    let mut order: Vec<usize> = (0..rects.len()).collect();
+    order.sort_by_key(|&i| rects[i].row);
+    let mut bands: Vec<Band> = Vec::new();
+    for index in order {
+        let top = rects[index].row * cell_size;
+        let bottom = (rects[index].row + rects[index].span_h) * cell_size;
+        match bands.last_mut() {
+            Some(band) if top < band.rows.end => {
+                band.rows.end = band.rows.end.max(bottom);
+                band.tasks.push(index);
+            }
+            _ => bands.push(Band {
+                rows: top..bottom,
+                tasks: vec![index],
+            }),
+        }
+    }
+    bands
+}
+
+/// Composites every entry into `buf` using `jobs` worker threads. The
+/// --strict / --on-error abort policies (and cancellation) stop the
+/// remaining workers at the next cell and surface the first failure.
+pub fn composite(
+    buf: &mut [u8],
+    (width, height): (u32, u32),
+    cell_size: u32,
+    entries: &[ManifestEntry],
+    rects: &[&crate::CellRect],
+    args: &crate::Args,
+    jobs: usize,
+) -> error::Result<Outcome> {
+    let cell_border = match args.cell_border.as_deref() {
+        Some(spec) => Some(crate::parse_cell_border(spec)?),
+        None => None,
+    };
+    let bands = plan_bands(rects, cell_size);
+    tracing::debug!("parallel composite: {} bands across {} workers", bands.len(), jobs);
+    let token = crate::cancel::token();
+    let observer = progress::get();
+
+    // Carve the canvas into one &mut slice per band, top to bottom.
+    let mut slices: Vec<(&Band, &mut [u8])> = Vec::with_capacity(bands.len());
+    let mut rest = buf;
+    let mut carved = 0u32;
+    for band in &bands {
+        let skip = ((band.rows.start - carved) as usize) * width as usize * 4;
+        let take = ((band.rows.end - band.rows.start) as usize) * width as usize * 4;
+        let (_, tail) = rest.split_at_mut(skip);
+        let (slice, tail) = tail.split_at_mut(take);
+        slices.push((band, slice));
+        rest = tail;
+        carved = band.rows.end;
+    }
+    debug_assert!(carved <= height);
+
+    let abort = AtomicBool::new(false);
+    let failure: Mutex<Option<Error>> = Mutex::new(None);
+    let outcomes: Mutex<Vec<(usize, Result<(), image::ImageError>)>> =
+        Mutex::new(Vec::with_capacity(entries.len()));
+
+    std::thread::scope(|scope| {
+        let mut per_worker: Vec<Vec<(&Band, &mut [u8])>> =
+            (0..jobs).map(|_| Vec::new()).collect();
+        for (at, band_slice) in slices.into_iter().enumerate() {
+            per_worker[at % jobs].push(band_slice);
+        }
+        for worker in per_worker {
+            scope.spawn(|| {
+                for (band, slice) in worker {
+                    let band_h = band.rows.end - band.rows.start;
+                    for &index in &band.tasks {
+                        if abort.load(Ordering::Relaxed) {
+                            return;
+                        }
+                        if token.as_ref().is_some_and(|t| t.load(Ordering::Relaxed)) {
+                            abort.store(true, Ordering::Relaxed);
+                            let mut slot = failure.lock().unwrap();
+                            if slot.is_none() {
+                                *slot = Some(Error::Cancelled);
+                            }
+                            return;
+                        }
+                        let image_start = std::time::Instant::now();
+                        let (entry, rect) = (&entries[index], &rects[index]);
+                        let cell = (
+                            rect.col * cell_size,
+                            rect.row * cell_size - band.rows.start,
+                            rect.span_w * cell_size,
+                            rect.span_h * cell_size,
+                        );
+                        match entry.load_image() {
+                            Ok(img) => {
+                                crate::paste_image(slice, (width, band_h), cell, &img);
+                                // Border first, then caption, matching the
+                                // serial loop: captions stay legible over a
+                                // thick border.
+                                if let Some((px, color)) = cell_border {
+                                    let color = match color {
+                                        crate::CellBorderColor::Fixed(c) => c,
+                                        crate::CellBorderColor::Dominant => crate::palette::dominant(&img),
+                                        crate::CellBorderColor::Folder => {
+                                            crate::palette::folder_color(&entry.path)
+                                                .unwrap_or([128, 128, 128, 255])
+                                        }
+                                    };
+                                    crate::draw_cell_border(slice, (width, band_h), cell, px, color);
+                                }
+                                if let Some(caption) = &entry.caption {
+                                    crate::draw_caption(slice, (width, band_h), cell, cell_size, args, caption);
+                                }
+                                if args.number_cells {
+                                    let base = crate::NUMBER_BASE.load(Ordering::Relaxed);
+                                    crate::draw_cell_number(
+                                        slice, (width, band_h),
+                                        (cell.0, cell.1),
+                                        cell_size,
+                                        base + index + 1,
+                                    );
+                                }
+                                if let Some(observer) = &observer {
+                                    observer.on_event(progress::Event::ImageDone {
+                                        index,
+                                        total: entries.len(),
+                                        path: &entry.path,
+                                        seconds: image_start.elapsed().as_secs_f64(),
+                                    });
+                                }
+                                outcomes.lock().unwrap().push((index, Ok(())));
+                            }
+                            Err(e) => {
+                                if args.strict || args.on_error == crate::OnError::Abort {
+                                    abort.store(true, Ordering::Relaxed);
+                                    let mut slot = failure.lock().unwrap();
+                                    if slot.is_none() {
+                                        *slot = Some(Error::Decode(entry.path.clone(), e));
+                                    }
+                                    return;
+                                }
+                                tracing::error!("Error processing {:?}: {}", entry.path, e);
+                                if args.on_error == crate::OnError::Placeholder {
+                                    crate::draw_placeholder(slice, (width, band_h), cell, cell_size, &entry.path);
+                                }
+                                // Skipped cells keep their number so the
+                                // count stays aligned with the manifest.
+                                if args.number_cells {
+                                    let base = crate::NUMBER_BASE.load(Ordering::Relaxed);
+                                    crate::draw_cell_number(
+                                        slice, (width, band_h),
+                                        (cell.0, cell.1),
+                                        cell_size,
+                                        base + index + 1,
+                                    );
+                                }
+                                outcomes.lock().unwrap().push((index, Err(e)));
+                            }
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    if let Some(error) = failure.into_inner().unwrap() {
+        return Err(error);
+    }
+    let mut outcomes = outcomes.into_inner().unwrap();
+    outcomes.sort_by_key(|(index, _)| *index);
+    let mut result = Outcome {
+        pasted: 0,
+        skipped: Vec::new(),
+    };
+    for (index, outcome) in outcomes {
+        match outcome {
+            Ok(()) => result.pasted += 1,
+            Err(e) => result.skipped.push((entries[index].path.clone(), e)),
+        }
+    }
+    Ok(result)
+}
@@ -0,0 +1,92 @@
+//! PDF input via page rendering (`--features pdf`).
+//!
+//! Scanned archives are folders of PDFs, not images. With the `pdf`
+//! feature each PDF contributes its first page as a cell — or every
+//! page with `--pdf-all-pages` — rendered by shelling out to poppler's
+//! `pdftoppm` binary (and `pdfinfo` for page counts) rather than
+//! linking a PDF library. Pages are rasterized at the cell resolution
+//! so dense documents stay legible when zoomed.
+
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+use crate::error;
+use crate::manifest::ManifestEntry;
+
+static CELL_SIZE: OnceLock<u32> = OnceLock::new();
+
+/// Registers the target raster resolution from --cell-size. Call once
+/// before rendering starts; later calls are ignored.
+pub fn configure(cell_size: u32) {
+    let _ = CELL_SIZE.set(cell_size);
+}
+
+/// Whether this extension belongs to a PDF file.
+pub fn is_pdf_ext(ext: &str) -> bool {
+    ext == "pdf"
+}
+
+/// Whether the path looks like a PDF file.
+pub fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| is_pdf_ext(&ext.to_lowercase()))
+}
+
+/// The number of pages in the document, via pdfinfo.
+fn page_count(path: &Path) -> io::Result<usize> {
+    let output = Command::new("pdfinfo").arg(path).output().map_err(|e| {
+        io::Error::other(format!("cannot run pdfinfo (is poppler installed?): {}", e))
+    })?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("pdfinfo failed for {:?}", path)));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("Pages:"))
+        .and_then(|rest| rest.trim().parse().ok())
+        .ok_or_else(|| io::Error::other(format!("no page count for {:?}", path)))
+}
+
+/// Renders one page (1-based) as PNG bytes, decoded by the normal
+/// image pipeline afterwards.
+pub fn render_page(path: &Path, page: usize) -> io::Result<Vec<u8>> {
+    let cell = CELL_SIZE.get().copied().unwrap_or(512);
+    let page = page.to_string();
+    let output = Command::new("pdftoppm")
+        .args(["-png", "-f", &page, "-l", &page, "-scale-to", &cell.to_string()])
+        .arg(path)
+        .output()
+        .map_err(|e| {
+            io::Error::other(format!("cannot run pdftoppm (is poppler installed?): {}", e))
+        })?;
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(io::Error::other(format!(
+            "pdftoppm could not render page {} of {:?}",
+            page, path
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Replaces each PDF entry with one in-memory entry per page
+/// (--pdf-all-pages). Non-PDF entries pass through untouched.
+pub fn expand(entries: &mut Vec<ManifestEntry>) -> error::Result<()> {
+    let mut expanded = Vec::with_capacity(entries.len());
+    for entry in entries.drain(..) {
+        if entry.data.is_some() || !is_pdf(&entry.path) {
+            expanded.push(entry);
+            continue;
+        }
+        let pages = page_count(&entry.path)?;
+        for page in 1..=pages {
+            let mut per_page = entry.clone();
+            per_page.data = Some(render_page(&entry.path, page)?);
+            expanded.push(per_page);
+        }
+    }
+    *entries = expanded;
+    Ok(())
+}
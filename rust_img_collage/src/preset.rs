@@ -0,0 +1,78 @@
+//! Bundled option presets (`--preset`).
+//!
+//! A preset fills in a curated combination of flags before the run
+//! starts, so one switch produces a finished artefact. Explicit flags
+//! always win: the preset only touches options the user left unset.
+//! `year-review` turns a year's dump of photos into a shareable annual
+//! summary — month sections in chronological order, a title banner, and
+//! a stats footer computed from the images themselves.
+
+use crate::date;
+use crate::manifest::ManifestEntry;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// The computed stats line, filled per render so --per-folder batches
+/// get fresh numbers.
+static STATS: Mutex<Option<String>> = Mutex::new(None);
+
+/// Applies the chosen preset to the parsed arguments.
+pub fn apply(args: &mut crate::Args) {
+    match args.preset {
+        Some(crate::Preset::YearReview) => {
+            if args.group_by.is_none() {
+                args.group_by = Some(crate::GroupBy::Month);
+            }
+            if args.page_header.is_none() {
+                args.page_header = Some("{title}".to_string());
+            }
+            if args.page_footer.is_none() {
+                args.stats_footer = true;
+            }
+        }
+        None => {}
+    }
+}
+
+/// Computes and stores the stats footer for this render.
+pub fn set_stats(entries: &[ManifestEntry]) {
+    *STATS.lock().unwrap() = Some(stats_line(entries));
+}
+
+/// The stats line computed for the current render, if any.
+pub fn stats() -> Option<String> {
+    STATS.lock().unwrap().clone()
+}
+
+/// One line of library stats: photo count, busiest capture day, and the
+/// cameras that took them.
+fn stats_line(entries: &[ManifestEntry]) -> String {
+    let mut days: BTreeMap<date::Day, usize> = BTreeMap::new();
+    let mut cameras: Vec<String> = Vec::new();
+    for entry in entries {
+        if let Some(day) = date::capture_day(entry) {
+            *days.entry(day).or_default() += 1;
+        }
+        if let Some(camera) = date::exif_summary(entry).camera {
+            if !cameras.contains(&camera) {
+                cameras.push(camera);
+            }
+        }
+    }
+    let mut line = format!("{} photos", entries.len());
+    if let Some(((y, m, d), count)) = days.iter().max_by_key(|(_, count)| **count) {
+        line.push_str(&format!(
+            " - busiest day {:04}-{:02}-{:02} ({} photos)",
+            y, m, d, count
+        ));
+    }
+    if !cameras.is_empty() {
+        let extra = cameras.len().saturating_sub(3);
+        cameras.truncate(3);
+        line.push_str(&format!(" - {}", cameras.join(", ")));
+        if extra > 0 {
+            line.push_str(&format!(" +{} more", extra));
+        }
+    }
+    line
+}
@@ -0,0 +1,83 @@
+//! Native preview window (`--preview`, `--features preview`).
+//!
+//! After a render the finished collage opens in a lightweight minifb
+//! window, downscaled to fit the screen, so the result can be eyeballed
+//! without hunting for the output file. Space re-rolls seeded layouts —
+//! the seed bumps by one and the whole pipeline runs again — and Enter
+//! or Escape accepts what's on screen.
+
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+
+use crate::error::{self, Error};
+
+/// The window never exceeds this size; larger collages are downscaled.
+const MAX_WINDOW: (u32, u32) = (1280, 800);
+
+/// Shows the rendered output and re-renders on demand until a verdict
+/// key arrives. The first render has already happened when this runs.
+pub fn rerun_loop(args: &mut crate::Args) -> error::Result<()> {
+    let output = args
+        .output_file
+        .clone()
+        .or_else(|| args.input_dir.clone())
+        .ok_or_else(|| Error::Usage("missing output file argument".to_string()))?;
+    if output.contains("{folder}") {
+        return Err(Error::Usage(
+            "--preview cannot follow --per-folder outputs".to_string(),
+        ));
+    }
+    let open = |width: u32, height: u32| {
+        Window::new(
+            &format!("{} — space re-rolls, enter accepts", output),
+            width as usize,
+            height as usize,
+            WindowOptions::default(),
+        )
+        .map_err(|e| Error::Usage(format!("cannot open a preview window: {}", e)))
+    };
+    let (mut pixels, (mut width, mut height)) = load_scaled(&output)?;
+    let mut window = open(width, height)?;
+    while window.is_open() {
+        if window.is_key_pressed(Key::Enter, KeyRepeat::No)
+            || window.is_key_pressed(Key::Escape, KeyRepeat::No)
+            || window.is_key_pressed(Key::Q, KeyRepeat::No)
+        {
+            break;
+        }
+        if window.is_key_pressed(Key::Space, KeyRepeat::No)
+            || window.is_key_pressed(Key::R, KeyRepeat::No)
+        {
+            args.seed += 1;
+            tracing::info!("Re-rolling with seed {}", args.seed);
+            crate::run(args)?;
+            let reloaded = load_scaled(&output)?;
+            let dims = reloaded.1;
+            pixels = reloaded.0;
+            // A different arrangement can change the canvas size, and
+            // the buffer has to match the window exactly.
+            if dims != (width, height) {
+                (width, height) = dims;
+                window = open(width, height)?;
+            }
+        }
+        window
+            .update_with_buffer(&pixels, width as usize, height as usize)
+            .map_err(|e| Error::Usage(format!("preview window lost: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Loads the output downscaled to the window cap, as minifb's 0RGB
+/// words.
+fn load_scaled(output: &str) -> error::Result<(Vec<u32>, (u32, u32))> {
+    let img = image::open(output)
+        .map_err(|e| Error::output(output, e))?
+        .thumbnail(MAX_WINDOW.0, MAX_WINDOW.1)
+        .to_rgba8();
+    let (width, height) = img.dimensions();
+    let pixels = img
+        .pixels()
+        .map(|p| (p[0] as u32) << 16 | (p[1] as u32) << 8 | p[2] as u32)
+        .collect();
+    Ok((pixels, (width, height)))
+}
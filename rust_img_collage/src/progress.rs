@@ -0,0 +1,56 @@
+//! Progress events for embedders.
+//!
+//! The compositing loop emits an [`Event`] per step; an embedder (the
+//! server's job table, a GUI) installs a [`ProgressObserver`] on the
+//! build thread — the same per-thread arrangement as [`crate::cancel`]
+//! — and drives its own progress UI from the events instead of scraping
+//! the log output. The CLI installs none and keeps its tracing output.
+
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::Arc;
+
+/// One step of a build.
+pub enum Event<'a> {
+    /// The entry list is final, with this many images to composite.
+    Scanned(usize),
+    /// One image was composited into its cell.
+    ImageDone {
+        index: usize,
+        total: usize,
+        path: &'a Path,
+        seconds: f64,
+    },
+    /// Compositing finished; the canvas is being encoded.
+    Encoding,
+}
+
+/// Receives build progress. Implementations are called from the
+/// compositing loop, so they should be cheap and must not block.
+pub trait ProgressObserver: Send + Sync {
+    fn on_event(&self, event: Event<'_>);
+}
+
+thread_local! {
+    static OBSERVER: RefCell<Option<Arc<dyn ProgressObserver>>> = const { RefCell::new(None) };
+}
+
+/// Installs `observer` for builds on the current thread.
+pub fn set(observer: Arc<dyn ProgressObserver>) {
+    OBSERVER.with(|slot| *slot.borrow_mut() = Some(observer));
+}
+
+/// The current thread's observer, for handing to worker threads that
+/// composite on its behalf (see [`crate::parallel`]).
+pub fn get() -> Option<Arc<dyn ProgressObserver>> {
+    OBSERVER.with(|slot| slot.borrow().clone())
+}
+
+/// Delivers `event` to the current thread's observer, if any.
+pub fn emit(event: Event<'_>) {
+    OBSERVER.with(|slot| {
+        if let Some(observer) = slot.borrow().as_ref() {
+            observer.on_event(event);
+        }
+    });
+}
@@ -0,0 +1,52 @@
+//! QR-code tiles (`--qr-tile`).
+//!
+//! Each `--qr-tile URL` becomes one grid cell carrying a scannable QR
+//! code, so a printed collage can link back to the online album. The
+//! code is rendered at cell resolution into an in-memory PNG and flows
+//! through the pipeline as an ordinary manifest entry, which keeps
+//! spans, pagination and the parallel compositor none the wiser.
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use qrcode::{Color, QrCode};
+use std::path::PathBuf;
+
+/// Quiet-zone width around the code, in modules (the spec asks for 4).
+const QUIET_MODULES: u32 = 4;
+
+/// Renders `url` as a QR tile sized for `cell_size` cells. The entry's
+/// path is a synthetic `qr:` marker for logs and placeholders.
+pub fn tile_entry(url: &str, cell_size: u32) -> error::Result<ManifestEntry> {
+    let code = QrCode::new(url.as_bytes())
+        .map_err(|e| Error::Usage(format!("cannot encode --qr-tile {:?}: {}", url, e)))?;
+    let modules = code.width() as u32;
+    let colors = code.to_colors();
+
+    // Whole pixels per module, never below 1; small cells just get a
+    // denser code that still scans at print resolution.
+    let scale = (cell_size / (modules + 2 * QUIET_MODULES)).max(1);
+    let side = (modules + 2 * QUIET_MODULES) * scale;
+    let mut img = image::RgbaImage::from_pixel(side, side, image::Rgba([255, 255, 255, 255]));
+    for (i, color) in colors.iter().enumerate() {
+        if *color != Color::Dark {
+            continue;
+        }
+        let mx = (i as u32 % modules + QUIET_MODULES) * scale;
+        let my = (i as u32 / modules + QUIET_MODULES) * scale;
+        for y in 0..scale {
+            for x in 0..scale {
+                img.put_pixel(mx + x, my + y, image::Rgba([0, 0, 0, 255]));
+            }
+        }
+    }
+
+    let mut bytes = Vec::new();
+    image::DynamicImage::ImageRgba8(img)
+        .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+        .map_err(|e| Error::Usage(format!("cannot render --qr-tile {:?}: {}", url, e)))?;
+
+    let mut entry = ManifestEntry::from_path(PathBuf::from(format!("qr:{}", url)));
+    entry.data = Some(bytes);
+    entry.url = Some(url.to_string());
+    Ok(entry)
+}
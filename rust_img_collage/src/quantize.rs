@@ -0,0 +1,169 @@
+//! Palette quantization for the output (--quantize/--dither).
+//!
+//! E-ink displays and retro-style exports want indexed color: the
+//! canvas is reduced to at most 256 RGBA palette entries by median cut,
+//! each pixel remapped to its nearest entry with the chosen dithering.
+//! PNG outputs are then written as true PNG8 (palette plus tRNS for
+//! alpha); the other containers keep their truecolor encoding, which
+//! still compresses drastically better once the colors collapse.
+
+use std::io::BufWriter;
+
+use crate::error::{self, Error};
+
+/// Cap on the median-cut sample set; beyond this, pixels are strided.
+const MAX_SAMPLES: usize = 1 << 16;
+
+/// Classic 4x4 Bayer matrix for ordered dithering, values 0-15.
+const BAYER: [[i32; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Quantizes the canvas in place and returns the palette and one index
+/// per pixel, for containers that can store them directly.
+pub fn apply(
+    buf: &mut [u8],
+    (width, height): (u32, u32),
+    colors: usize,
+    dither: crate::Dither,
+) -> (Vec<[u8; 4]>, Vec<u8>) {
+    let palette = median_cut(buf, colors);
+    let num_pixels = width as usize * height as usize;
+    let mut indices = vec![0u8; num_pixels];
+    // Floyd-Steinberg pushes each pixel's rounding error onto its
+    // unvisited neighbours; two rows of running error are enough.
+    let mut error_rows = vec![[0f32; 4]; 2 * width as usize];
+    for y in 0..height as usize {
+        let (current, next) = error_rows.split_at_mut(width as usize);
+        next.fill([0.0; 4]);
+        for x in 0..width as usize {
+            let at = (y * width as usize + x) * 4;
+            let mut wanted = [0f32; 4];
+            for c in 0..4 {
+                wanted[c] = buf[at + c] as f32
+                    + match dither {
+                        crate::Dither::FloydSteinberg => current[x][c],
+                        // The Bayer threshold wobbles each pixel around
+                        // its true value so flat areas break into
+                        // patterns instead of bands.
+                        crate::Dither::Ordered => {
+                            (BAYER[y % 4][x % 4] as f32 / 16.0 - 0.5) * 32.0
+                        }
+                        crate::Dither::None => 0.0,
+                    };
+            }
+            let index = nearest(&palette, wanted);
+            indices[y * width as usize + x] = index as u8;
+            let chosen = palette[index];
+            if let crate::Dither::FloydSteinberg = dither {
+                for c in 0..4 {
+                    let err = wanted[c] - chosen[c] as f32;
+                    if x + 1 < width as usize {
+                        current[x + 1][c] += err * 7.0 / 16.0;
+                        next[x + 1][c] += err * 1.0 / 16.0;
+                    }
+                    if x > 0 {
+                        next[x - 1][c] += err * 3.0 / 16.0;
+                    }
+                    next[x][c] += err * 5.0 / 16.0;
+                }
+            }
+            buf[at..at + 4].copy_from_slice(&chosen);
+        }
+        error_rows.rotate_left(width as usize);
+    }
+    (palette, indices)
+}
+
+/// Writes an indexed PNG8: the palette as PLTE, its alphas as tRNS.
+pub fn write_png8(
+    output_path: &str,
+    (width, height): (u32, u32),
+    palette: &[[u8; 4]],
+    indices: &[u8],
+) -> error::Result<()> {
+    let file = std::fs::File::create(output_path).map_err(|e| Error::output(output_path, e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(palette.iter().flat_map(|c| c[..3].to_vec()).collect::<Vec<u8>>());
+    encoder.set_trns(palette.iter().map(|c| c[3]).collect::<Vec<u8>>());
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(indices))
+        .map_err(|e| Error::output(output_path, e))?;
+    Ok(())
+}
+
+/// Builds a palette of at most `colors` entries by median cut: starting
+/// from one box around every sampled pixel, the box with the widest
+/// channel range is split at its median until enough boxes exist, and
+/// each box averages into one entry.
+fn median_cut(buf: &[u8], colors: usize) -> Vec<[u8; 4]> {
+    let step = (buf.len() / 4 / MAX_SAMPLES).max(1);
+    let samples: Vec<[u8; 4]> = buf
+        .chunks_exact(4)
+        .step_by(step)
+        .map(|p| [p[0], p[1], p[2], p[3]])
+        .collect();
+    let mut boxes = vec![samples];
+    while boxes.len() < colors {
+        // Split the box with the widest single-channel range; when every
+        // box is a solid color the palette is already exact.
+        let Some((which, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, b)| widest_channel(b).map(|(c, range)| (i, c, range)))
+            .max_by_key(|&(_, _, range)| range)
+            .map(|(i, c, _)| (i, c))
+        else {
+            break;
+        };
+        let mut splitting = boxes.swap_remove(which);
+        splitting.sort_unstable_by_key(|p| p[channel]);
+        let upper = splitting.split_off(splitting.len() / 2);
+        boxes.push(splitting);
+        boxes.push(upper);
+    }
+    boxes
+        .iter()
+        .filter(|b| !b.is_empty())
+        .map(|b| {
+            let mut sum = [0u64; 4];
+            for p in b {
+                for c in 0..4 {
+                    sum[c] += p[c] as u64;
+                }
+            }
+            [0, 1, 2, 3].map(|c| (sum[c] / b.len() as u64) as u8)
+        })
+        .collect()
+}
+
+/// The channel with the largest value range in the box, if any channel
+/// varies at all.
+fn widest_channel(samples: &[[u8; 4]]) -> Option<(usize, u8)> {
+    (0..4)
+        .map(|c| {
+            let min = samples.iter().map(|p| p[c]).min().unwrap_or(0);
+            let max = samples.iter().map(|p| p[c]).max().unwrap_or(0);
+            (c, max - min)
+        })
+        .filter(|&(_, range)| range > 0)
+        .max_by_key(|&(_, range)| range)
+}
+
+/// The palette index closest to the wanted color, by squared distance.
+fn nearest(palette: &[[u8; 4]], wanted: [f32; 4]) -> usize {
+    let mut best = (0, f32::MAX);
+    for (i, entry) in palette.iter().enumerate() {
+        let dist: f32 = (0..4)
+            .map(|c| {
+                let d = wanted[c] - entry[c] as f32;
+                d * d
+            })
+            .sum();
+        if dist < best.1 {
+            best = (i, dist);
+        }
+    }
+    best.0
+}
@@ -0,0 +1,148 @@
+//! Radial layout (`--layout radial`): a featured image in the middle and
+//! the rest in concentric rings around it, shrinking with each ring. The
+//! centre image is picked with `--center <pattern>` (same globs as
+//! --order) and defaults to the first entry.
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use crate::summary::RunSummary;
+use memmap2::MmapMut;
+use std::f64::consts::TAU;
+use tempfile::tempfile;
+
+/// Shrink factor applied to the image size for each successive ring.
+const RING_SHRINK: f64 = 0.8;
+/// Spacing factor along a ring (1.0 would butt images edge to edge).
+const RING_SPACING: f64 = 1.15;
+
+/// One placed image: centre position and square edge length.
+struct Placement {
+    x: f64,
+    y: f64,
+    size: f64,
+}
+
+/// Plans the centre and rings, returning placements (one per entry, centre
+/// first) relative to the canvas centre, plus the needed canvas radius.
+fn plan_rings(count: usize, cell_size: u32) -> (Vec<Placement>, f64) {
+    let mut placements = Vec::with_capacity(count);
+    let center_size = cell_size as f64 * 2.0;
+    placements.push(Placement { x: 0.0, y: 0.0, size: center_size });
+
+    let gap = cell_size as f64 * 0.1;
+    let mut remaining = count - 1;
+    let mut ring = 1u32;
+    let mut size = cell_size as f64;
+    let mut radius = center_size / 2.0 + gap + size / 2.0;
+    while remaining > 0 {
+        let capacity = ((TAU * radius) / (size * RING_SPACING)).floor().max(1.0) as usize;
+        let in_ring = capacity.min(remaining);
+        // Offset alternate rings by half a slot so seams don't line up.
+        let offset = if ring.is_multiple_of(2) { 0.5 } else { 0.0 };
+        for i in 0..in_ring {
+            let angle = TAU * (i as f64 + offset) / in_ring as f64;
+            placements.push(Placement {
+                x: radius * angle.cos(),
+                y: radius * angle.sin(),
+                size,
+            });
+        }
+        remaining -= in_ring;
+        let next_size = (size * RING_SHRINK).max(32.0);
+        radius += size / 2.0 + gap + next_size / 2.0;
+        size = next_size;
+        ring += 1;
+    }
+    let outer = placements
+        .iter()
+        .map(|p| (p.x.powi(2) + p.y.powi(2)).sqrt() + p.size / 2.0)
+        .fold(0.0f64, f64::max);
+    (placements, outer + gap)
+}
+
+/// Renders the radial collage to `output_path`.
+pub fn create_radial(
+    entries: &[ManifestEntry],
+    args: &crate::Args,
+    output_path: &str,
+    run: &mut RunSummary,
+) -> error::Result<()> {
+    if entries.is_empty() {
+        return Err(Error::NoImages);
+    }
+
+    // Move the centre image (first --center match) to the front.
+    let mut ordered: Vec<&ManifestEntry> = entries.iter().collect();
+    if let Some(pattern) = &args.center {
+        let found = ordered.iter().position(|entry| {
+            let path = entry.path.to_string_lossy();
+            let name = entry
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            crate::glob_match(pattern, &path)
+                || crate::glob_match(pattern, &name)
+                || crate::glob_match(&format!("*/{}", pattern), &path)
+        });
+        match found {
+            Some(index) => {
+                let center = ordered.remove(index);
+                ordered.insert(0, center);
+            }
+            None => tracing::warn!("No image matches --center {:?}; using the first", pattern),
+        }
+    }
+
+    let (placements, radius) = plan_rings(ordered.len(), args.cell_size);
+    let side = (radius * 2.0).ceil() as u32;
+    let center = side as f64 / 2.0;
+    tracing::debug!(
+        "radial layout: {} images, canvas {}x{} px",
+        ordered.len(), side, side
+    );
+    run.total_images = entries.len();
+    run.canvas_width = side;
+    run.canvas_height = side;
+
+    let num_pixels = (side as u64 * side as u64) as usize;
+    let file = tempfile()?;
+    file.set_len(num_pixels as u64 * 4)?;
+    crate::resources::add_temp(num_pixels as u64 * 4);
+    let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+    crate::background::fill(&mut mmap, (side, side));
+
+    let composite_start = std::time::Instant::now();
+    for (entry, place) in ordered.iter().zip(&placements) {
+        let size = place.size.round() as u32;
+        let x = (center + place.x - place.size / 2.0).round().max(0.0) as u32;
+        let y = (center + place.y - place.size / 2.0).round().max(0.0) as u32;
+        match entry.load_image() {
+            Ok(img) => {
+                crate::paste_image(&mut mmap, (side, side), (x, y, size, size), &img);
+            }
+            Err(e) => {
+                if args.strict || args.on_error == crate::OnError::Abort {
+                    return Err(Error::Decode(entry.path.clone(), e));
+                }
+                tracing::error!("Error processing {:?}: {}", entry.path, e);
+                run.skip(&entry.path, &e);
+                if args.on_error == crate::OnError::Placeholder {
+                    crate::draw_placeholder(&mut mmap, (side, side), (x, y, size, size), size, &entry.path);
+                }
+            }
+        }
+    }
+    mmap.flush()?;
+    run.phase_seconds
+        .insert("composite".to_string(), composite_start.elapsed().as_secs_f64());
+
+    let encode_start = std::time::Instant::now();
+    crate::save_canvas(&mmap, (side, side), args, output_path)?;
+    run.phase_seconds
+        .insert("encode".to_string(), encode_start.elapsed().as_secs_f64());
+    run.output_file = output_path.to_string();
+    run.output_bytes = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!("Radial collage saved to '{}'", output_path);
+    Ok(())
+}
@@ -0,0 +1,32 @@
+//! Camera RAW input (`--features raw`).
+//!
+//! Originals folders are mostly CR2/NEF/ARW/DNG, which the `image`
+//! crate can't open. With the `raw` feature those files are decoded and
+//! demosaiced through rawloader/imagepipe straight into the pipeline,
+//! so RAW shoots collage like any other folder. The decode is the
+//! pipeline's slowest path by far — RAW files are big and demosaicing
+//! is real work — so --threads helps here more than anywhere.
+
+use std::path::Path;
+
+/// Whether this extension belongs to a RAW format rawloader handles.
+pub fn is_raw_ext(ext: &str) -> bool {
+    matches!(ext, "cr2" | "nef" | "arw" | "dng" | "raf" | "orf" | "rw2")
+}
+
+/// Whether the path looks like a camera RAW file.
+pub fn is_raw(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| is_raw_ext(&ext.to_lowercase()))
+}
+
+/// Decodes and demosaics a RAW file at full resolution.
+pub fn decode(path: &Path) -> Result<image::DynamicImage, String> {
+    let decoded = imagepipe::simple_decode_8bit(path, 0, 0)
+        .map_err(|e| format!("RAW decode of {:?} failed: {}", path, e))?;
+    let (width, height) = (decoded.width as u32, decoded.height as u32);
+    image::RgbImage::from_raw(width, height, decoded.data)
+        .map(image::DynamicImage::ImageRgb8)
+        .ok_or_else(|| format!("RAW decode of {:?} returned a malformed buffer", path))
+}
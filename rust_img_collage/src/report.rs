@@ -0,0 +1,64 @@
+//! End-of-run audit report (`--report report.json` or `.txt`).
+//!
+//! Large automated runs silently drop inputs for many reasons — wrong
+//! extension, size and sharpness filters, exact duplicates, decode
+//! failures — and the debug log scrolls past. With --report every
+//! dropped file is collected with its reason and written out next to
+//! the collage, as JSON or plain text depending on the extension, so
+//! the run can be audited after the fact.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// One dropped input and why it was dropped.
+#[derive(serde::Serialize)]
+struct Entry {
+    path: String,
+    reason: String,
+}
+
+/// Collection only happens when --report was given; the collector is
+/// global because drops surface everywhere from the scanner down.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static ENTRIES: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+
+/// Turns collection on, registered once from --report.
+pub fn configure() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Records a dropped input; a no-op unless --report was given.
+pub fn note(path: &Path, reason: impl ToString) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    ENTRIES.lock().unwrap().push(Entry {
+        path: path.display().to_string(),
+        reason: reason.to_string(),
+    });
+}
+
+/// Writes the collected report: pretty JSON for a .json path, one
+/// `path<TAB>reason` line per file for anything else.
+pub fn write(path: &Path) -> crate::error::Result<()> {
+    let mut entries = ENTRIES.lock().unwrap();
+    // The scanner's counting pass sees the same files as the real scan;
+    // repeat notes for the same file and reason collapse to one.
+    let mut seen = std::collections::HashSet::new();
+    entries.retain(|e| seen.insert((e.path.clone(), e.reason.clone())));
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+    let text = if ext.eq_ignore_ascii_case("json") {
+        serde_json::to_string_pretty(&serde_json::json!({ "skipped": *entries })).unwrap()
+    } else {
+        let mut lines = String::new();
+        for entry in entries.iter() {
+            lines.push_str(&format!("{}\t{}\n", entry.path, entry.reason));
+        }
+        lines
+    };
+    std::fs::write(path, text)
+        .map_err(|e| crate::error::Error::output(&path.to_string_lossy(), e))?;
+    tracing::info!("Skip report ({} entries) saved to {:?}", entries.len(), path);
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! Process resource accounting (`--report-resources`).
+//!
+//! Cheap global counters fed from the hot paths: every canvas backing
+//! file adds its size here, every decode its RGBA bytes. Together with
+//! the kernel's peak-RSS figure the final report says what a scheduled
+//! collage job actually needs, which is the number container and CI
+//! memory limits get sized from.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static TEMP_BYTES: AtomicU64 = AtomicU64::new(0);
+static DECODED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Records a canvas backing file of `bytes` in the temp dir.
+pub fn add_temp(bytes: u64) {
+    TEMP_BYTES.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// Records `bytes` of decoded RGBA pixels.
+pub fn add_decoded(bytes: u64) {
+    DECODED_BYTES.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// The memory limit of the cgroup this process runs in, as set by
+/// Docker/Kubernetes: cgroup v2 `memory.max`, falling back to v1
+/// `memory.limit_in_bytes`. `max` and the kernel's no-limit sentinel
+/// read as None, as does anything that isn't Linux.
+#[cfg(target_os = "linux")]
+pub fn cgroup_memory_limit() -> Option<u64> {
+    let raw = std::fs::read_to_string("/sys/fs/cgroup/memory.max")
+        .or_else(|_| std::fs::read_to_string("/sys/fs/cgroup/memory/memory.limit_in_bytes"))
+        .ok()?;
+    let bytes: u64 = raw.trim().parse().ok()?;
+    // v1 reports "no limit" as a page-rounded near-u64::MAX value.
+    (bytes < 1 << 60).then_some(bytes)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn cgroup_memory_limit() -> Option<u64> {
+    None
+}
+
+/// Peak resident set size, if the platform can tell us.
+#[cfg(unix)]
+fn peak_rss_bytes() -> Option<u64> {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+    // ru_maxrss is KiB on Linux but bytes on macOS.
+    let scale = if cfg!(target_os = "macos") { 1 } else { 1024 };
+    Some(usage.ru_maxrss as u64 * scale)
+}
+
+#[cfg(not(unix))]
+fn peak_rss_bytes() -> Option<u64> {
+    None
+}
+
+/// Logs the resource report at the end of the run.
+pub fn report() {
+    let mib = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+    let rss = match peak_rss_bytes() {
+        Some(rss) => format!("{:.0} MiB", mib(rss)),
+        None => "unknown".to_string(),
+    };
+    tracing::info!(
+        "Resources: peak RSS {}, {:.0} MiB of temp canvases, {:.0} MiB decoded",
+        rss,
+        mib(TEMP_BYTES.load(Ordering::Relaxed)),
+        mib(DECODED_BYTES.load(Ordering::Relaxed)),
+    );
+}
@@ -0,0 +1,143 @@
+//! Rows layout (`--layout rows`): every row is `--cell-size` tall, but
+//! each image keeps its native aspect ratio — no cropping and no
+//! letterboxing, just variable cell widths. Images are packed into rows
+//! greedily against a target width chosen to keep the canvas near
+//! square, a middle ground between the uniform grid and a fully
+//! justified layout.
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use crate::summary::RunSummary;
+use image::imageops::FilterType;
+use memmap2::MmapMut;
+use tempfile::tempfile;
+
+/// One placed image: row, x offset within the row, and cell width.
+struct Slot {
+    row: u32,
+    x: u32,
+    width: u32,
+}
+
+/// Packs entry widths into rows greedily: a row closes once it reaches
+/// the target width. Returns the slots and the widest row.
+fn pack_rows(widths: &[u32], target: u32) -> (Vec<Slot>, u32) {
+    let mut slots = Vec::with_capacity(widths.len());
+    let mut row = 0u32;
+    let mut x = 0u32;
+    let mut max_width = 0u32;
+    for &width in widths {
+        // Break before adding if the row already holds something and the
+        // overshoot would be worse than starting fresh.
+        if x > 0 && x + width > target && x + width - target > target.saturating_sub(x) {
+            max_width = max_width.max(x);
+            row += 1;
+            x = 0;
+        }
+        slots.push(Slot { row, x, width });
+        x += width;
+    }
+    max_width = max_width.max(x);
+    (slots, max_width)
+}
+
+/// Renders the variable-aspect rows collage to `output_path`.
+pub fn create_rows(
+    entries: &[ManifestEntry],
+    args: &crate::Args,
+    output_path: &str,
+    run: &mut RunSummary,
+) -> error::Result<()> {
+    if entries.is_empty() {
+        return Err(Error::NoImages);
+    }
+    let row_height = args.cell_size;
+
+    // Each cell is as wide as the aspect ratio demands at the row height;
+    // unreadable headers get a square cell and fail properly later.
+    let widths: Vec<u32> = entries
+        .iter()
+        .map(|entry| match entry.dimensions() {
+            Some((w, h)) if h > 0 => {
+                ((w as f64 / h as f64) * row_height as f64).round().max(1.0) as u32
+            }
+            _ => row_height,
+        })
+        .collect();
+
+    // Aim the row width at a near-square canvas.
+    let total: u64 = widths.iter().map(|&w| w as u64).sum();
+    let target = ((total * row_height as u64) as f64).sqrt().ceil() as u32;
+    let target = target.max(*widths.iter().max().unwrap_or(&row_height));
+    let (slots, width) = pack_rows(&widths, target);
+    let nrows = slots.last().map(|s| s.row + 1).unwrap_or(1);
+    let height = nrows * row_height;
+    tracing::debug!(
+        "rows layout: {} images in {} rows, canvas {}x{} px",
+        entries.len(), nrows, width, height
+    );
+    run.total_images = entries.len();
+    run.grid_rows = nrows;
+    run.canvas_width = width;
+    run.canvas_height = height;
+
+    let num_pixels = (width as u64 * height as u64) as usize;
+    let file = tempfile()?;
+    file.set_len(num_pixels as u64 * 4)?;
+    crate::resources::add_temp(num_pixels as u64 * 4);
+    let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+    crate::background::fill(&mut mmap, (width, height));
+
+    let composite_start = std::time::Instant::now();
+    for (entry, slot) in entries.iter().zip(&slots) {
+        let y = slot.row * row_height;
+        match entry.load_image() {
+            Ok(img) => {
+                // Exact resize: the cell width was derived from the aspect
+                // ratio, so nothing is cropped or padded.
+                let resized = img
+                    .resize_exact(slot.width, row_height, FilterType::Lanczos3)
+                    .to_rgba8();
+                for (py, row_pixels) in resized.rows().enumerate() {
+                    for (px, pixel) in row_pixels.enumerate() {
+                        let tx = slot.x + px as u32;
+                        let ty = y + py as u32;
+                        if tx >= width {
+                            break;
+                        }
+                        let index = ((ty as u64 * width as u64 + tx as u64) * 4) as usize;
+                        mmap[index..index + 4].copy_from_slice(&pixel.0);
+                    }
+                }
+            }
+            Err(e) => {
+                if args.strict || args.on_error == crate::OnError::Abort {
+                    return Err(Error::Decode(entry.path.clone(), e));
+                }
+                tracing::error!("Error processing {:?}: {}", entry.path, e);
+                run.skip(&entry.path, &e);
+                if args.on_error == crate::OnError::Placeholder {
+                    crate::draw_placeholder(
+                        &mut mmap,
+                        (width, height),
+                        (slot.x, y, slot.width, row_height),
+                        row_height,
+                        &entry.path,
+                    );
+                }
+            }
+        }
+    }
+    mmap.flush()?;
+    run.phase_seconds
+        .insert("composite".to_string(), composite_start.elapsed().as_secs_f64());
+
+    let encode_start = std::time::Instant::now();
+    crate::save_canvas(&mmap, (width, height), args, output_path)?;
+    run.phase_seconds
+        .insert("encode".to_string(), encode_start.elapsed().as_secs_f64());
+    run.output_file = output_path.to_string();
+    run.output_bytes = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!("Rows collage saved to '{}' ({} rows)", output_path, nrows);
+    Ok(())
+}
@@ -0,0 +1,104 @@
+//! Optional S3 input backend (enabled with the `s3` cargo feature).
+//!
+//! Lets `s3://bucket/prefix` be passed as the input root: objects under the
+//! prefix are listed and streamed down with bounded concurrency, so server
+//! jobs don't need a local mirror of the bucket. Credentials and region come
+//! from the usual AWS environment variables / profile; `AWS_ENDPOINT_URL`
+//! can point at S3-compatible stores.
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use s3::bucket::Bucket;
+use s3::creds::Credentials;
+use s3::Region;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Splits `s3://bucket/prefix` into bucket and prefix (prefix may be empty).
+fn parse_s3_url(url: &str) -> (String, String) {
+    let rest = url.trim_start_matches("s3://");
+    match rest.split_once('/') {
+        Some((bucket, prefix)) => (bucket.to_string(), prefix.to_string()),
+        None => (rest.to_string(), String::new()),
+    }
+}
+
+/// Returns true if the object key has a supported image extension.
+fn is_image_key(key: &str) -> bool {
+    let ext = Path::new(key)
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    ext == "webp" || ext == "jpg" || ext == "jpeg"
+}
+
+/// Lists image objects under the prefix and downloads them with at most
+/// `concurrency` requests in flight, returning entries sorted by key with
+/// the object bytes held in memory.
+pub fn load_s3_entries(url: &str, concurrency: usize) -> error::Result<Vec<ManifestEntry>> {
+    let (bucket_name, prefix) = parse_s3_url(url);
+    let region = std::env::var("AWS_ENDPOINT_URL")
+        .map(|endpoint| Region::Custom {
+            region: std::env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            endpoint,
+        })
+        .or_else(|_| Region::from_default_env())
+        .map_err(|_| {
+            Error::Usage(
+                "unable to determine AWS region (set AWS_REGION or AWS_ENDPOINT_URL)".to_string(),
+            )
+        })?;
+    let credentials = Credentials::default()
+        .map_err(|e| Error::Usage(format!("unable to load AWS credentials: {}", e)))?;
+    let bucket = Bucket::new(&bucket_name, region, credentials)
+        .map_err(|e| Error::Usage(format!("unable to open s3://{}: {}", bucket_name, e)))?
+        .with_path_style();
+
+    let lists = bucket.list(prefix.clone(), None).map_err(|e| {
+        Error::Io(std::io::Error::other(format!(
+            "unable to list s3://{}/{}: {}",
+            bucket_name, prefix, e
+        )))
+    })?;
+    let mut keys: Vec<String> = lists
+        .into_iter()
+        .flat_map(|page| page.contents)
+        .map(|object| object.key)
+        .filter(|key| is_image_key(key))
+        .collect();
+    keys.sort();
+    tracing::info!("Images in bucket prefix: {}", keys.len());
+
+    // Bounded-concurrency download: workers pull the next key index from a
+    // shared counter, same scheme as the HTTP fetcher.
+    let next = AtomicUsize::new(0);
+    let results: std::sync::Mutex<Vec<(usize, Vec<u8>)>> = std::sync::Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..concurrency.clamp(1, keys.len().max(1)) {
+            scope.spawn(|| loop {
+                let job = next.fetch_add(1, Ordering::Relaxed);
+                let Some(key) = keys.get(job) else {
+                    break;
+                };
+                match bucket.get_object(key) {
+                    Ok(response) => {
+                        results.lock().unwrap().push((job, response.to_vec()));
+                    }
+                    Err(e) => tracing::warn!("Error downloading s3://{}/{}: {}", bucket_name, key, e),
+                }
+            });
+        }
+    });
+
+    let mut downloads = results.into_inner().unwrap();
+    downloads.sort_by_key(|(idx, _)| *idx);
+    Ok(downloads
+        .into_iter()
+        .map(|(idx, data)| {
+            let mut entry = ManifestEntry::from_path(PathBuf::from(&keys[idx]));
+            entry.data = Some(data);
+            entry
+        })
+        .collect())
+}
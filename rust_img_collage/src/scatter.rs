@@ -0,0 +1,161 @@
+//! Scatter layout (`--layout scatter`): tiles overlap like photos spread
+//! on a table. Tile centres sit on a lattice squeezed by `--overlap` and
+//! jittered by the run seed, and `--z-order` decides which tiles paint
+//! last — and therefore on top — so hero images aren't buried.
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use crate::summary::RunSummary;
+use memmap2::MmapMut;
+use std::cmp;
+use tempfile::tempfile;
+
+/// Bytes an entry occupies, used by `--z-order size`.
+fn entry_bytes(entry: &ManifestEntry) -> u64 {
+    match &entry.data {
+        Some(bytes) => bytes.len() as u64,
+        None => std::fs::metadata(&entry.path).map(|m| m.len()).unwrap_or(0),
+    }
+}
+
+/// Paint order (back to front) for the given policy: input order keeps
+/// later images on top, random shuffles with the seed, size puts the
+/// largest files on top.
+fn paint_order(entries: &[ManifestEntry], z_order: crate::ZOrder, seed: u64) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    match z_order {
+        crate::ZOrder::Input => {}
+        crate::ZOrder::Random => {
+            let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15).wrapping_add(1);
+            for i in 0..order.len() {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let j = i + (state % (order.len() - i) as u64) as usize;
+                order.swap(i, j);
+            }
+        }
+        crate::ZOrder::Size => {
+            order.sort_by_key(|&i| entry_bytes(&entries[i]));
+        }
+    }
+    order
+}
+
+/// Renders the scatter collage to `output_path`.
+pub fn create_scatter(
+    entries: &[ManifestEntry],
+    args: &crate::Args,
+    output_path: &str,
+    run: &mut RunSummary,
+) -> error::Result<()> {
+    if entries.is_empty() {
+        return Err(Error::NoImages);
+    }
+    if !(0.0..=90.0).contains(&args.overlap) {
+        return Err(Error::Usage("--overlap must be between 0 and 90".to_string()));
+    }
+    let cell_size = args.cell_size;
+    let n = entries.len() as u32;
+    let ncols = cmp::max(1, (n as f64).sqrt().ceil() as u32);
+    let nrows = n.div_ceil(ncols);
+
+    // Centres on a lattice squeezed by the overlap, plus seeded jitter;
+    // the margin absorbs the worst-case jitter so coordinates stay
+    // on-canvas.
+    let spacing = (cell_size as f64 * (1.0 - args.overlap / 100.0)).max(1.0);
+    let jitter = (spacing / 4.0) as u32;
+    let margin = jitter;
+    let width = ((ncols - 1) as f64 * spacing) as u32 + cell_size + 2 * margin;
+    let height = ((nrows - 1) as f64 * spacing) as u32 + cell_size + 2 * margin;
+    let mut state = args.seed.wrapping_mul(0x9E37_79B9_7F4A_7C15).wrapping_add(1);
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let positions: Vec<(u32, u32)> = (0..n)
+        .map(|k| {
+            let base_x = margin as f64 + (k % ncols) as f64 * spacing;
+            let base_y = margin as f64 + (k / ncols) as f64 * spacing;
+            let dx = (next() % (2 * jitter as u64 + 1)) as i64 - jitter as i64;
+            let dy = (next() % (2 * jitter as u64 + 1)) as i64 - jitter as i64;
+            ((base_x as i64 + dx) as u32, (base_y as i64 + dy) as u32)
+        })
+        .collect();
+    tracing::debug!(
+        "scatter layout: {} tiles at {:.0}% overlap, canvas {}x{} px",
+        n, args.overlap, width, height
+    );
+    run.total_images = entries.len();
+    run.grid_cols = ncols;
+    run.grid_rows = nrows;
+    run.canvas_width = width;
+    run.canvas_height = height;
+
+    let num_pixels = (width as u64 * height as u64) as usize;
+    let file = tempfile()?;
+    file.set_len(num_pixels as u64 * 4)?;
+    crate::resources::add_temp(num_pixels as u64 * 4);
+    let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+    crate::background::fill(&mut mmap, (width, height));
+
+    let composite_start = std::time::Instant::now();
+    for &k in &paint_order(entries, args.z_order, args.seed) {
+        let entry = &entries[k];
+        let (x, y) = positions[k];
+        match entry.load_image() {
+            Ok(img) => {
+                // Explicit manifest rotation wins over the seeded jitter.
+                let angle = entry.rotation.or_else(|| {
+                    args.rotate_jitter
+                        .map(|j| crate::rotate::jitter_angle(args.seed, k, j))
+                });
+                match angle {
+                    Some(angle) if angle != 0.0 => crate::rotate::paste_rotated(
+                        &mut mmap,
+                        (width, height),
+                        (x, y, cell_size, cell_size),
+                        angle,
+                        &img,
+                    ),
+                    _ => crate::paste_image(
+                        &mut mmap,
+                        (width, height),
+                        (x, y, cell_size, cell_size),
+                        &img,
+                    ),
+                }
+            }
+            Err(e) => {
+                if args.strict || args.on_error == crate::OnError::Abort {
+                    return Err(Error::Decode(entry.path.clone(), e));
+                }
+                tracing::error!("Error processing {:?}: {}", entry.path, e);
+                run.skip(&entry.path, &e);
+                if args.on_error == crate::OnError::Placeholder {
+                    crate::draw_placeholder(
+                        &mut mmap,
+                        (width, height),
+                        (x, y, cell_size, cell_size),
+                        cell_size,
+                        &entry.path,
+                    );
+                }
+            }
+        }
+    }
+    mmap.flush()?;
+    run.phase_seconds
+        .insert("composite".to_string(), composite_start.elapsed().as_secs_f64());
+
+    let encode_start = std::time::Instant::now();
+    crate::save_canvas(&mmap, (width, height), args, output_path)?;
+    run.phase_seconds
+        .insert("encode".to_string(), encode_start.elapsed().as_secs_f64());
+    run.output_file = output_path.to_string();
+    run.output_bytes = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!("Scatter collage saved to '{}'", output_path);
+    Ok(())
+}
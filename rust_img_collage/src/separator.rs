@@ -0,0 +1,187 @@
+//! Folder separators (`--folder-separator`): the plain grid with a thin
+//! horizontal band between the cells of different subfolders — enough to
+//! delineate albums without the full labeled section headers the
+//! timeline layout draws. Each folder's images fill rows as usual; the
+//! band (given as `PX` or `PX:#rrggbb`) runs the canvas width between
+//! groups, and `--folder-separator-label` writes the folder name on it.
+
+use memmap2::MmapMut;
+use std::cmp;
+use std::path::PathBuf;
+use tempfile::tempfile;
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use crate::summary::RunSummary;
+use crate::text;
+
+/// Parses `--folder-separator` as `PX` or `PX:COLOR`. An explicit
+/// colour is returned as `Some`; a bare `PX` leaves the colour to the
+/// renderer (the --palette folder colour when one is configured, grey
+/// otherwise).
+pub fn parse(spec: &str) -> error::Result<(u32, Option<[u8; 4]>)> {
+    let bad = || {
+        Error::Usage(format!(
+            "invalid --folder-separator {:?}; expected PX or PX:#rrggbb",
+            spec
+        ))
+    };
+    let (px, color) = match spec.split_once(':') {
+        Some((px, color)) => (px, Some(color)),
+        None => (spec, None),
+    };
+    let px: u32 = px.trim().parse().map_err(|_| bad())?;
+    if px == 0 {
+        return Err(bad());
+    }
+    let color = match color {
+        Some(color) => Some(crate::background::parse_color(color.trim()).ok_or_else(bad)?),
+        None => None,
+    };
+    Ok((px, color))
+}
+
+/// Renders the separated grid to `output_path`.
+pub fn create_separated(
+    entries: &[ManifestEntry],
+    args: &crate::Args,
+    output_path: &str,
+    run: &mut RunSummary,
+) -> error::Result<()> {
+    if entries.is_empty() {
+        return Err(Error::NoImages);
+    }
+    let spec = args.folder_separator.as_deref().expect("checked by the dispatcher");
+    let (sep_px, sep_color) = parse(spec)?;
+    let cell_size = args.cell_size;
+    let scale = cmp::max(1, cell_size / 200);
+    // A labeled band grows to hold the text; an unlabeled one stays at
+    // exactly the requested thickness.
+    let band_height = if args.folder_separator_label {
+        cmp::max(sep_px, text::line_height(scale) + 2 * scale)
+    } else {
+        sep_px
+    };
+
+    // Group by parent folder, keeping the scan order of both the
+    // folders and the images inside them. With --merge-folders the key
+    // is the folder's base name, so a merged album stays one section.
+    let mut groups: Vec<(PathBuf, Vec<&ManifestEntry>)> = Vec::new();
+    for entry in entries {
+        let folder = entry.path.parent().map(PathBuf::from).unwrap_or_default();
+        let folder = match args.merge_folders {
+            Some(_) => folder.file_name().map(PathBuf::from).unwrap_or(folder),
+            None => folder,
+        };
+        match groups.last_mut() {
+            Some((last, group)) if *last == folder => group.push(entry),
+            _ => groups.push((folder, vec![entry])),
+        }
+    }
+
+    let ncols = args
+        .columns
+        .unwrap_or_else(|| cmp::max(1, (entries.len() as f64).sqrt().ceil() as u32));
+    let width = ncols * cell_size;
+    let mut height = 0u32;
+    for (i, (_, group)) in groups.iter().enumerate() {
+        if i > 0 {
+            height += band_height;
+        }
+        height += (group.len() as u32).div_ceil(ncols) * cell_size;
+    }
+    tracing::debug!(
+        "separated layout: {} folders, {} images, canvas {}x{} px",
+        groups.len(), entries.len(), width, height
+    );
+    run.total_images = entries.len();
+    run.grid_cols = ncols;
+    run.grid_rows = groups.iter().map(|(_, g)| (g.len() as u32).div_ceil(ncols)).sum();
+    run.canvas_width = width;
+    run.canvas_height = height;
+
+    let num_pixels = (width as u64 * height as u64) as usize;
+    let file = tempfile()?;
+    file.set_len(num_pixels as u64 * 4)?;
+    crate::resources::add_temp(num_pixels as u64 * 4);
+    let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+    crate::background::fill(&mut mmap, (width, height));
+
+    let composite_start = std::time::Instant::now();
+    let mut y = 0u32;
+    for (i, (folder, group)) in groups.iter().enumerate() {
+        if i > 0 {
+            // Explicit PX:COLOR wins; a bare PX takes the folder's
+            // --palette colour (the band sits above its folder's rows,
+            // matching the label), grey without a palette.
+            let band_color = sep_color
+                .or_else(|| crate::palette::folder_color(&group[0].path))
+                .unwrap_or([128, 128, 128, 255]);
+            for row in y..y + band_height {
+                for x in 0..width {
+                    let index = ((row as u64 * width as u64 + x as u64) * 4) as usize;
+                    mmap[index..index + 4].copy_from_slice(&band_color);
+                }
+            }
+            if args.folder_separator_label {
+                let name = folder.file_name().unwrap_or_default().to_string_lossy();
+                text::draw_text(
+                    &mut mmap, (width, height),
+                    (
+                        scale as i64 * 2,
+                        y as i64 + (band_height as i64 - text::line_height(scale) as i64) / 2,
+                    ),
+                    scale, [255, 255, 255, 255], &name,
+                );
+            }
+            y += band_height;
+        }
+        for (k, entry) in group.iter().enumerate() {
+            let cell_x = (k as u32 % ncols) * cell_size;
+            let cell_y = y + (k as u32 / ncols) * cell_size;
+            match entry.load_image() {
+                Ok(img) => {
+                    crate::paste_image(
+                        &mut mmap,
+                        (width, height),
+                        (cell_x, cell_y, cell_size, cell_size),
+                        &img,
+                    );
+                }
+                Err(e) => {
+                    if args.strict || args.on_error == crate::OnError::Abort {
+                        return Err(Error::Decode(entry.path.clone(), e));
+                    }
+                    tracing::error!("Error processing {:?}: {}", entry.path, e);
+                    run.skip(&entry.path, &e);
+                    if args.on_error == crate::OnError::Placeholder {
+                        crate::draw_placeholder(
+                            &mut mmap,
+                            (width, height),
+                            (cell_x, cell_y, cell_size, cell_size),
+                            cell_size,
+                            &entry.path,
+                        );
+                    }
+                }
+            }
+        }
+        y += (group.len() as u32).div_ceil(ncols) * cell_size;
+    }
+    mmap.flush()?;
+    run.phase_seconds
+        .insert("composite".to_string(), composite_start.elapsed().as_secs_f64());
+
+    let encode_start = std::time::Instant::now();
+    crate::save_canvas(&mmap, (width, height), args, output_path)?;
+    run.phase_seconds
+        .insert("encode".to_string(), encode_start.elapsed().as_secs_f64());
+    run.output_file = output_path.to_string();
+    run.output_bytes = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!(
+        "Separated collage saved to '{}' ({} folders)",
+        output_path,
+        groups.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,227 @@
+//! HTTP server mode (`serve` subcommand).
+//!
+//! A small REST API for triggering collage builds from other machines:
+//!
+//! - `POST /jobs` with a JSON body (`{"input": "/path", "cell_size": 200}`)
+//!   or a raw zip upload queues a build and returns `{"id": n}`.
+//! - `GET /jobs/<id>` reports `queued`, `running`, `done`, `failed`, or
+//!   `cancelled`.
+//! - `GET /jobs/<id>/result` downloads the finished collage.
+//! - `DELETE /jobs/<id>` cancels a running build between images.
+
+use crate::manifest::ManifestEntry;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// One queued or finished build.
+struct Job {
+    /// queued | running | done | failed | cancelled.
+    state: Mutex<(String, Option<String>)>,
+    output: PathBuf,
+    /// Cooperative cancellation token shared with the job thread.
+    cancel: Arc<AtomicBool>,
+    /// (composited, total) images, fed by the progress observer.
+    progress: Mutex<(usize, usize)>,
+}
+
+/// Feeds build progress back into the job table for `GET /jobs/<id>`.
+struct JobObserver(Arc<Job>);
+
+impl crate::progress::ProgressObserver for JobObserver {
+    fn on_event(&self, event: crate::progress::Event<'_>) {
+        match event {
+            crate::progress::Event::Scanned(total) => {
+                *self.0.progress.lock().unwrap() = (0, total);
+            }
+            crate::progress::Event::ImageDone { index, total, path, seconds } => {
+                tracing::trace!("job progress {}/{}: {:?} in {:.0} ms", index + 1, total, path, seconds * 1000.0);
+                *self.0.progress.lock().unwrap() = (index + 1, total);
+            }
+            crate::progress::Event::Encoding => {}
+        }
+    }
+}
+
+impl Job {
+    fn set_state(&self, state: &str, error: Option<String>) {
+        *self.state.lock().unwrap() = (state.to_string(), error);
+    }
+}
+
+/// Builds the entry list for a job input: a directory root or an archive.
+fn entries_for_input(input: &str) -> Result<Vec<ManifestEntry>, String> {
+    let path = std::path::Path::new(input);
+    if crate::archive::is_archive(path) {
+        crate::archive::load_archive_entries(path).map_err(|e| e.to_string())
+    } else if path.is_dir() {
+        let (paths, _) = crate::get_sorted_image_paths(input, None, false).map_err(|e| e.to_string())?;
+        Ok(paths.into_iter().map(ManifestEntry::from_path).collect())
+    } else {
+        Err(format!("input {:?} is neither a directory nor an archive", input))
+    }
+}
+
+/// Runs one job to completion, updating its state.
+fn run_job(job: Arc<Job>, input: String, cell_size: u32) {
+    crate::cancel::set(job.cancel.clone());
+    crate::progress::set(Arc::new(JobObserver(job.clone())));
+    job.set_state("running", None);
+    let entries = match entries_for_input(&input) {
+        Ok(entries) if !entries.is_empty() => entries,
+        Ok(_) => {
+            job.set_state("failed", Some("no images found".to_string()));
+            return;
+        }
+        Err(e) => {
+            job.set_state("failed", Some(e));
+            return;
+        }
+    };
+    // Reuse the CLI defaults for everything but the requested options.
+    let args = <crate::Args as clap::Parser>::parse_from([
+        "rust_img_collage",
+        "--cell-size",
+        &cell_size.to_string(),
+        "unused",
+        "unused",
+    ]);
+    let mut run = crate::summary::RunSummary::default();
+    match crate::create_collage(&entries, &args, job.output.to_str().unwrap(), &mut run, 0) {
+        Ok(()) => job.set_state("done", None),
+        Err(crate::error::Error::Cancelled) => job.set_state("cancelled", None),
+        Err(e) => job.set_state("failed", Some(e.to_string())),
+    }
+}
+
+fn json_response(status: u16, body: serde_json::Value) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let data = body.to_string().into_bytes();
+    tiny_http::Response::from_data(data)
+        .with_status_code(status)
+        .with_header(
+            tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap(),
+        )
+}
+
+/// Runs the HTTP server until the process is killed.
+pub fn serve(port: u16) -> crate::error::Result<()> {
+    let server = tiny_http::Server::http(("0.0.0.0", port)).map_err(|e| {
+        crate::error::Error::Io(std::io::Error::other(format!(
+            "failed to bind 0.0.0.0:{}: {}",
+            port, e
+        )))
+    })?;
+    tracing::info!("Listening on http://0.0.0.0:{}", port);
+
+    let jobs: Arc<Mutex<HashMap<u64, Arc<Job>>>> = Arc::new(Mutex::new(HashMap::new()));
+    let mut next_id: u64 = 1;
+    let work_dir = std::env::temp_dir().join("image_collage_server");
+    std::fs::create_dir_all(&work_dir)?;
+
+    for mut request in server.incoming_requests() {
+        let url = request.url().to_string();
+        let method = request.method().clone();
+        let response = match (method, url.as_str()) {
+            (tiny_http::Method::Post, "/jobs") => {
+                let content_type = request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("Content-Type"))
+                    .map(|h| h.value.as_str().to_string())
+                    .unwrap_or_default();
+
+                let mut body = Vec::new();
+                let _ = request.as_reader().read_to_end(&mut body);
+
+                let (input, cell_size) = if content_type.starts_with("application/json") {
+                    match serde_json::from_slice::<serde_json::Value>(&body) {
+                        Ok(v) => (
+                            v.get("input").and_then(|s| s.as_str()).map(String::from),
+                            v.get("cell_size").and_then(|n| n.as_u64()).unwrap_or(200) as u32,
+                        ),
+                        Err(_) => (None, 200),
+                    }
+                } else {
+                    // Raw archive upload: stash it in the work dir.
+                    let upload = work_dir.join(format!("upload_{}.zip", next_id));
+                    match std::fs::write(&upload, &body) {
+                        Ok(()) => (Some(upload.to_string_lossy().into_owned()), 200),
+                        Err(_) => (None, 200),
+                    }
+                };
+
+                match input {
+                    Some(input) => {
+                        let id = next_id;
+                        next_id += 1;
+                        let job = Arc::new(Job {
+                            state: Mutex::new(("queued".to_string(), None)),
+                            output: work_dir.join(format!("job_{}.webp", id)),
+                            cancel: Arc::new(AtomicBool::new(false)),
+                            progress: Mutex::new((0, 0)),
+                        });
+                        jobs.lock().unwrap().insert(id, job.clone());
+                        std::thread::spawn(move || run_job(job, input, cell_size));
+                        json_response(202, serde_json::json!({ "id": id }))
+                    }
+                    None => json_response(400, serde_json::json!({ "error": "missing input" })),
+                }
+            }
+            (tiny_http::Method::Get, _) if url.starts_with("/jobs/") => {
+                let rest = &url["/jobs/".len()..];
+                let (id_str, want_result) = match rest.strip_suffix("/result") {
+                    Some(id) => (id, true),
+                    None => (rest, false),
+                };
+                match id_str.parse::<u64>().ok().and_then(|id| jobs.lock().unwrap().get(&id).cloned()) {
+                    Some(job) => {
+                        let (state, error) = job.state.lock().unwrap().clone();
+                        if want_result {
+                            if state == "done" {
+                                match std::fs::File::open(&job.output) {
+                                    Ok(file) => {
+                                        request.respond(tiny_http::Response::from_file(file)).ok();
+                                        continue;
+                                    }
+                                    Err(e) => json_response(
+                                        500,
+                                        serde_json::json!({ "error": e.to_string() }),
+                                    ),
+                                }
+                            } else {
+                                json_response(409, serde_json::json!({ "state": state }))
+                            }
+                        } else {
+                            let (done, total) = *job.progress.lock().unwrap();
+                            json_response(
+                                200,
+                                serde_json::json!({
+                                    "state": state,
+                                    "error": error,
+                                    "done_images": done,
+                                    "total_images": total,
+                                }),
+                            )
+                        }
+                    }
+                    None => json_response(404, serde_json::json!({ "error": "no such job" })),
+                }
+            }
+            (tiny_http::Method::Delete, _) if url.starts_with("/jobs/") => {
+                let id_str = &url["/jobs/".len()..];
+                match id_str.parse::<u64>().ok().and_then(|id| jobs.lock().unwrap().get(&id).cloned()) {
+                    Some(job) => {
+                        job.cancel.store(true, Ordering::Relaxed);
+                        let (state, _) = job.state.lock().unwrap().clone();
+                        json_response(202, serde_json::json!({ "state": state }))
+                    }
+                    None => json_response(404, serde_json::json!({ "error": "no such job" })),
+                }
+            }
+            _ => json_response(404, serde_json::json!({ "error": "not found" })),
+        };
+        request.respond(response).ok();
+    }
+    Ok(())
+}
@@ -0,0 +1,71 @@
+//! Persistent skip list of known-bad files (`--skip-list skip.txt`).
+//!
+//! Watch and scheduled runs hit the same corrupt files every time, and
+//! each retry costs a full (failing) decode. With --skip-list the files
+//! that failed to decode are appended to a plain text file, one path
+//! per line, and later runs drop them up front. `--retry-skipped`
+//! decodes them anyway and rewrites the list from scratch, so files
+//! fixed in the meantime earn their way back in.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Collection only happens when --skip-list was given; the collector is
+/// global because decode failures surface from every layout module.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static FAILED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Turns collection on, registered once from --skip-list.
+pub fn configure() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Reads the list: one path per line, blank lines and `#` comments
+/// ignored. A missing file is an empty list, so the first run needs no
+/// setup.
+pub fn load(path: &Path) -> HashSet<PathBuf> {
+    let text = std::fs::read_to_string(path).unwrap_or_default();
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Records a decode failure; a no-op unless --skip-list was given.
+pub fn note(path: &Path) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    FAILED.lock().unwrap().push(path.display().to_string());
+}
+
+/// Writes the list back: the existing entries plus this run's failures,
+/// or — with --retry-skipped — this run's failures alone, so a retry
+/// pass drops everything that decodes again.
+pub fn write(path: &Path, retry: bool) -> crate::error::Result<()> {
+    let failed = FAILED.lock().unwrap();
+    let mut lines: Vec<String> = if retry {
+        Vec::new()
+    } else {
+        load(path).iter().map(|p| p.display().to_string()).collect()
+    };
+    let mut seen: HashSet<String> = lines.iter().cloned().collect();
+    for path in failed.iter() {
+        if seen.insert(path.clone()) {
+            lines.push(path.clone());
+        }
+    }
+    lines.sort();
+    let mut text = String::new();
+    for line in &lines {
+        text.push_str(line);
+        text.push('\n');
+    }
+    std::fs::write(path, text)
+        .map_err(|e| crate::error::Error::output(&path.to_string_lossy(), e))?;
+    tracing::info!("Skip list ({} files) saved to {:?}", lines.len(), path);
+    Ok(())
+}
@@ -0,0 +1,102 @@
+//! Pluggable image acquisition for embedders.
+//!
+//! Every decode in the pipeline goes through [`read`], which resolves an
+//! entry path to its encoded bytes via the registered [`ImageSource`].
+//! The default is [`Filesystem`]; embedders register something else once
+//! per process — the same configure-once arrangement as [`crate::layout`]
+//! — to feed images from a database or object store without touching the
+//! layout or compositing code:
+//!
+//! ```ignore
+//! struct Database(Pool);
+//! impl source::ImageSource for Database {
+//!     fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> { /* ... */ }
+//! }
+//! source::set(Box::new(Database(pool)));
+//! ```
+//!
+//! Archive inputs ([`crate::archive`]) arrive with their bytes already in
+//! memory and bypass the source; `--fetch` URLs are rewritten to cache
+//! files first ([`crate::fetch`]), so they read like any other path.
+
+// Everything beyond the filesystem default is for embedders only.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Resolves an entry path to the encoded image bytes. Implementations
+/// are called from the compositing loop, one image at a time.
+pub trait ImageSource: Send + Sync {
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>>;
+}
+
+/// The default source: paths are files on disk.
+pub struct Filesystem;
+
+impl ImageSource for Filesystem {
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+}
+
+/// A fixed set of in-memory images keyed by their entry paths.
+pub struct Memory(pub HashMap<PathBuf, Vec<u8>>);
+
+impl ImageSource for Memory {
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        self.0.get(path).cloned().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no in-memory image for {:?}", path),
+            )
+        })
+    }
+}
+
+/// Fetches each path as an http(s) URL, with no cache or retries; for
+/// anything beyond a handful of images, prefer `--fetch`. Not available
+/// on wasm32 (no sockets); a browser embedder fetches itself and uses
+/// [`Memory`].
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Http;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ImageSource for Http {
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        let url = path.to_str().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "non-UTF-8 URL")
+        })?;
+        let response = ureq::get(url)
+            .call()
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let mut data = Vec::new();
+        response.into_reader().read_to_end(&mut data)?;
+        Ok(data)
+    }
+}
+
+static SOURCE: OnceLock<Box<dyn ImageSource>> = OnceLock::new();
+
+/// Registers the process-wide image source. Call before rendering
+/// starts; later calls are ignored.
+pub fn set(source: Box<dyn ImageSource>) {
+    let _ = SOURCE.set(source);
+}
+
+/// True if an embedder has registered a source; lets callers skip
+/// filesystem-only fast paths that would bypass it.
+pub fn is_custom() -> bool {
+    SOURCE.get().is_some()
+}
+
+/// Reads `path` through the registered source (filesystem by default).
+pub fn read(path: &Path) -> std::io::Result<Vec<u8>> {
+    match SOURCE.get() {
+        Some(source) => source.read(path),
+        None => Filesystem.read(path),
+    }
+}
@@ -0,0 +1,133 @@
+//! CSS sprite sheet output (`--sprite`).
+//!
+//! Packs the input images tightly at their native sizes (no forced square
+//! cells) using simple shelf packing, and writes a companion stylesheet
+//! with a `background-position` rule per input filename.
+
+use crate::manifest::ManifestEntry;
+use image::{ImageBuffer, Rgba};
+use std::fs;
+use std::path::Path;
+
+/// A packed sprite: pixel position and size on the sheet.
+struct Sprite {
+    name: String,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+/// Derives a CSS class name fragment from the entry's file stem:
+/// lowercase alphanumerics, everything else collapsed to '-'.
+fn css_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("sprite");
+    let mut name = String::new();
+    for ch in stem.chars() {
+        if ch.is_ascii_alphanumeric() {
+            name.push(ch.to_ascii_lowercase());
+        } else if !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+    name.trim_matches('-').to_string()
+}
+
+/// Builds the sprite sheet and stylesheet. The stylesheet path defaults to
+/// the output path with a `.css` extension; a `.scss` extension emits an
+/// SCSS `$sprites` map instead of plain rules.
+pub fn create_sprite_sheet(
+    entries: &[ManifestEntry],
+    output_path: &str,
+    css_path: Option<&Path>,
+) -> crate::error::Result<()> {
+    // Decode everything up front; sprite inputs are icon-sized.
+    let mut images = Vec::new();
+    for entry in entries {
+        match entry.load_image() {
+            Ok(img) => images.push((entry, img.to_rgba8())),
+            Err(e) => tracing::warn!("Error processing {:?}: {}", entry.path, e),
+        }
+    }
+    if images.is_empty() {
+        tracing::warn!("No images found!");
+        return Ok(());
+    }
+
+    // Shelf packing: aim for a roughly square sheet, filling rows
+    // left-to-right in input order.
+    let total_area: u64 = images.iter().map(|(_, im)| (im.width() * im.height()) as u64).sum();
+    let max_w = images.iter().map(|(_, im)| im.width()).max().unwrap();
+    let target_width = std::cmp::max((total_area as f64).sqrt().ceil() as u32, max_w);
+
+    let mut sprites = Vec::with_capacity(images.len());
+    let (mut pen_x, mut pen_y, mut row_h, mut sheet_w) = (0u32, 0u32, 0u32, 0u32);
+    for (entry, img) in &images {
+        let (w, h) = img.dimensions();
+        if pen_x + w > target_width {
+            pen_y += row_h;
+            pen_x = 0;
+            row_h = 0;
+        }
+        sprites.push(Sprite {
+            name: css_name(&entry.path),
+            x: pen_x,
+            y: pen_y,
+            w,
+            h,
+        });
+        pen_x += w;
+        row_h = std::cmp::max(row_h, h);
+        sheet_w = std::cmp::max(sheet_w, pen_x);
+    }
+    let sheet_h = pen_y + row_h;
+
+    // Composite the sheet.
+    let mut sheet = ImageBuffer::<Rgba<u8>, Vec<u8>>::new(sheet_w, sheet_h);
+    for (sprite, (_, img)) in sprites.iter().zip(images.iter()) {
+        image::imageops::overlay(&mut sheet, img, sprite.x as i64, sprite.y as i64);
+    }
+    sheet.save(output_path)?;
+    tracing::info!("Sprite sheet saved to '{}'", output_path);
+
+    // Companion stylesheet.
+    let default_css = match output_path.rsplit_once('.') {
+        Some((stem, _)) => format!("{}.css", stem),
+        None => format!("{}.css", output_path),
+    };
+    let css_path = css_path
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| default_css.into());
+    let sheet_file = Path::new(output_path)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or(output_path);
+
+    let scss = css_path.extension().and_then(|s| s.to_str()) == Some("scss");
+    let mut out = String::new();
+    if scss {
+        // An SCSS map: name -> (x, y, width, height).
+        out.push_str("$sprites: (\n");
+        for s in &sprites {
+            out.push_str(&format!(
+                "  \"{}\": ({}px, {}px, {}px, {}px),\n",
+                s.name, s.x, s.y, s.w, s.h
+            ));
+        }
+        out.push_str(");\n");
+    } else {
+        for s in &sprites {
+            out.push_str(&format!(
+                ".sprite-{} {{ background: url({}) -{}px -{}px no-repeat; width: {}px; height: {}px; }}\n",
+                s.name, sheet_file, s.x, s.y, s.w, s.h
+            ));
+        }
+    }
+    fs::write(&css_path, out)
+        .map_err(|e| crate::error::Error::output(&css_path.to_string_lossy(), e))?;
+    tracing::info!("Stylesheet saved to {:?}", css_path);
+    Ok(())
+}
@@ -0,0 +1,70 @@
+//! Machine-readable run reporting: `--summary out.json` and
+//! `--log-format json`.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Log output style for the tracing subscriber.
+#[derive(Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines (the default).
+    #[default]
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+/// A file that was skipped, and why.
+#[derive(Serialize, Clone)]
+pub struct Skipped {
+    pub path: String,
+    pub reason: String,
+}
+
+/// Everything a CI pipeline needs to know about one run, written as JSON
+/// by `--summary`.
+#[derive(Serialize, Default, Clone)]
+pub struct RunSummary {
+    /// Number of images that went into the collage.
+    pub total_images: usize,
+    /// Final canvas size in pixels.
+    pub canvas_width: u32,
+    pub canvas_height: u32,
+    /// Grid size in cells.
+    pub grid_cols: u32,
+    pub grid_rows: u32,
+    /// Files skipped or substituted, with reasons.
+    pub skipped: Vec<Skipped>,
+    /// Wall-clock seconds spent in each phase.
+    pub phase_seconds: BTreeMap<String, f64>,
+    /// Output file and its encoded size in bytes.
+    pub output_file: String,
+    pub output_bytes: u64,
+}
+
+impl RunSummary {
+    /// Records a skipped file, forwarding it to the --report collector.
+    pub fn skip(&mut self, path: &Path, reason: impl ToString) {
+        let reason = reason.to_string();
+        crate::report::note(path, &reason);
+        crate::skiplist::note(path);
+        self.skipped.push(Skipped {
+            path: path.display().to_string(),
+            reason,
+        });
+    }
+
+    /// Writes the summary as pretty JSON.
+    pub fn write(&self, path: &Path) -> crate::error::Result<()> {
+        // Under --deterministic the wall-clock timings are dropped so
+        // the summary itself snapshot-tests cleanly.
+        if crate::date::deterministic() && !self.phase_seconds.is_empty() {
+            let mut scrubbed = self.clone();
+            scrubbed.phase_seconds.clear();
+            return scrubbed.write(path);
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self).unwrap())
+            .map_err(|e| crate::error::Error::output(&path.to_string_lossy(), e))
+    }
+}
@@ -0,0 +1,324 @@
+//! Declarative poster layouts (`--layout-file layout.toml`).
+//!
+//! The file declares a cell grid and named areas, CSS-grid style: each
+//! area pins a position and span and says (via a glob pattern, matched
+//! like --order) which image fills it. Areas are filled in file order,
+//! each taking the first not-yet-used matching entry:
+//!
+//! ```toml
+//! cols = 4
+//! rows = 3
+//!
+//! [[area]]
+//! name = "hero"
+//! col = 0
+//! row = 0
+//! span_w = 2
+//! span_h = 2
+//! pattern = "hero*"
+//!
+//! [[area]]
+//! col = 2
+//! row = 0
+//! # pattern defaults to "*": next unused image
+//! ```
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use crate::summary::RunSummary;
+use memmap2::MmapMut;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tempfile::tempfile;
+
+#[derive(Deserialize)]
+struct LayoutFile {
+    /// Grid size in cells.
+    cols: u32,
+    rows: u32,
+    #[serde(default, rename = "area")]
+    areas: Vec<Area>,
+}
+
+fn default_span() -> u32 {
+    1
+}
+
+#[derive(Deserialize)]
+struct Area {
+    /// Only used in diagnostics.
+    #[serde(default)]
+    name: Option<String>,
+    col: u32,
+    row: u32,
+    #[serde(default = "default_span")]
+    span_w: u32,
+    #[serde(default = "default_span")]
+    span_h: u32,
+    /// Glob choosing the image; defaults to the next unused entry.
+    #[serde(default)]
+    pattern: Option<String>,
+}
+
+impl Area {
+    fn label(&self, index: usize) -> String {
+        self.name.clone().unwrap_or_else(|| format!("#{}", index))
+    }
+}
+
+/// Picks the first unused entry matching the area's pattern.
+fn pick_entry<'a>(
+    entries: &'a [ManifestEntry],
+    used: &mut [bool],
+    pattern: Option<&str>,
+) -> Option<&'a ManifestEntry> {
+    let index = entries.iter().enumerate().position(|(i, entry)| {
+        if used[i] {
+            return false;
+        }
+        let Some(pattern) = pattern else {
+            return true;
+        };
+        let path = entry.path.to_string_lossy();
+        let name = entry
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        crate::glob_match(pattern, &path)
+            || crate::glob_match(pattern, &name)
+            || crate::glob_match(&format!("*/{}", pattern), &path)
+    })?;
+    used[index] = true;
+    Some(&entries[index])
+}
+
+/// The geometry round-trip of --export-layout / --import-layout: the
+/// grid size and one rectangle per placed image, as JSON so a few
+/// positions or swaps can be hand-edited between runs.
+#[derive(Serialize, Deserialize)]
+struct Geometry {
+    cols: u32,
+    rows: u32,
+    cells: Vec<GeometryCell>,
+}
+
+/// One placed image: its source path and grid rectangle.
+#[derive(Serialize, Deserialize)]
+pub struct GeometryCell {
+    pub path: String,
+    pub col: u32,
+    pub row: u32,
+    pub span_w: u32,
+    pub span_h: u32,
+}
+
+/// Writes the computed geometry (--export-layout).
+pub fn export_geometry(
+    path: &Path,
+    (cols, rows): (u32, u32),
+    cells: Vec<GeometryCell>,
+) -> error::Result<()> {
+    let geometry = Geometry { cols, rows, cells };
+    let text = serde_json::to_string_pretty(&geometry).expect("geometry serializes");
+    std::fs::write(path, text).map_err(|e| Error::output(&path.to_string_lossy(), e))?;
+    tracing::info!("Layout geometry saved to {:?}", path);
+    Ok(())
+}
+
+/// Renders straight from an exported (and possibly hand-edited)
+/// geometry file (--import-layout): each cell pastes the entry whose
+/// path matches, by full path first and bare filename as a fallback, so
+/// the file survives a moved library.
+pub fn create_from_geometry(
+    entries: &[ManifestEntry],
+    args: &crate::Args,
+    geometry_path: &Path,
+    output_path: &str,
+    run: &mut RunSummary,
+) -> error::Result<()> {
+    let text = std::fs::read_to_string(geometry_path)?;
+    let geometry: Geometry = serde_json::from_str(&text)
+        .map_err(|e| Error::Usage(format!("invalid layout geometry {:?}: {}", geometry_path, e)))?;
+    if geometry.cols == 0 || geometry.rows == 0 || geometry.cells.is_empty() {
+        return Err(Error::Usage(format!(
+            "layout geometry {:?} needs non-zero cols/rows and at least one cell",
+            geometry_path
+        )));
+    }
+    for cell in &geometry.cells {
+        if cell.span_w == 0
+            || cell.span_h == 0
+            || cell.col + cell.span_w > geometry.cols
+            || cell.row + cell.span_h > geometry.rows
+        {
+            return Err(Error::Usage(format!(
+                "geometry cell {:?} does not fit the {}x{} grid",
+                cell.path, geometry.cols, geometry.rows
+            )));
+        }
+    }
+
+    let cell_size = args.cell_size;
+    let width = geometry.cols * cell_size;
+    let height = geometry.rows * cell_size;
+    tracing::debug!(
+        "imported geometry: {} cells in a {}x{} grid, canvas {}x{} px",
+        geometry.cells.len(), geometry.cols, geometry.rows, width, height
+    );
+    run.grid_cols = geometry.cols;
+    run.grid_rows = geometry.rows;
+    run.canvas_width = width;
+    run.canvas_height = height;
+
+    let num_pixels = (width as u64 * height as u64) as usize;
+    let file = tempfile()?;
+    file.set_len(num_pixels as u64 * 4)?;
+    crate::resources::add_temp(num_pixels as u64 * 4);
+    let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+    crate::background::fill(&mut mmap, (width, height));
+
+    let composite_start = std::time::Instant::now();
+    for cell in &geometry.cells {
+        let entry = entries
+            .iter()
+            .find(|entry| entry.path.to_string_lossy() == cell.path)
+            .or_else(|| {
+                let name = Path::new(&cell.path).file_name();
+                entries.iter().find(|entry| entry.path.file_name() == name)
+            });
+        let Some(entry) = entry else {
+            tracing::warn!("No scanned image matches geometry cell {:?}", cell.path);
+            continue;
+        };
+        let rect = (
+            cell.col * cell_size,
+            cell.row * cell_size,
+            cell.span_w * cell_size,
+            cell.span_h * cell_size,
+        );
+        match entry.load_image() {
+            Ok(img) => {
+                crate::paste_image(&mut mmap, (width, height), rect, &img);
+                run.total_images += 1;
+            }
+            Err(e) => {
+                if args.strict || args.on_error == crate::OnError::Abort {
+                    return Err(Error::Decode(entry.path.clone(), e));
+                }
+                tracing::error!("Error processing {:?}: {}", entry.path, e);
+                run.skip(&entry.path, &e);
+                if args.on_error == crate::OnError::Placeholder {
+                    crate::draw_placeholder(&mut mmap, (width, height), rect, cell_size, &entry.path);
+                }
+            }
+        }
+    }
+    mmap.flush()?;
+    run.phase_seconds
+        .insert("composite".to_string(), composite_start.elapsed().as_secs_f64());
+
+    let encode_start = std::time::Instant::now();
+    crate::save_canvas(&mmap, (width, height), args, output_path)?;
+    run.phase_seconds
+        .insert("encode".to_string(), encode_start.elapsed().as_secs_f64());
+    run.output_file = output_path.to_string();
+    run.output_bytes = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!("Imported-geometry collage saved to '{}'", output_path);
+    Ok(())
+}
+
+/// Renders the declared layout to `output_path`.
+pub fn create_from_layout_file(
+    entries: &[ManifestEntry],
+    args: &crate::Args,
+    layout_path: &Path,
+    output_path: &str,
+    run: &mut RunSummary,
+) -> error::Result<()> {
+    let text = std::fs::read_to_string(layout_path)?;
+    let layout: LayoutFile = toml::from_str(&text)
+        .map_err(|e| Error::Usage(format!("invalid layout file {:?}: {}", layout_path, e)))?;
+    if layout.cols == 0 || layout.rows == 0 || layout.areas.is_empty() {
+        return Err(Error::Usage(format!(
+            "layout file {:?} needs non-zero cols/rows and at least one [[area]]",
+            layout_path
+        )));
+    }
+    for (i, area) in layout.areas.iter().enumerate() {
+        if area.span_w == 0
+            || area.span_h == 0
+            || area.col + area.span_w > layout.cols
+            || area.row + area.span_h > layout.rows
+        {
+            return Err(Error::Usage(format!(
+                "layout area {} does not fit the {}x{} grid",
+                area.label(i),
+                layout.cols,
+                layout.rows
+            )));
+        }
+    }
+
+    let cell_size = args.cell_size;
+    let width = layout.cols * cell_size;
+    let height = layout.rows * cell_size;
+    tracing::debug!(
+        "layout file: {} areas in a {}x{} grid, canvas {}x{} px",
+        layout.areas.len(), layout.cols, layout.rows, width, height
+    );
+    run.grid_cols = layout.cols;
+    run.grid_rows = layout.rows;
+    run.canvas_width = width;
+    run.canvas_height = height;
+
+    let num_pixels = (width as u64 * height as u64) as usize;
+    let file = tempfile()?;
+    file.set_len(num_pixels as u64 * 4)?;
+    crate::resources::add_temp(num_pixels as u64 * 4);
+    let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+    crate::background::fill(&mut mmap, (width, height));
+
+    let composite_start = std::time::Instant::now();
+    let mut used = vec![false; entries.len()];
+    for (i, area) in layout.areas.iter().enumerate() {
+        let Some(entry) = pick_entry(entries, &mut used, area.pattern.as_deref()) else {
+            tracing::warn!("No unused image matches layout area {}", area.label(i));
+            continue;
+        };
+        let rect = (
+            area.col * cell_size,
+            area.row * cell_size,
+            area.span_w * cell_size,
+            area.span_h * cell_size,
+        );
+        match entry.load_image() {
+            Ok(img) => {
+                crate::paste_image(&mut mmap, (width, height), rect, &img);
+                run.total_images += 1;
+            }
+            Err(e) => {
+                if args.strict || args.on_error == crate::OnError::Abort {
+                    return Err(Error::Decode(entry.path.clone(), e));
+                }
+                tracing::error!("Error processing {:?}: {}", entry.path, e);
+                run.skip(&entry.path, &e);
+                if args.on_error == crate::OnError::Placeholder {
+                    crate::draw_placeholder(&mut mmap, (width, height), rect, cell_size, &entry.path);
+                }
+            }
+        }
+    }
+    mmap.flush()?;
+    run.phase_seconds
+        .insert("composite".to_string(), composite_start.elapsed().as_secs_f64());
+
+    let encode_start = std::time::Instant::now();
+    crate::save_canvas(&mmap, (width, height), args, output_path)?;
+    run.phase_seconds
+        .insert("encode".to_string(), encode_start.elapsed().as_secs_f64());
+    run.output_file = output_path.to_string();
+    run.output_bytes = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!("Layout-file collage saved to '{}'", output_path);
+    Ok(())
+}
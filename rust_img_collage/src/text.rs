@@ -0,0 +1,364 @@
+//! Text rendering for captions.
+//!
+//! By default uses a classic built-in 5x7 pixel font (one byte per
+//! column, bit 0 = top row) covering printable ASCII; other characters
+//! render as '?'. With `--font` a chain of real TTF/OTF fonts is
+//! rasterized instead, each character drawn by the first font in the
+//! chain that covers it.
+
+use crate::error::{self, Error};
+
+/// Glyphs for ASCII 0x20..=0x7E, 5 column bytes each.
+#[rustfmt::skip]
+const FONT_5X7: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // '!'
+    [0x00, 0x07, 0x00, 0x07, 0x00], // '"'
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // '#'
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // '$'
+    [0x23, 0x13, 0x08, 0x64, 0x62], // '%'
+    [0x36, 0x49, 0x55, 0x22, 0x50], // '&'
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '\''
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // '('
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // ')'
+    [0x14, 0x08, 0x3E, 0x08, 0x14], // '*'
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // '+'
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ','
+    [0x08, 0x08, 0x08, 0x08, 0x08], // '-'
+    [0x00, 0x60, 0x60, 0x00, 0x00], // '.'
+    [0x20, 0x10, 0x08, 0x04, 0x02], // '/'
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // '0'
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // '1'
+    [0x42, 0x61, 0x51, 0x49, 0x46], // '2'
+    [0x21, 0x41, 0x45, 0x4B, 0x31], // '3'
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // '4'
+    [0x27, 0x45, 0x45, 0x45, 0x39], // '5'
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], // '6'
+    [0x01, 0x71, 0x09, 0x05, 0x03], // '7'
+    [0x36, 0x49, 0x49, 0x49, 0x36], // '8'
+    [0x06, 0x49, 0x49, 0x29, 0x1E], // '9'
+    [0x00, 0x36, 0x36, 0x00, 0x00], // ':'
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ';'
+    [0x08, 0x14, 0x22, 0x41, 0x00], // '<'
+    [0x14, 0x14, 0x14, 0x14, 0x14], // '='
+    [0x00, 0x41, 0x22, 0x14, 0x08], // '>'
+    [0x02, 0x01, 0x51, 0x09, 0x06], // '?'
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // '@'
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // 'A'
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // 'B'
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // 'C'
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], // 'D'
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // 'E'
+    [0x7F, 0x09, 0x09, 0x09, 0x01], // 'F'
+    [0x3E, 0x41, 0x49, 0x49, 0x7A], // 'G'
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // 'H'
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // 'I'
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // 'J'
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // 'K'
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // 'L'
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F], // 'M'
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // 'N'
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // 'O'
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // 'P'
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // 'Q'
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // 'R'
+    [0x46, 0x49, 0x49, 0x49, 0x31], // 'S'
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // 'T'
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // 'U'
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // 'V'
+    [0x3F, 0x40, 0x38, 0x40, 0x3F], // 'W'
+    [0x63, 0x14, 0x08, 0x14, 0x63], // 'X'
+    [0x07, 0x08, 0x70, 0x08, 0x07], // 'Y'
+    [0x61, 0x51, 0x49, 0x45, 0x43], // 'Z'
+    [0x00, 0x7F, 0x41, 0x41, 0x00], // '['
+    [0x02, 0x04, 0x08, 0x10, 0x20], // '\\'
+    [0x00, 0x41, 0x41, 0x7F, 0x00], // ']'
+    [0x04, 0x02, 0x01, 0x02, 0x04], // '^'
+    [0x40, 0x40, 0x40, 0x40, 0x40], // '_'
+    [0x00, 0x01, 0x02, 0x04, 0x00], // '`'
+    [0x20, 0x54, 0x54, 0x54, 0x78], // 'a'
+    [0x7F, 0x48, 0x44, 0x44, 0x38], // 'b'
+    [0x38, 0x44, 0x44, 0x44, 0x20], // 'c'
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // 'd'
+    [0x38, 0x54, 0x54, 0x54, 0x18], // 'e'
+    [0x08, 0x7E, 0x09, 0x01, 0x02], // 'f'
+    [0x0C, 0x52, 0x52, 0x52, 0x3E], // 'g'
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // 'h'
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // 'i'
+    [0x20, 0x40, 0x44, 0x3D, 0x00], // 'j'
+    [0x7F, 0x10, 0x28, 0x44, 0x00], // 'k'
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // 'l'
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // 'm'
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // 'n'
+    [0x38, 0x44, 0x44, 0x44, 0x38], // 'o'
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // 'p'
+    [0x08, 0x14, 0x14, 0x18, 0x7C], // 'q'
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // 'r'
+    [0x48, 0x54, 0x54, 0x54, 0x20], // 's'
+    [0x04, 0x3F, 0x44, 0x40, 0x20], // 't'
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // 'u'
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // 'v'
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // 'w'
+    [0x44, 0x28, 0x10, 0x28, 0x44], // 'x'
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // 'y'
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // 'z'
+    [0x00, 0x08, 0x36, 0x41, 0x00], // '{'
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // '|'
+    [0x00, 0x41, 0x36, 0x08, 0x00], // '}'
+    [0x08, 0x04, 0x08, 0x10, 0x08], // '~'
+];
+
+/// Glyph advance in pixels at scale 1 (5 columns + 1 spacing).
+pub const GLYPH_ADVANCE: u32 = 6;
+/// Line height in pixels at scale 1 (7 rows + 1 spacing).
+pub const LINE_HEIGHT: u32 = 8;
+
+/// One font of the --font chain: the fontdue rasterizer plus the raw
+/// bytes, which rustybuzz re-parses per line for shaping. The chain
+/// stack needs system font access, so wasm32 keeps the built-in font
+/// only.
+#[cfg(not(target_arch = "wasm32"))]
+struct ChainFont {
+    font: fontdue::Font,
+    data: Vec<u8>,
+}
+
+/// The --font fallback chain, with the --font-size in pixels at scale 1.
+#[cfg(not(target_arch = "wasm32"))]
+struct FontChain {
+    fonts: Vec<ChainFont>,
+    size: f32,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+static CHAIN: std::sync::OnceLock<FontChain> = std::sync::OnceLock::new();
+
+/// Resolves one --font spec to font bytes: a file path as-is, anything
+/// else as an installed family name looked up via fontdb.
+#[cfg(not(target_arch = "wasm32"))]
+fn font_bytes(spec: &str) -> error::Result<Vec<u8>> {
+    let path = std::path::Path::new(spec);
+    if path.is_file() {
+        return Ok(std::fs::read(path)?);
+    }
+    let mut db = fontdb::Database::new();
+    db.load_system_fonts();
+    let query = fontdb::Query {
+        families: &[fontdb::Family::Name(spec)],
+        ..Default::default()
+    };
+    db.query(&query)
+        .and_then(|id| db.with_face_data(id, |data, _| data.to_vec()))
+        .ok_or_else(|| {
+            Error::Usage(format!("no font file or installed font family matches {:?}", spec))
+        })
+}
+
+/// Parses and installs the --font chain; call once at startup.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn configure(specs: &[String], size: f32) -> error::Result<()> {
+    if !(size > 0.0 && size.is_finite()) {
+        return Err(Error::Usage("--font-size must be positive".to_string()));
+    }
+    let mut fonts = Vec::with_capacity(specs.len());
+    for spec in specs {
+        let data = font_bytes(spec)?;
+        let font = fontdue::Font::from_bytes(data.as_slice(), fontdue::FontSettings::default())
+            .map_err(|e| Error::Usage(format!("cannot load font {:?}: {}", spec, e)))?;
+        if rustybuzz::Face::from_slice(&data, 0).is_none() {
+            return Err(Error::Usage(format!("cannot shape with font {:?}", spec)));
+        }
+        fonts.push(ChainFont { font, data });
+    }
+    let _ = CHAIN.set(FontChain { fonts, size });
+    Ok(())
+}
+
+/// The font covering `ch`: the first in the chain with a real glyph for
+/// it, or the first font (whose fallback glyph then shows).
+#[cfg(not(target_arch = "wasm32"))]
+fn pick_font_index(chain: &FontChain, ch: char) -> usize {
+    chain
+        .fonts
+        .iter()
+        .position(|cf| cf.font.lookup_glyph_index(ch) != 0)
+        .unwrap_or(0)
+}
+
+/// One shaped glyph of a caption line: chain font index, glyph id, and
+/// its position in pixels relative to the line origin on the baseline.
+#[cfg(not(target_arch = "wasm32"))]
+struct ShapedGlyph {
+    font: usize,
+    glyph: u16,
+    x: f32,
+    y: f32,
+}
+
+/// Shapes one line: bidi-reorders it into visual runs, splits each run
+/// where font coverage changes, and runs rustybuzz over each piece so
+/// complex scripts (Arabic joining, Devanagari conjuncts, ...) come out
+/// as the right glyphs in the right order. Returns the positioned glyphs
+/// and the total advance in pixels.
+#[cfg(not(target_arch = "wasm32"))]
+fn shape_line(chain: &FontChain, text: &str, px: f32) -> (Vec<ShapedGlyph>, f32) {
+    let mut glyphs = Vec::new();
+    let mut pen = 0.0f32;
+    let bidi = unicode_bidi::BidiInfo::new(text, None);
+    let Some(paragraph) = bidi.paragraphs.first() else {
+        return (glyphs, pen);
+    };
+    let (levels, runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+    for run in runs {
+        let rtl = levels[run.start].is_rtl();
+        let run_text = &text[run];
+        // Split where font coverage changes, in logical order; an RTL
+        // run lays its pieces out right to left.
+        let mut pieces: Vec<(usize, std::ops::Range<usize>)> = Vec::new();
+        for (at, ch) in run_text.char_indices() {
+            let font = pick_font_index(chain, ch);
+            match pieces.last_mut() {
+                Some((last, range)) if *last == font => range.end = at + ch.len_utf8(),
+                _ => pieces.push((font, at..at + ch.len_utf8())),
+            }
+        }
+        if rtl {
+            pieces.reverse();
+        }
+        for (font, range) in pieces {
+            // Checked in configure, so the face always parses.
+            let Some(face) = rustybuzz::Face::from_slice(&chain.fonts[font].data, 0) else {
+                continue;
+            };
+            let scale = px / face.units_per_em() as f32;
+            let mut buffer = rustybuzz::UnicodeBuffer::new();
+            buffer.push_str(&run_text[range]);
+            buffer.set_direction(if rtl {
+                rustybuzz::Direction::RightToLeft
+            } else {
+                rustybuzz::Direction::LeftToRight
+            });
+            let shaped = rustybuzz::shape(&face, &[], buffer);
+            for (info, pos) in shaped.glyph_infos().iter().zip(shaped.glyph_positions()) {
+                glyphs.push(ShapedGlyph {
+                    font,
+                    glyph: info.glyph_id as u16,
+                    x: pen + pos.x_offset as f32 * scale,
+                    y: pos.y_offset as f32 * scale,
+                });
+                pen += pos.x_advance as f32 * scale;
+            }
+        }
+    }
+    (glyphs, pen)
+}
+
+/// Line height in pixels at the given integer scale: the chain font's
+/// own line metrics, or LINE_HEIGHT for the built-in font.
+pub fn line_height(scale: u32) -> u32 {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(chain) = CHAIN.get() {
+        let px = chain.size * scale as f32;
+        return chain.fonts[0]
+            .font
+            .horizontal_line_metrics(px)
+            .map(|m| m.new_line_size.ceil() as u32)
+            .unwrap_or_else(|| px.ceil() as u32);
+    }
+    LINE_HEIGHT * scale
+}
+
+/// Width in pixels of `text` at the given integer scale.
+pub fn text_width(text: &str, scale: u32) -> u32 {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(chain) = CHAIN.get() {
+        let px = chain.size * scale as f32;
+        return shape_line(chain, text, px).1.ceil() as u32;
+    }
+    text.chars().count() as u32 * GLYPH_ADVANCE * scale
+}
+
+/// Draws `text` with the --font chain, anti-aliased, with the baseline
+/// placed from the first font's ascent so (x, y) stays the line's
+/// top-left corner.
+#[cfg(not(target_arch = "wasm32"))]
+fn draw_text_chain(
+    chain: &FontChain,
+    buf: &mut [u8],
+    (buf_width, buf_height): (u32, u32),
+    (x, y): (i64, i64),
+    scale: u32,
+    color: [u8; 4],
+    text: &str,
+) {
+    let px = chain.size * scale as f32;
+    let ascent = chain.fonts[0]
+        .font
+        .horizontal_line_metrics(px)
+        .map(|m| m.ascent)
+        .unwrap_or(px);
+    let baseline = y + ascent.round() as i64;
+    for shaped in shape_line(chain, text, px).0 {
+        let (metrics, coverage) = chain.fonts[shaped.font].font.rasterize_indexed(shaped.glyph, px);
+        let glyph_x = x + shaped.x.round() as i64 + metrics.xmin as i64;
+        let glyph_y = baseline - shaped.y.round() as i64 - metrics.ymin as i64 - metrics.height as i64;
+        for (i, &cov) in coverage.iter().enumerate() {
+            if cov == 0 {
+                continue;
+            }
+            let px_x = glyph_x + (i % metrics.width) as i64;
+            let px_y = glyph_y + (i / metrics.width) as i64;
+            if px_x < 0 || px_y < 0 || px_x >= buf_width as i64 || px_y >= buf_height as i64 {
+                continue;
+            }
+            let offset = ((px_y as u64 * buf_width as u64 + px_x as u64) * 4) as usize;
+            let alpha = cov as f64 / 255.0 * color[3] as f64 / 255.0;
+            for c in 0..3 {
+                let dst = buf[offset + c] as f64;
+                buf[offset + c] = (color[c] as f64 * alpha + dst * (1.0 - alpha)).round() as u8;
+            }
+            buf[offset + 3] = buf[offset + 3].max((alpha * 255.0).round() as u8);
+        }
+    }
+}
+
+/// Draws `text` into an RGBA buffer at (x, y) (top-left of the text),
+/// clipping at the buffer edges. `scale` is an integer pixel multiplier.
+pub fn draw_text(
+    buf: &mut [u8],
+    (buf_width, buf_height): (u32, u32),
+    (x, y): (i64, i64),
+    scale: u32,
+    color: [u8; 4],
+    text: &str,
+) {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(chain) = CHAIN.get().filter(|chain| !chain.fonts.is_empty()) {
+        return draw_text_chain(chain, buf, (buf_width, buf_height), (x, y), scale, color, text);
+    }
+    let mut pen_x = x;
+    for ch in text.chars() {
+        let idx = (ch as usize).wrapping_sub(0x20);
+        let glyph = FONT_5X7.get(idx).unwrap_or(&FONT_5X7[b'?' as usize - 0x20]);
+        for (col, bits) in glyph.iter().enumerate() {
+            for row in 0..7 {
+                if bits & (1 << row) == 0 {
+                    continue;
+                }
+                // Draw a scale x scale block for this font pixel.
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let px = pen_x + (col as u32 * scale + dx) as i64;
+                        let py = y + (row * scale + dy) as i64;
+                        if px < 0 || py < 0 || px >= buf_width as i64 || py >= buf_height as i64 {
+                            continue;
+                        }
+                        let offset = ((py as u64 * buf_width as u64 + px as u64) * 4) as usize;
+                        buf[offset..offset + 4].copy_from_slice(&color);
+                    }
+                }
+            }
+        }
+        pen_x += (GLYPH_ADVANCE * scale) as i64;
+    }
+}
@@ -0,0 +1,88 @@
+//! Text-only tiles (`--text-tile`, manifest `text` column).
+//!
+//! Quotes, dates and album titles can occupy grid cells of their own:
+//! the text is word-wrapped and centred on a charcoal card rendered at
+//! cell resolution, then flows through the pipeline as an ordinary
+//! in-memory entry — spans, captions and every layout apply unchanged.
+//! A `--font` chain styles the tiles the same way it styles labels.
+
+use crate::manifest::ManifestEntry;
+use crate::text;
+use std::path::PathBuf;
+
+/// Card background; dark enough that the default white text reads.
+const CARD: [u8; 4] = [34, 34, 34, 255];
+/// Text colour.
+const INK: [u8; 4] = [235, 235, 235, 255];
+
+/// Greedily wraps `text` into lines no wider than `max_width` pixels.
+/// A single over-long word gets its own line and is left to clip.
+fn wrap(text: &str, scale: u32, max_width: u32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if line.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", line, word)
+        };
+        if text::text_width(&candidate, scale) <= max_width || line.is_empty() {
+            line = candidate;
+        } else {
+            lines.push(std::mem::take(&mut line));
+            line = word.to_string();
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Renders `content` as a (w, h) card and returns the PNG bytes.
+fn render(content: &str, (w, h): (u32, u32)) -> Vec<u8> {
+    let mut buf = vec![0u8; (w as u64 * h as u64 * 4) as usize];
+    for pixel in buf.chunks_exact_mut(4) {
+        pixel.copy_from_slice(&CARD);
+    }
+    let scale = std::cmp::max(1, w.min(h) / 200);
+    let pad = 2 * scale;
+    let lines = wrap(content, scale, w.saturating_sub(2 * pad));
+    let line_height = text::line_height(scale);
+    let block = lines.len() as u32 * line_height;
+    let top = (h as i64 - block as i64) / 2;
+    for (i, line) in lines.iter().enumerate() {
+        let tw = text::text_width(line, scale);
+        let tx = (w as i64 - tw as i64) / 2;
+        let ty = top + i as i64 * line_height as i64;
+        text::draw_text(&mut buf, (w, h), (tx, ty), scale, INK, line);
+    }
+
+    let img = image::RgbaImage::from_raw(w, h, buf).expect("card buffer sized to fit");
+    let mut bytes = Vec::new();
+    image::DynamicImage::ImageRgba8(img)
+        .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+        .expect("PNG encoding of an in-memory card");
+    bytes
+}
+
+/// A fresh text tile for `--text-tile`, sized for one cell.
+pub fn entry(content: &str, cell_size: u32) -> ManifestEntry {
+    let mut entry = ManifestEntry::from_path(PathBuf::from(format!("text:{}", content)));
+    entry.data = Some(render(content, (cell_size, cell_size)));
+    entry
+}
+
+/// Converts manifest entries carrying a `text` column into rendered
+/// cards in place, honouring each entry's span.
+pub fn apply(entries: &mut [ManifestEntry], cell_size: u32) {
+    for entry in entries.iter_mut() {
+        if let Some(content) = entry.text.take() {
+            let (sw, sh) = entry.span_cells();
+            entry.data = Some(render(&content, (sw * cell_size, sh * cell_size)));
+            if entry.path.as_os_str().is_empty() {
+                entry.path = PathBuf::from(format!("text:{}", content));
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Tiled pyramid output (`--tiles dzi|iiif`) for very large collages.
+//!
+//! The deepest pyramid level is cut straight out of the memory-mapped
+//! canvas, and each shallower level is downsampled from the tiles of the
+//! level below it, so the full image is never materialized in memory.
+//! The DZI layout is what OpenSeadragon expects; the IIIF layout writes a
+//! static Image API level-0 tree with an `info.json`.
+
+use crate::error::{self, Error};
+use image::{ImageBuffer, RgbaImage};
+use std::fs;
+use std::path::PathBuf;
+
+/// Tile edge length in pixels.
+const TILE_SIZE: u32 = 256;
+
+/// The DZI/IIIF level count: `ceil(log2(max_dim))` halvings get the long
+/// edge down to one pixel, with no duplicate level when it is an exact
+/// power of two.
+fn max_level(full_w: u32, full_h: u32) -> u32 {
+    match full_w.max(full_h) {
+        0 | 1 => 0,
+        max_dim => 32 - (max_dim - 1).leading_zeros(),
+    }
+}
+
+/// Dimensions of a pyramid level (`shift` halvings below full resolution).
+fn level_dims(full_w: u32, full_h: u32, shift: u32) -> (u32, u32) {
+    (
+        ((full_w as u64 + (1 << shift) - 1) >> shift).max(1) as u32,
+        ((full_h as u64 + (1 << shift) - 1) >> shift).max(1) as u32,
+    )
+}
+
+/// Copies a region of the RGBA canvas into an owned image.
+fn extract_region(canvas: &[u8], canvas_w: u32, x: u32, y: u32, w: u32, h: u32) -> RgbaImage {
+    let mut out: RgbaImage = ImageBuffer::new(w, h);
+    let buf: &mut [u8] = out.as_mut();
+    for row in 0..h {
+        let src = (((y + row) as u64 * canvas_w as u64 + x as u64) * 4) as usize;
+        let dst = (row as u64 * w as u64 * 4) as usize;
+        buf[dst..dst + (w * 4) as usize]
+            .copy_from_slice(&canvas[src..src + (w * 4) as usize]);
+    }
+    out
+}
+
+/// Writes one level of the pyramid. The deepest level reads from the
+/// canvas; other levels are built by halving the four child tiles of the
+/// level below (already on disk), loaded via `child_path`.
+fn write_level(
+    canvas: Option<(&[u8], u32)>,
+    level_w: u32,
+    level_h: u32,
+    tile_path: impl Fn(u32, u32) -> PathBuf,
+    child_path: impl Fn(u32, u32) -> PathBuf,
+) -> error::Result<()> {
+    let cols = level_w.div_ceil(TILE_SIZE);
+    let rows = level_h.div_ceil(TILE_SIZE);
+    for row in 0..rows {
+        for col in 0..cols {
+            let x = col * TILE_SIZE;
+            let y = row * TILE_SIZE;
+            let w = TILE_SIZE.min(level_w - x);
+            let h = TILE_SIZE.min(level_h - y);
+            let tile = match canvas {
+                Some((canvas, canvas_w)) => extract_region(canvas, canvas_w, x, y, w, h),
+                None => {
+                    // Stitch up to four child tiles and halve them.
+                    let mut stitched: RgbaImage = ImageBuffer::new(w * 2, h * 2);
+                    for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                        let child = child_path(col * 2 + dx, row * 2 + dy);
+                        if child.is_file() {
+                            if let Ok(img) = image::open(&child) {
+                                image::imageops::overlay(
+                                    &mut stitched,
+                                    &img.to_rgba8(),
+                                    (dx * TILE_SIZE) as i64,
+                                    (dy * TILE_SIZE) as i64,
+                                );
+                            }
+                        }
+                    }
+                    image::imageops::resize(
+                        &stitched,
+                        w,
+                        h,
+                        image::imageops::FilterType::Triangle,
+                    )
+                }
+            };
+            let path = tile_path(col, row);
+            fs::create_dir_all(path.parent().unwrap())
+                .map_err(|e| Error::output(&path.to_string_lossy(), e))?;
+            tile.save(&path)
+                .map_err(|e| Error::output(&path.to_string_lossy(), e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes a Deep Zoom (DZI) pyramid: `{stem}.dzi` plus `{stem}_files/`.
+pub fn write_dzi(canvas: &[u8], full_w: u32, full_h: u32, output_path: &str) -> error::Result<()> {
+    let stem = output_path
+        .rsplit_once('.')
+        .map(|(s, _)| s.to_string())
+        .unwrap_or_else(|| output_path.to_string());
+    let files_dir = PathBuf::from(format!("{}_files", stem));
+    let max_level = max_level(full_w, full_h);
+
+    for level in (0..=max_level).rev() {
+        let shift = max_level - level;
+        let (lw, lh) = level_dims(full_w, full_h, shift);
+        let level_dir = files_dir.join(level.to_string());
+        let child_dir = files_dir.join((level + 1).to_string());
+        write_level(
+            (shift == 0).then_some((canvas, full_w)),
+            lw,
+            lh,
+            |c, r| level_dir.join(format!("{}_{}.png", c, r)),
+            |c, r| child_dir.join(format!("{}_{}.png", c, r)),
+        )?;
+    }
+
+    let dzi = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <Image xmlns=\"http://schemas.microsoft.com/deepzoom/2008\" \
+         TileSize=\"{}\" Overlap=\"0\" Format=\"png\">\n\
+         \x20 <Size Width=\"{}\" Height=\"{}\"/>\n\
+         </Image>\n",
+        TILE_SIZE, full_w, full_h
+    );
+    let dzi_path = format!("{}.dzi", stem);
+    fs::write(&dzi_path, dzi).map_err(|e| Error::output(&dzi_path, e))?;
+    tracing::info!("DZI pyramid saved to '{}' ({} levels)", dzi_path, max_level + 1);
+    Ok(())
+}
+
+/// Writes a static IIIF Image API (level 0) tree: `{stem}/info.json` plus
+/// region/size tile directories.
+pub fn write_iiif(canvas: &[u8], full_w: u32, full_h: u32, output_path: &str) -> error::Result<()> {
+    let stem = output_path
+        .rsplit_once('.')
+        .map(|(s, _)| s.to_string())
+        .unwrap_or_else(|| output_path.to_string());
+    let root = PathBuf::from(&stem);
+    let max_level = max_level(full_w, full_h);
+
+    // IIIF tiles are addressed by full-resolution region; each scale factor
+    // is an independent pass over the canvas, deepest first so shallower
+    // levels can reuse the children already on disk.
+    let mut scale_factors = Vec::new();
+    for level in (0..=max_level).rev() {
+        let shift = max_level - level;
+        let scale: u32 = 1 << shift;
+        scale_factors.push(scale);
+        let (lw, lh) = level_dims(full_w, full_h, shift);
+        let tile_path = |c: u32, r: u32| {
+            let x = c * TILE_SIZE * scale;
+            let y = r * TILE_SIZE * scale;
+            let w = (TILE_SIZE * scale).min(full_w - x);
+            let h = (TILE_SIZE * scale).min(full_h - y);
+            let tw = TILE_SIZE.min(lw - c * TILE_SIZE);
+            root.join(format!("{},{},{},{}", x, y, w, h))
+                .join(format!("{},", tw))
+                .join("0")
+                .join("default.png")
+        };
+        let prev_scale = scale / 2;
+        let child_path = |c: u32, r: u32| {
+            let (pw, ph) = level_dims(full_w, full_h, shift - 1);
+            if c * TILE_SIZE >= pw || r * TILE_SIZE >= ph {
+                return PathBuf::new();
+            }
+            let x = c * TILE_SIZE * prev_scale;
+            let y = r * TILE_SIZE * prev_scale;
+            let w = (TILE_SIZE * prev_scale).min(full_w - x);
+            let h = (TILE_SIZE * prev_scale).min(full_h - y);
+            let tw = TILE_SIZE.min(pw - c * TILE_SIZE);
+            root.join(format!("{},{},{},{}", x, y, w, h))
+                .join(format!("{},", tw))
+                .join("0")
+                .join("default.png")
+        };
+        write_level(
+            (shift == 0).then_some((canvas, full_w)),
+            lw,
+            lh,
+            tile_path,
+            child_path,
+        )?;
+    }
+
+    let info = serde_json::json!({
+        "@context": "http://iiif.io/api/image/2/context.json",
+        "@id": stem,
+        "protocol": "http://iiif.io/api/image",
+        "profile": ["http://iiif.io/api/image/2/level0.json"],
+        "width": full_w,
+        "height": full_h,
+        "tiles": [{ "width": TILE_SIZE, "scaleFactors": scale_factors }],
+    });
+    let info_path = root.join("info.json");
+    fs::create_dir_all(&root).map_err(|e| Error::output(&info_path.to_string_lossy(), e))?;
+    fs::write(&info_path, serde_json::to_string_pretty(&info).unwrap())
+        .map_err(|e| Error::output(&info_path.to_string_lossy(), e))?;
+    tracing::info!("IIIF pyramid saved to {:?} ({} levels)", root, max_level + 1);
+    Ok(())
+}
@@ -0,0 +1,153 @@
+//! Timeline layout (`--layout timeline`): images grouped by capture day,
+//! each group laid out in rows beneath its own date band — an automatic
+//! photo-journal page. Dates come from EXIF when present, mtime otherwise;
+//! undated images collect in a final "undated" group.
+
+use crate::date::{self, Day};
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use crate::summary::RunSummary;
+use crate::text;
+use memmap2::MmapMut;
+use std::cmp;
+use std::collections::BTreeMap;
+use tempfile::tempfile;
+
+/// Formats a group heading at the grouping granularity, like
+/// `2024-07-15`, `2024-07` or `2024`; a --date-format overrides the
+/// ISO default (the truncated fields render as zeros at coarser
+/// granularities, so month formats should stick to `%B %Y` and the
+/// like).
+fn group_label(day: Option<Day>, group_by: crate::GroupBy, format: Option<&str>) -> String {
+    match day {
+        Some((y, m, d)) => match format {
+            Some(format) => date::format_day((y, m, d), format),
+            None => match group_by {
+                crate::GroupBy::Day => format!("{:04}-{:02}-{:02}", y, m, d),
+                crate::GroupBy::Month => format!("{:04}-{:02}", y, m),
+                crate::GroupBy::Year => format!("{:04}", y),
+            },
+        },
+        None => "undated".to_string(),
+    }
+}
+
+/// Renders the timeline page to `output_path`.
+pub fn create_timeline(
+    entries: &[ManifestEntry],
+    args: &crate::Args,
+    output_path: &str,
+    run: &mut RunSummary,
+) -> error::Result<()> {
+    if entries.is_empty() {
+        return Err(Error::NoImages);
+    }
+    let cell_size = args.cell_size;
+    let scale = cmp::max(1, cell_size / 200);
+    let band_height = text::line_height(scale) * 2;
+
+    // Bucket by capture period, chronologically; day keys are truncated
+    // to the --group-by granularity, and `(true, ..)` sorts undated last.
+    let group_by = args.group_by.unwrap_or(crate::GroupBy::Day);
+    let mut groups: BTreeMap<(bool, Day), Vec<&ManifestEntry>> = BTreeMap::new();
+    for entry in entries {
+        let key = match date::capture_day(entry) {
+            Some((y, m, d)) => (
+                false,
+                match group_by {
+                    crate::GroupBy::Day => (y, m, d),
+                    crate::GroupBy::Month => (y, m, 0),
+                    crate::GroupBy::Year => (y, 0, 0),
+                },
+            ),
+            None => (true, (0, 0, 0)),
+        };
+        groups.entry(key).or_default().push(entry);
+    }
+
+    // One column grid wide enough for the largest group to stay readable.
+    let ncols = cmp::max(1, (entries.len() as f64).sqrt().ceil() as u32);
+    let width = ncols * cell_size;
+    let mut height = 0u32;
+    for group in groups.values() {
+        height += band_height + (group.len() as u32).div_ceil(ncols) * cell_size;
+    }
+    tracing::debug!(
+        "timeline layout: {} groups, {} images, canvas {}x{} px",
+        groups.len(), entries.len(), width, height
+    );
+    run.total_images = entries.len();
+    run.grid_cols = ncols;
+    run.grid_rows = groups.values().map(|g| (g.len() as u32).div_ceil(ncols)).sum();
+    run.canvas_width = width;
+    run.canvas_height = height;
+
+    let num_pixels = (width as u64 * height as u64) as usize;
+    let file = tempfile()?;
+    file.set_len(num_pixels as u64 * 4)?;
+    crate::resources::add_temp(num_pixels as u64 * 4);
+    let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+    // Same "transparent white" background as the grid layout.
+    crate::background::fill(&mut mmap, (width, height));
+
+    let composite_start = std::time::Instant::now();
+    let mut y = 0u32;
+    for ((undated, day), group) in &groups {
+        let label = group_label(
+            if *undated { None } else { Some(*day) },
+            group_by,
+            args.date_format.as_deref(),
+        );
+        let label_y = y as i64 + (text::line_height(scale) / 2) as i64;
+        text::draw_text(
+            &mut mmap, (width, height),
+            (scale as i64 * 2, label_y),
+            scale, [0, 0, 0, 255], &label,
+        );
+        y += band_height;
+
+        for (i, entry) in group.iter().enumerate() {
+            let cell_x = (i as u32 % ncols) * cell_size;
+            let cell_y = y + (i as u32 / ncols) * cell_size;
+            match entry.load_image() {
+                Ok(img) => {
+                    crate::paste_image(
+                        &mut mmap,
+                        (width, height),
+                        (cell_x, cell_y, cell_size, cell_size),
+                        &img,
+                    );
+                }
+                Err(e) => {
+                    if args.strict || args.on_error == crate::OnError::Abort {
+                        return Err(Error::Decode(entry.path.clone(), e));
+                    }
+                    tracing::error!("Error processing {:?}: {}", entry.path, e);
+                    run.skip(&entry.path, &e);
+                    if args.on_error == crate::OnError::Placeholder {
+                        crate::draw_placeholder(
+                            &mut mmap,
+                            (width, height),
+                            (cell_x, cell_y, cell_size, cell_size),
+                            cell_size,
+                            &entry.path,
+                        );
+                    }
+                }
+            }
+        }
+        y += (group.len() as u32).div_ceil(ncols) * cell_size;
+    }
+    mmap.flush()?;
+    run.phase_seconds
+        .insert("composite".to_string(), composite_start.elapsed().as_secs_f64());
+
+    let encode_start = std::time::Instant::now();
+    crate::save_canvas(&mmap, (width, height), args, output_path)?;
+    run.phase_seconds
+        .insert("encode".to_string(), encode_start.elapsed().as_secs_f64());
+    run.output_file = output_path.to_string();
+    run.output_bytes = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!("Timeline saved to '{}' ({} groups)", output_path, groups.len());
+    Ok(())
+}
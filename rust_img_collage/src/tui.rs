@@ -0,0 +1,236 @@
+//! `tui` subcommand: interactive preview and reorder (`--features tui`).
+//!
+//! A ratatui front end over the existing pipeline: the scanned library
+//! comes up as a navigable list with a half-block pixel preview of the
+//! selected image. Favourites pin to the front of the grid, rejects
+//! drop out, the cell size nudges up and down, and `r` leaves the
+//! terminal alone again and renders through the normal pipeline with
+//! exactly those choices.
+
+use std::collections::{HashMap, HashSet};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::Frame;
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+
+/// Preview thumbnail edge in pixels; scaled to the pane when drawn.
+const THUMB: u32 = 96;
+
+/// What the user has decided so far.
+struct Session {
+    entries: Vec<ManifestEntry>,
+    selected: usize,
+    /// Indices pinned to the front, in the order they were pinned.
+    pinned: Vec<usize>,
+    /// Indices dropped from the render.
+    dropped: HashSet<usize>,
+    cell_size: u32,
+    /// Decoded preview thumbnails, keyed by entry index.
+    thumbs: HashMap<usize, Option<image::RgbaImage>>,
+}
+
+/// Runs the interactive session; on `r` the terminal is restored and
+/// the collage renders with the session's ordering and cell size.
+pub fn run_tui(args: &crate::Args, input: &str, output: &str) -> error::Result<()> {
+    use std::io::IsTerminal;
+    if !std::io::stdout().is_terminal() {
+        return Err(Error::Usage(
+            "the tui subcommand needs an interactive terminal".to_string(),
+        ));
+    }
+    let (image_paths, _) =
+        crate::get_sorted_image_paths(input, args.limit_per_folder, args.lexicographic)?;
+    if image_paths.is_empty() {
+        return Err(Error::NoImages);
+    }
+    let mut session = Session {
+        entries: image_paths.into_iter().map(ManifestEntry::from_path).collect(),
+        selected: 0,
+        pinned: Vec::new(),
+        dropped: HashSet::new(),
+        cell_size: args.cell_size,
+        thumbs: HashMap::new(),
+    };
+    let mut terminal = ratatui::init();
+    let decision = loop {
+        let draw = terminal.draw(|frame| draw_ui(frame, &mut session));
+        if let Err(e) = draw {
+            break Err(Error::Io(e));
+        }
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => break Ok(false),
+                KeyCode::Char('r') => break Ok(true),
+                KeyCode::Up | KeyCode::Char('k') => {
+                    session.selected = session.selected.saturating_sub(1);
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    session.selected = (session.selected + 1).min(session.entries.len() - 1);
+                }
+                KeyCode::Char('p') => {
+                    let i = session.selected;
+                    match session.pinned.iter().position(|&p| p == i) {
+                        Some(at) => {
+                            session.pinned.remove(at);
+                        }
+                        None => {
+                            session.dropped.remove(&i);
+                            session.pinned.push(i);
+                        }
+                    }
+                }
+                KeyCode::Char('d') => {
+                    let i = session.selected;
+                    if !session.dropped.remove(&i) {
+                        session.pinned.retain(|&p| p != i);
+                        session.dropped.insert(i);
+                    }
+                }
+                KeyCode::Char('+') | KeyCode::Char('=') => {
+                    session.cell_size = (session.cell_size + 25).min(2000);
+                }
+                KeyCode::Char('-') => {
+                    session.cell_size = session.cell_size.saturating_sub(25).max(25);
+                }
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(Error::Io(e)),
+        }
+    };
+    ratatui::restore();
+    if !decision? {
+        return Ok(());
+    }
+    // Pins first in pin order, then the survivors in scan order.
+    let mut ordered: Vec<ManifestEntry> = session
+        .pinned
+        .iter()
+        .map(|&i| session.entries[i].clone())
+        .collect();
+    ordered.extend(
+        session
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !session.dropped.contains(i) && !session.pinned.contains(i))
+            .map(|(_, entry)| entry.clone()),
+    );
+    if ordered.is_empty() {
+        return Err(Error::NoImages);
+    }
+    let mut render_args = args.clone();
+    render_args.cell_size = session.cell_size;
+    crate::render(&mut ordered, &render_args, output)
+}
+
+/// One frame: the list on the left, the preview and key help on the
+/// right.
+fn draw_ui(frame: &mut Frame, session: &mut Session) {
+    let [list_area, side] =
+        Layout::horizontal([Constraint::Percentage(45), Constraint::Percentage(55)])
+            .areas(frame.area());
+    let [preview_area, help_area] =
+        Layout::vertical([Constraint::Min(3), Constraint::Length(4)]).areas(side);
+
+    let items: Vec<ListItem> = session
+        .entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let name = entry.path.to_string_lossy();
+            let (marker, style) = if session.dropped.contains(&i) {
+                ("x ", Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT))
+            } else if session.pinned.contains(&i) {
+                ("* ", Style::default().fg(Color::Yellow))
+            } else {
+                ("  ", Style::default())
+            };
+            ListItem::new(Line::from(format!("{}{}", marker, name))).style(style)
+        })
+        .collect();
+    let kept = session.entries.len() - session.dropped.len();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            " {} images, {} kept, {} pinned ",
+            session.entries.len(),
+            kept,
+            session.pinned.len()
+        )))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(session.selected));
+    frame.render_stateful_widget(list, list_area, &mut state);
+
+    let preview = Block::default().borders(Borders::ALL).title(" preview ");
+    let inner = preview.inner(preview_area);
+    frame.render_widget(preview, preview_area);
+    draw_preview(frame, inner, session);
+
+    let help = Paragraph::new(vec![
+        Line::from("up/down move   p pin to front   d drop"),
+        Line::from(format!("+/- cell size ({} px)", session.cell_size)),
+        Line::from("r render and quit   q quit without rendering"),
+    ])
+    .block(Block::default().borders(Borders::ALL).title(" keys "));
+    frame.render_widget(help, help_area);
+}
+
+/// Paints the selected image into the pane with half blocks: each text
+/// cell carries two pixel rows, upper in the foreground colour, lower
+/// in the background.
+fn draw_preview(frame: &mut Frame, area: Rect, session: &mut Session) {
+    if area.width == 0 || area.height == 0 {
+        return;
+    }
+    let index = session.selected;
+    let thumb = session
+        .thumbs
+        .entry(index)
+        .or_insert_with(|| {
+            session.entries[index]
+                .load_image()
+                .ok()
+                .map(|img| img.thumbnail(THUMB, THUMB).to_rgba8())
+        })
+        .as_ref();
+    let Some(thumb) = thumb else {
+        frame.render_widget(Paragraph::new("(unreadable)"), area);
+        return;
+    };
+    // Fit the thumb into the pane; a text cell is roughly twice as tall
+    // as it is wide, which the half blocks already compensate for.
+    let (pane_w, pane_h) = (area.width as u32, area.height as u32 * 2);
+    let scale = (pane_w as f64 / thumb.width() as f64)
+        .min(pane_h as f64 / thumb.height() as f64)
+        .min(1.0);
+    let (draw_w, draw_h) = (
+        ((thumb.width() as f64 * scale) as u32).max(1),
+        ((thumb.height() as f64 * scale) as u32).max(1),
+    );
+    let sample = |x: u32, y: u32| {
+        let sx = (x as f64 / scale) as u32;
+        let sy = (y as f64 / scale) as u32;
+        let p = thumb.get_pixel(sx.min(thumb.width() - 1), sy.min(thumb.height() - 1));
+        Color::Rgb(p[0], p[1], p[2])
+    };
+    for y in 0..draw_h.div_ceil(2).min(area.height as u32) {
+        for x in 0..draw_w.min(area.width as u32) {
+            let Some(cell) = frame
+                .buffer_mut()
+                .cell_mut((area.x + x as u16, area.y + y as u16))
+            else {
+                continue;
+            };
+            cell.set_symbol("▀").set_fg(sample(x, y * 2));
+            if y * 2 + 1 < draw_h {
+                cell.set_bg(sample(x, y * 2 + 1));
+            }
+        }
+    }
+}
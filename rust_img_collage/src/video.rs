@@ -0,0 +1,82 @@
+//! Video input via frame extraction (`--features ffmpeg`).
+//!
+//! Mixed photo/video albums shouldn't leave holes: with the `ffmpeg`
+//! feature, video files found in the input folders contribute one
+//! representative frame each, extracted by shelling out to the `ffmpeg`
+//! binary (and `ffprobe` for durations) rather than linking the
+//! libraries — the binaries are ubiquitous and the version coupling is
+//! looser. `--video-timestamp` picks the frame: first, middle, or a
+//! number of seconds in.
+
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Which moment of a video becomes its cell image.
+#[derive(Clone, Copy)]
+pub enum Timestamp {
+    First,
+    Middle,
+    Seconds(f64),
+}
+
+static TIMESTAMP: OnceLock<Timestamp> = OnceLock::new();
+
+/// Registers the frame choice from --video-timestamp. Call once before
+/// rendering starts; later calls are ignored.
+pub fn configure(choice: Timestamp) {
+    let _ = TIMESTAMP.set(choice);
+}
+
+/// Whether this extension belongs to a container ffmpeg can open.
+pub fn is_video_ext(ext: &str) -> bool {
+    matches!(ext, "mp4" | "mov" | "mkv" | "webm" | "avi" | "m4v")
+}
+
+/// Whether the path looks like a video file.
+pub fn is_video(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| is_video_ext(&ext.to_lowercase()))
+}
+
+/// The stream duration in seconds, via ffprobe.
+fn duration_seconds(path: &Path) -> io::Result<f64> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-show_entries", "format=duration", "-of", "csv=p=0"])
+        .arg(path)
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("ffprobe failed for {:?}", path)));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .map_err(|_| io::Error::other(format!("unparseable duration for {:?}", path)))
+}
+
+/// Extracts the chosen frame as PNG bytes, decoded by the normal image
+/// pipeline afterwards.
+pub fn extract_frame(path: &Path) -> io::Result<Vec<u8>> {
+    let seconds = match TIMESTAMP.get().copied().unwrap_or(Timestamp::First) {
+        Timestamp::First => 0.0,
+        Timestamp::Middle => duration_seconds(path)? / 2.0,
+        Timestamp::Seconds(s) => s,
+    };
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-ss", &format!("{:.3}", seconds), "-i"])
+        .arg(path)
+        .args(["-frames:v", "1", "-f", "image2pipe", "-c:v", "png", "-"])
+        .output()
+        .map_err(|e| {
+            io::Error::other(format!("cannot run ffmpeg (is it installed?): {}", e))
+        })?;
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(io::Error::other(format!(
+            "ffmpeg could not extract a frame from {:?}",
+            path
+        )));
+    }
+    Ok(output.stdout)
+}
@@ -0,0 +1,170 @@
+//! Voronoi layout (`--layout voronoi`): one jittered site per image, a
+//! Voronoi cell per site, and each image cover-fitted to its cell's
+//! bounding box with a thin border between cells — an organic mosaic.
+//!
+//! Sites start on a regular grid and are jittered by the run seed, so the
+//! nearest site for any pixel is always within the surrounding 3x3 block
+//! of grid cells; that keeps the per-pixel search constant-time instead
+//! of scanning every site.
+
+use crate::error::{self, Error};
+use crate::manifest::ManifestEntry;
+use crate::summary::RunSummary;
+use memmap2::MmapMut;
+use std::cmp;
+use tempfile::tempfile;
+
+/// Renders the Voronoi mosaic to `output_path`.
+pub fn create_voronoi(
+    entries: &[ManifestEntry],
+    args: &crate::Args,
+    output_path: &str,
+    run: &mut RunSummary,
+) -> error::Result<()> {
+    if entries.is_empty() {
+        return Err(Error::NoImages);
+    }
+    let cell_size = args.cell_size;
+    let n = entries.len() as u32;
+    let ncols = cmp::max(1, (n as f64).sqrt().ceil() as u32);
+    let nrows = n.div_ceil(ncols);
+    let width = ncols * cell_size;
+    let height = nrows * cell_size;
+    tracing::debug!(
+        "voronoi layout: {} sites on a {}x{} lattice, canvas {}x{} px",
+        n, ncols, nrows, width, height
+    );
+    run.total_images = entries.len();
+    run.grid_cols = ncols;
+    run.grid_rows = nrows;
+    run.canvas_width = width;
+    run.canvas_height = height;
+
+    // Jittered sites: each stays within the middle of its lattice cell.
+    let mut state = args.seed.wrapping_mul(0x9E37_79B9_7F4A_7C15).wrapping_add(1);
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let jitter = cell_size / 3;
+    let sites: Vec<(f64, f64)> = (0..n)
+        .map(|k| {
+            let cx = (k % ncols) * cell_size + cell_size / 2;
+            let cy = (k / ncols) * cell_size + cell_size / 2;
+            let dx = (next() % (2 * jitter as u64 + 1)) as i64 - jitter as i64;
+            let dy = (next() % (2 * jitter as u64 + 1)) as i64 - jitter as i64;
+            (cx as i64 + dx, cy as i64 + dy)
+        })
+        .map(|(x, y)| (x as f64, y as f64))
+        .collect();
+
+    // Owner map: nearest site per pixel, searched over the 3x3 lattice
+    // neighbourhood.
+    let nearest = |x: u32, y: u32| -> u32 {
+        let col = x / cell_size;
+        let row = y / cell_size;
+        let mut best = 0u32;
+        let mut best_d = f64::MAX;
+        for dr in -1i64..=1 {
+            for dc in -1i64..=1 {
+                let (c, r) = (col as i64 + dc, row as i64 + dr);
+                if c < 0 || r < 0 || c >= ncols as i64 || r >= nrows as i64 {
+                    continue;
+                }
+                let k = (r as u32) * ncols + c as u32;
+                if k >= n {
+                    continue;
+                }
+                let (sx, sy) = sites[k as usize];
+                let d = (sx - x as f64).powi(2) + (sy - y as f64).powi(2);
+                if d < best_d {
+                    best_d = d;
+                    best = k;
+                }
+            }
+        }
+        best
+    };
+    let mut owner = vec![0u32; (width as u64 * height as u64) as usize];
+    let mut bounds: Vec<(u32, u32, u32, u32)> = vec![(u32::MAX, u32::MAX, 0, 0); n as usize];
+    for y in 0..height {
+        for x in 0..width {
+            let k = nearest(x, y);
+            owner[(y * width + x) as usize] = k;
+            let b = &mut bounds[k as usize];
+            b.0 = b.0.min(x);
+            b.1 = b.1.min(y);
+            b.2 = b.2.max(x);
+            b.3 = b.3.max(y);
+        }
+    }
+
+    let num_pixels = (width as u64 * height as u64) as usize;
+    let file = tempfile()?;
+    file.set_len(num_pixels as u64 * 4)?;
+    crate::resources::add_temp(num_pixels as u64 * 4);
+    let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+    crate::background::fill(&mut mmap, (width, height));
+
+    // Cover-fit each image to its cell's bounding box, writing only the
+    // pixels the cell owns.
+    let composite_start = std::time::Instant::now();
+    for (k, entry) in entries.iter().enumerate() {
+        let (x0, y0, x1, y1) = bounds[k];
+        if x0 == u32::MAX {
+            continue; // Degenerate cell (site crowded out entirely).
+        }
+        let (bw, bh) = (x1 - x0 + 1, y1 - y0 + 1);
+        let img = match entry.load_image() {
+            Ok(img) => img,
+            Err(e) => {
+                if args.strict || args.on_error == crate::OnError::Abort {
+                    return Err(Error::Decode(entry.path.clone(), e));
+                }
+                tracing::error!("Error processing {:?}: {}", entry.path, e);
+                run.skip(&entry.path, &e);
+                continue;
+            }
+        };
+        let filled = img
+            .resize_to_fill(bw, bh, image::imageops::FilterType::Lanczos3)
+            .to_rgba8();
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                if owner[(y * width + x) as usize] != k as u32 {
+                    continue;
+                }
+                let pixel = filled.get_pixel(x - x0, y - y0);
+                let index = ((y * width + x) * 4) as usize;
+                mmap[index..index + 4].copy_from_slice(&pixel.0);
+            }
+        }
+    }
+
+    // Thin dark border wherever adjacent pixels belong to different cells.
+    for y in 0..height {
+        for x in 0..width {
+            let k = owner[(y * width + x) as usize];
+            let boundary = (x + 1 < width && owner[(y * width + x + 1) as usize] != k)
+                || (y + 1 < height && owner[((y + 1) * width + x) as usize] != k);
+            if boundary {
+                let index = ((y * width + x) * 4) as usize;
+                mmap[index..index + 4].copy_from_slice(&[32, 32, 32, 255]);
+            }
+        }
+    }
+    mmap.flush()?;
+    run.phase_seconds
+        .insert("composite".to_string(), composite_start.elapsed().as_secs_f64());
+
+    let encode_start = std::time::Instant::now();
+    crate::save_canvas(&mmap, (width, height), args, output_path)?;
+    run.phase_seconds
+        .insert("encode".to_string(), encode_start.elapsed().as_secs_f64());
+    run.output_file = output_path.to_string();
+    run.output_bytes = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    tracing::info!("Voronoi mosaic saved to '{}' ({} cells)", output_path, n);
+    Ok(())
+}
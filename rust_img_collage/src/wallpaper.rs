@@ -0,0 +1,192 @@
+//! Wallpaper mode (`--wallpaper`): one collage across every monitor.
+//!
+//! After the normal render the collage is scaled to cover the combined
+//! monitor area — detected with `xrandr`, or described by `--monitors`
+//! when there is no display to ask — and cut into one file per monitor,
+//! aligned at the seams so the picture continues across bezels. The
+//! pieces reuse the --paginate naming (out-1.webp, out-2.webp, ...) in
+//! monitor order, and `--set-wallpaper` hands them to `feh` when it is
+//! installed.
+
+use std::process::Command;
+
+use crate::error::{self, Error};
+
+/// One monitor's pixel geometry within the combined desktop.
+pub struct Monitor {
+    pub x: i64,
+    pub y: i64,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Parses --monitors: `2x2560x1440` (count, then per-monitor size, side
+/// by side) or an explicit `2560x1440+0+0,1920x1080+2560+0` list.
+pub fn parse_monitors(spec: &str) -> error::Result<Vec<Monitor>> {
+    let bad = || {
+        Error::Usage(format!(
+            "invalid --monitors {:?}; expected 2x2560x1440 or 2560x1440+0+0,1920x1080+2560+0",
+            spec
+        ))
+    };
+    let parts: Vec<&str> = spec.split(',').map(str::trim).collect();
+    if parts.len() == 1 {
+        if let Some((count, size)) = parts[0].split_once('x') {
+            if let (Ok(count), Some((w, h))) = (count.parse::<u32>(), size.split_once('x')) {
+                let width: u32 = w.parse().map_err(|_| bad())?;
+                let height: u32 = h.parse().map_err(|_| bad())?;
+                if count == 0 || width == 0 || height == 0 {
+                    return Err(bad());
+                }
+                return Ok((0..count)
+                    .map(|i| Monitor {
+                        x: i as i64 * width as i64,
+                        y: 0,
+                        width,
+                        height,
+                    })
+                    .collect());
+            }
+        }
+    }
+    let mut monitors = Vec::new();
+    let mut next_x = 0i64;
+    for part in parts {
+        let (size, offset) = match part.split_once('+') {
+            Some((size, rest)) => (size, Some(rest)),
+            None => (part, None),
+        };
+        let (w, h) = size.split_once('x').ok_or_else(bad)?;
+        let width: u32 = w.parse().map_err(|_| bad())?;
+        let height: u32 = h.parse().map_err(|_| bad())?;
+        let (x, y) = match offset {
+            Some(rest) => {
+                let (x, y) = rest.split_once('+').ok_or_else(bad)?;
+                (x.parse().map_err(|_| bad())?, y.parse().map_err(|_| bad())?)
+            }
+            // Without offsets the monitors line up side by side.
+            None => (next_x, 0),
+        };
+        next_x = x + width as i64;
+        monitors.push(Monitor { x, y, width, height });
+    }
+    if monitors.is_empty() {
+        return Err(bad());
+    }
+    Ok(monitors)
+}
+
+/// Reads the connected monitors from xrandr (`WxH+X+Y` fields).
+pub fn detect() -> error::Result<Vec<Monitor>> {
+    let output = Command::new("xrandr").output().map_err(|_| {
+        Error::Usage(
+            "cannot run xrandr to detect monitors; pass --monitors instead".to_string(),
+        )
+    })?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut monitors = Vec::new();
+    for line in text.lines() {
+        if !line.contains(" connected") {
+            continue;
+        }
+        for field in line.split_whitespace() {
+            let mut dims = field.split(['x', '+']);
+            if let (Some(w), Some(h), Some(x), Some(y), None) =
+                (dims.next(), dims.next(), dims.next(), dims.next(), dims.next())
+            {
+                if let (Ok(width), Ok(height), Ok(x), Ok(y)) =
+                    (w.parse(), h.parse(), x.parse(), y.parse())
+                {
+                    monitors.push(Monitor { x, y, width, height });
+                    break;
+                }
+            }
+        }
+    }
+    if monitors.is_empty() {
+        return Err(Error::Usage(
+            "xrandr reported no connected monitors; pass --monitors instead".to_string(),
+        ));
+    }
+    Ok(monitors)
+}
+
+/// Scales the rendered collage to cover the combined area and writes
+/// one aligned crop per monitor.
+pub fn apply(args: &crate::Args) -> error::Result<()> {
+    let output = args
+        .output_file
+        .clone()
+        .or_else(|| args.input_dir.clone())
+        .ok_or_else(|| Error::Usage("missing output file argument".to_string()))?;
+    let monitors = match args.monitors.as_deref() {
+        Some(spec) => parse_monitors(spec)?,
+        None => detect()?,
+    };
+    let min_x = monitors.iter().map(|m| m.x).min().unwrap_or(0);
+    let min_y = monitors.iter().map(|m| m.y).min().unwrap_or(0);
+    let total_w = monitors
+        .iter()
+        .map(|m| (m.x - min_x) as u64 + m.width as u64)
+        .max()
+        .unwrap_or(1) as u32;
+    let total_h = monitors
+        .iter()
+        .map(|m| (m.y - min_y) as u64 + m.height as u64)
+        .max()
+        .unwrap_or(1) as u32;
+    let collage = image::open(&output).map_err(|e| Error::output(&output, e))?;
+    // Cover, not fit: scale until both axes fill, then centre-crop.
+    let scale = (total_w as f64 / collage.width() as f64)
+        .max(total_h as f64 / collage.height() as f64);
+    let scaled = collage.resize_exact(
+        (collage.width() as f64 * scale).round() as u32,
+        (collage.height() as f64 * scale).round() as u32,
+        image::imageops::FilterType::Lanczos3,
+    );
+    let crop_x = (scaled.width() - total_w) / 2;
+    let crop_y = (scaled.height() - total_h) / 2;
+    let desktop = scaled.crop_imm(crop_x, crop_y, total_w, total_h);
+    let mut pieces = Vec::with_capacity(monitors.len());
+    for (i, monitor) in monitors.iter().enumerate() {
+        let piece = desktop.crop_imm(
+            (monitor.x - min_x) as u32,
+            (monitor.y - min_y) as u32,
+            monitor.width,
+            monitor.height,
+        );
+        let piece_path = crate::page_output_path(&output, i + 1);
+        piece
+            .save(&piece_path)
+            .map_err(|e| Error::output(&piece_path, e))?;
+        tracing::info!(
+            "Wallpaper piece for monitor {} ({}x{}) saved to '{}'",
+            i + 1,
+            monitor.width,
+            monitor.height,
+            piece_path
+        );
+        pieces.push(piece_path);
+    }
+    if args.set_wallpaper {
+        set_desktop(&pieces)?;
+    }
+    Ok(())
+}
+
+/// Sets the pieces as the desktop background via feh, which assigns one
+/// file per monitor in order.
+fn set_desktop(pieces: &[String]) -> error::Result<()> {
+    let status = Command::new("feh")
+        .arg("--bg-fill")
+        .args(pieces)
+        .status()
+        .map_err(|_| {
+            Error::Usage("cannot run feh to set the wallpaper (is it installed?)".to_string())
+        })?;
+    if !status.success() {
+        return Err(Error::Usage("feh could not set the wallpaper".to_string()));
+    }
+    tracing::info!("Wallpaper set via feh");
+    Ok(())
+}
@@ -0,0 +1,105 @@
+//! In-memory grid rendering for the wasm32 build.
+//!
+//! On wasm32 there is no filesystem, no mmap, and no threads, so the
+//! native pipeline (tempfile-backed canvas, file output) is compiled out
+//! and this module is the whole surface: encoded image buffers in,
+//! encoded collage bytes out. A browser tool calls [`render`] through
+//! its bindgen layer:
+//!
+//! ```ignore
+//! let bytes = wasm::render(&buffers, &wasm::Options::default())?;
+//! ```
+//!
+//! The module is portable and compiles natively too, which is how it
+//! stays covered by the regular build.
+
+// Natively the binary renders through the full pipeline instead.
+#![allow(dead_code)]
+
+use crate::manifest::ManifestEntry;
+use std::path::PathBuf;
+
+/// Output encodings supported in the browser.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Png,
+    Webp,
+}
+
+/// Options for an in-memory render; a trimmed-down `Args`.
+pub struct Options {
+    /// Cell size in pixels.
+    pub cell_size: u32,
+    /// Grid columns; defaults to the near-square grid.
+    pub columns: Option<u32>,
+    pub format: Format,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            cell_size: 200,
+            columns: None,
+            format: Format::Png,
+        }
+    }
+}
+
+/// Renders `images` (encoded bytes, any supported format) as a plain
+/// grid and returns the encoded collage. Decode failures skip the cell,
+/// matching the native default `--on-error skip`; an error is returned
+/// only if nothing decodes or the options are unusable.
+pub fn render(images: &[Vec<u8>], options: &Options) -> Result<Vec<u8>, String> {
+    if images.is_empty() {
+        return Err("no images".to_string());
+    }
+    if options.cell_size == 0 || options.columns == Some(0) {
+        return Err("cell_size and columns must be non-zero".to_string());
+    }
+    let cell_size = options.cell_size;
+    let n = images.len() as u32;
+    let ncols = options
+        .columns
+        .unwrap_or_else(|| std::cmp::max(1, (n as f64).sqrt().ceil() as u32));
+    let nrows = n.div_ceil(ncols);
+    let (width, height) = (ncols * cell_size, nrows * cell_size);
+
+    let mut buf = vec![0u8; (width as u64 * height as u64 * 4) as usize];
+    crate::background::fill(&mut buf, (width, height));
+
+    let mut decoded = 0usize;
+    for (index, bytes) in images.iter().enumerate() {
+        let entry = ManifestEntry {
+            data: Some(bytes.clone()),
+            ..ManifestEntry::from_path(PathBuf::from(format!("image-{}", index)))
+        };
+        let rect = (
+            (index as u32 % ncols) * cell_size,
+            (index as u32 / ncols) * cell_size,
+            cell_size,
+            cell_size,
+        );
+        match entry.load_image() {
+            Ok(img) => {
+                crate::paste_image(&mut buf, (width, height), rect, &img);
+                decoded += 1;
+            }
+            Err(e) => tracing::error!("Error decoding image {}: {}", index, e),
+        }
+    }
+    if decoded == 0 {
+        return Err("no image decoded".to_string());
+    }
+
+    let canvas = image::RgbaImage::from_raw(width, height, buf)
+        .expect("canvas buffer matches its dimensions");
+    let format = match options.format {
+        Format::Png => image::ImageOutputFormat::Png,
+        Format::Webp => image::ImageOutputFormat::WebP,
+    };
+    let mut out = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::ImageRgba8(canvas)
+        .write_to(&mut out, format)
+        .map_err(|e| e.to_string())?;
+    Ok(out.into_inner())
+}